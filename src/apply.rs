@@ -0,0 +1,322 @@
+use crate::{DiffLine, DiffParser, OwnedDiffLine, Side};
+
+use std::io;
+use std::io::BufRead;
+
+fn error(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// The last line of a file may lack the newline the diff gives it.
+fn same_line(original: &[u8], line: &[u8]) -> bool {
+    original == line || (!original.ends_with(b"\n") && line.strip_suffix(b"\n") == Some(original))
+}
+
+/// Where a hunk was applied, as returned by `apply()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HunkResult {
+    /// The number of lines after the position in the hunk header that the
+    /// hunk was applied at, or before it if negative.
+    pub offset: i64,
+    /// The number of context lines at each end of the hunk that were ignored
+    /// to make it match, like patch's fuzz.
+    pub fuzz: usize,
+}
+
+// The most context lines ignored at each end of a hunk, as patch's default.
+const MAX_FUZZ: usize = 2;
+
+struct PendingHunk {
+    header: String,
+    // The index of the first old line, or the line to insert before.
+    start: usize,
+    lines: Vec<OwnedDiffLine>,
+}
+
+struct Patcher<'a> {
+    lines: Vec<&'a [u8]>,
+    out: Vec<u8>,
+    pos: usize,
+    // The offset of the last hunk applied, which later ones likely share.
+    offset: i64,
+    results: Vec<HunkResult>,
+}
+
+impl Patcher<'_> {
+    // Find where `old` matches no earlier than `pos`, nearest to `expected`.
+    fn find(&self, old: &[&[u8]], expected: usize) -> Option<usize> {
+        let last = self.lines.len().checked_sub(old.len())?;
+        if self.pos > last {
+            return None;
+        }
+
+        let expected = expected.clamp(self.pos, last);
+        let matches = |at: usize| {
+            old.iter()
+                .zip(&self.lines[at..])
+                .all(|(line, orig)| same_line(orig, line))
+        };
+
+        (0..=last - self.pos)
+            .flat_map(|distance| {
+                let after = Some(expected + distance).filter(|&at| at <= last);
+                let before = expected.checked_sub(distance).filter(|&at| at >= self.pos);
+                after.into_iter().chain(before)
+            })
+            .find(|&at| matches(at))
+    }
+
+    fn apply_hunk(&mut self, hunk: PendingHunk) -> io::Result<()> {
+        let is_context = |line: &&OwnedDiffLine| matches!(line, OwnedDiffLine::Context(..));
+        let leading = hunk.lines.iter().take_while(is_context).count();
+        let trailing = hunk.lines.iter().rev().take_while(is_context).count();
+
+        for fuzz in 0..=MAX_FUZZ {
+            let skip = fuzz.min(leading);
+            let end = (hunk.lines.len() - fuzz.min(trailing)).max(skip);
+            let edits = &hunk.lines[skip..end];
+            let old = edits
+                .iter()
+                .filter_map(|line| match line {
+                    OwnedDiffLine::Context(l, _) | OwnedDiffLine::Deleted(l, _) => Some(&l[..]),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            let stated = hunk.start + skip;
+            let expected = (stated as i64 + self.offset).max(0) as usize;
+            let at = match self.find(&old, expected) {
+                Some(at) => at,
+                None => continue,
+            };
+
+            for line in &self.lines[self.pos..at] {
+                self.out.extend_from_slice(line);
+            }
+            self.pos = at;
+
+            for line in edits {
+                match line {
+                    OwnedDiffLine::Context(..) => {
+                        self.out.extend_from_slice(self.lines[self.pos]);
+                        self.pos += 1;
+                    }
+                    OwnedDiffLine::Deleted(..) => self.pos += 1,
+                    OwnedDiffLine::Inserted(l, _) => self.out.extend_from_slice(l),
+                    _ => (),
+                }
+            }
+
+            self.offset = at as i64 - stated as i64;
+            self.results.push(HunkResult {
+                offset: self.offset,
+                fuzz,
+            });
+            return Ok(());
+        }
+
+        Err(error(format!("hunk {} does not apply", hunk.header)))
+    }
+}
+
+/// Apply the hunks yielded by `parser` to `original`, returning the patched
+/// file and where each hunk was applied.
+///
+/// Every hunk is applied to `original`, so the diff should only cover one
+/// file.  Like patch, a hunk whose context and deleted lines don't match
+/// `original` where its header says is looked for nearby, and then with up
+/// to two lines of context ignored at either end.  If it can't be found, an
+/// `InvalidData` error is returned.
+pub fn apply<R: BufRead>(
+    original: &[u8],
+    parser: &mut DiffParser<R>,
+) -> io::Result<(Vec<u8>, Vec<HunkResult>)> {
+    let mut patcher = Patcher {
+        lines: original.split_inclusive(|&b| b == b'\n').collect(),
+        out: Vec::with_capacity(original.len()),
+        pos: 0,
+        offset: 0,
+        results: vec![],
+    };
+    let mut hunk = None;
+    // The newline the diff added to the new file's last line, if it has none.
+    let mut no_newline: Option<&[u8]> = None;
+
+    while let Some(line) = parser.next_line() {
+        let line = line?;
+
+        match line {
+            DiffLine::Hunk(ref info) => {
+                if let Some(hunk) = hunk.take() {
+                    patcher.apply_hunk(hunk)?;
+                }
+                // A hunk with no old lines inserts after old_line_no.
+                let start = if info.old_line_len == 0 {
+                    info.old_line_no
+                } else {
+                    info.old_line_no.saturating_sub(1)
+                };
+                hunk = Some(PendingHunk {
+                    header: info.to_string(),
+                    start: start as usize,
+                    lines: vec![],
+                });
+            }
+            DiffLine::Context(..) | DiffLine::Deleted(..) | DiffLine::Inserted(..) => {
+                if let Some(hunk) = &mut hunk {
+                    hunk.lines.push(line.to_owned());
+                }
+            }
+            // Only the last line of the new file can lack a newline.
+            // A diff with CRLF line endings gives its last line one too.
+            DiffLine::NoNewlineAtEof {
+                side: Side::New,
+                marker,
+            } => {
+                no_newline = Some(if marker.ends_with(b"\r\n") {
+                    b"\r\n"
+                } else {
+                    b"\n"
+                })
+            }
+            DiffLine::Modified(..)
+            | DiffLine::MalformedHunk { .. }
+            | DiffLine::CombinedHunk(_)
+            | DiffLine::PropHunk(_)
+            | DiffLine::ContextHunk
+            | DiffLine::EdChange(..)
+            | DiffLine::EdDelete(..)
+            | DiffLine::EdAppend(_)
+            | DiffLine::GitBinaryPatch => {
+                return Err(error(format!("can't apply \"{}\"", line)));
+            }
+            _ => (),
+        }
+    }
+
+    if let Some(hunk) = hunk {
+        patcher.apply_hunk(hunk)?;
+    }
+
+    let mut out = patcher.out;
+    for line in &patcher.lines[patcher.pos..] {
+        out.extend_from_slice(line);
+    }
+    if let Some(newline) = no_newline {
+        if out.ends_with(newline) {
+            out.truncate(out.len() - newline.len());
+        }
+    }
+
+    Ok((out, patcher.results))
+}
+
+#[test]
+fn test_apply() {
+    let original = b"one\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+    let diff = b"--- a
++++ b
+@@ -0,0 +1 @@
++zero
+@@ -2,2 +3,2 @@
+ two
+-three
++3
+@@ -6,2 +7,3 @@
+ six
+ seven
++eight
+";
+    let (patched, _) = apply(original, &mut DiffParser::new(&diff[..])).unwrap();
+    assert_eq!(
+        patched,
+        b"zero\none\ntwo\n3\nfour\nfive\nsix\nseven\neight\n".to_vec()
+    );
+
+    let diff = b"--- a
++++ b
+@@ -7 +7 @@
+-seven
++seven
+\\ No newline at end of file
+";
+    let (patched, _) = apply(original, &mut DiffParser::new(&diff[..])).unwrap();
+    assert_eq!(patched, b"one\ntwo\nthree\nfour\nfive\nsix\nseven".to_vec());
+
+    let original = b"foo\nbar";
+    let diff = b"--- a
++++ b
+@@ -1,2 +1,2 @@
+-foo
++baz
+ bar
+\\ No newline at end of file
+";
+    let (patched, _) = apply(original, &mut DiffParser::new(&diff[..])).unwrap();
+    assert_eq!(patched, b"baz\nbar".to_vec());
+}
+
+#[test]
+fn test_apply_mismatch() {
+    let original = b"one\ntwo\nthree\n";
+    let diff = b"--- a
++++ b
+@@ -1,2 +1,2 @@
+ one
+-deux
++2
+";
+    let err = apply(original, &mut DiffParser::new(&diff[..])).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "hunk @@ -1,2 +1,2 @@ does not apply");
+
+    let diff = b"--- a
++++ b
+@@ -10 +10 @@
+-ten
++10
+";
+    let err = apply(original, &mut DiffParser::new(&diff[..])).unwrap_err();
+    assert_eq!(err.to_string(), "hunk @@ -10 +10 @@ does not apply");
+}
+
+#[test]
+fn test_apply_offset() {
+    let original = b"extra\nlines\none\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+    let diff = b"--- a
++++ b
+@@ -2,2 +2,2 @@
+ two
+-three
++3
+@@ -6,2 +6,3 @@
+ six
+ seven
++eight
+";
+    let (patched, results) = apply(original, &mut DiffParser::new(&diff[..])).unwrap();
+    assert_eq!(
+        patched,
+        b"extra\nlines\none\ntwo\n3\nfour\nfive\nsix\nseven\neight\n".to_vec()
+    );
+    assert_eq!(
+        results,
+        vec![
+            HunkResult { offset: 2, fuzz: 0 },
+            HunkResult { offset: 2, fuzz: 0 },
+        ]
+    );
+
+    let original = b"one\ntwo\nthree\n";
+    let diff = b"--- a
++++ b
+@@ -1,3 +1,3 @@
+ ONE
+-two
++2
+ three
+";
+    let (patched, results) = apply(original, &mut DiffParser::new(&diff[..])).unwrap();
+    assert_eq!(patched, b"one\n2\nthree\n".to_vec());
+    assert_eq!(results, vec![HunkResult { offset: 0, fuzz: 1 }]);
+}
@@ -0,0 +1,196 @@
+use crate::{DiffLine, DiffParser};
+
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+/// A `DiffParser` reading from a tokio `AsyncBufRead`, with the `tokio`
+/// feature.
+///
+/// Lines are parsed just as they are by `DiffParser`, which does the parsing
+/// here.  Only the reading is done differently.
+pub struct AsyncDiffParser<R> {
+    inner: R,
+    parser: DiffParser<io::Empty>,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncDiffParser<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_parser(inner, DiffParser::new(io::empty()))
+    }
+
+    /// Read from `inner` with the options set on `parser`, as in
+    /// `DiffParser::new(io::empty()).strict()`.
+    pub fn with_parser(inner: R, parser: DiffParser<io::Empty>) -> Self {
+        Self { inner, parser }
+    }
+
+    /// The parser doing the parsing, for its `line_number()` and the like.
+    pub fn parser(&self) -> &DiffParser<io::Empty> {
+        &self.parser
+    }
+
+    pub async fn next_line(&mut self) -> Option<io::Result<DiffLine<'_>>> {
+        if self.parser.submodule_due {
+            self.parser.submodule_due = false;
+            return self.parser.submodule_line().map(Ok);
+        }
+
+        if self.parser.skip_preamble {
+            while self.parser.preamble {
+                if let Err(err) = self.fill().await {
+                    return Some(Err(err));
+                }
+                match self.parser.skip_preamble_line() {
+                    Ok(true) => (),
+                    Ok(false) => break,
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        }
+
+        if self.parser.filter.is_some() {
+            loop {
+                if let Err(err) = self.fill().await {
+                    return Some(Err(err));
+                }
+                match self.parser.skip_line() {
+                    Ok(true) => (),
+                    Ok(false) => break,
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        }
+
+        if let Err(err) = self.fill().await {
+            return Some(Err(err));
+        }
+        // Any line the parser peeks at to see if a file's ended is read here.
+        if let Some(event) = self.parser.take_file_event() {
+            return self.parser.event_line(event).map(Ok);
+        }
+        self.parser.parse_line()
+    }
+
+    // Give the parser the next line to read, if it's going to read one.  At
+    // the end of input it's left to find nothing to read itself.
+    async fn fill(&mut self) -> io::Result<()> {
+        if self.parser.reparse || self.parser.unread.is_some() {
+            return Ok(());
+        }
+
+        let mut line = vec![];
+        let terminator = self.parser.terminator;
+        let read = match self.parser.max_line_len {
+            Some(max) => {
+                (&mut self.inner)
+                    .take(max as u64 + 1)
+                    .read_until(terminator, &mut line)
+                    .await?
+            }
+            None => self.inner.read_until(terminator, &mut line).await?,
+        };
+        let too_long = self.parser.is_too_long(&line);
+        if read > 0 {
+            self.parser.unread = Some(line);
+        }
+
+        // The parser finds the line too long, and the rest is skipped here.
+        if too_long {
+            loop {
+                let buf = self.inner.fill_buf().await?;
+                let (len, done) = match buf.iter().position(|&b| b == terminator) {
+                    Some(pos) => (pos + 1, true),
+                    None => (buf.len(), buf.is_empty()),
+                };
+                self.inner.consume(len);
+                self.parser.byte_offset += len as u64;
+                if done {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_async_parser() {
+    let diff = b"junk
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+--- a/bar
++++ b/bar
+@@ -1 +1 @@
+-bar
++baz
+";
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let lines = runtime.block_on(async {
+        let mut parser = AsyncDiffParser::new(&diff[..]);
+        let mut lines = vec![];
+        while let Some(line) = parser.next_line().await {
+            lines.push(line.unwrap().to_owned());
+        }
+        lines
+    });
+    let sync = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines, sync);
+
+    let lines = runtime.block_on(async {
+        let options = DiffParser::new(io::empty()).file_events();
+        let mut parser = AsyncDiffParser::with_parser(&diff[..], options);
+        let mut lines = vec![];
+        while let Some(line) = parser.next_line().await {
+            lines.push(line.unwrap().to_owned());
+        }
+        lines
+    });
+    let sync = DiffParser::new(&diff[..])
+        .file_events()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines, sync);
+    assert_eq!(lines.len(), 15);
+
+    let lines = runtime.block_on(async {
+        let options = DiffParser::new(io::empty())
+            .strip_prefix(1)
+            .filter_paths(vec![b"bar".to_vec()]);
+        let mut parser = AsyncDiffParser::with_parser(&diff[..], options);
+        let mut lines = vec![];
+        while let Some(line) = parser.next_line().await {
+            lines.push(line.unwrap().to_string());
+        }
+        assert_eq!(parser.parser().line_number(), 11);
+        lines
+    });
+    assert_eq!(lines[0], "--- bar");
+    assert_eq!(lines.len(), 5);
+
+    let lines = runtime.block_on(async {
+        let options = DiffParser::new(io::empty()).max_line_len(10);
+        let mut parser = AsyncDiffParser::with_parser(&diff[..], options);
+        let mut lines = vec![];
+        while let Some(line) = parser.next_line().await {
+            lines.push(line.map(|line| line.to_string()).map_err(|e| e.to_string()));
+        }
+        assert_eq!(parser.parser().byte_offset(), diff.len() as u64);
+        lines
+    });
+    assert_eq!(lines[2], Ok("+++ b/foo".to_string()));
+    assert_eq!(
+        lines[3],
+        Err("line 4: line too long, found \"@@ -1 +1 @@\"".to_string())
+    );
+    assert_eq!(lines[4], Ok("-foo".to_string()));
+}
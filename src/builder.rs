@@ -0,0 +1,206 @@
+use crate::DiffParser;
+
+use std::io::BufRead;
+
+/// The options for a `DiffParser`, to set up once and build parsers from.
+/// Each option is set as by the `DiffParser` method of the same name.
+///
+/// ```
+/// use diffparser::{DiffLine, DiffParserBuilder};
+///
+/// let builder = DiffParserBuilder::new().strip_prefix(1).strict();
+/// let mut parser = builder.build(&b"--- a/foo\n+++ b/foo\n"[..]);
+///
+/// match parser.next_line() {
+///     Some(Ok(DiffLine::OldFile(fi))) => assert_eq!(&fi.filename[..], b"foo"),
+///     line => panic!("unexpected {:?}", line),
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiffParserBuilder {
+    strip: usize,
+    strict: bool,
+    terminator: u8,
+    max_line_len: Option<usize>,
+    verbatim: bool,
+    file_events: bool,
+    tab_context: bool,
+    spaced_ranges: bool,
+    unspaced_headers: bool,
+    skip_preamble: bool,
+    context_format: bool,
+    ed_format: bool,
+    stats: bool,
+    paths: Option<Vec<Vec<u8>>>,
+}
+
+impl Default for DiffParserBuilder {
+    fn default() -> Self {
+        Self {
+            strip: 0,
+            strict: false,
+            terminator: b'\n',
+            max_line_len: None,
+            verbatim: false,
+            file_events: false,
+            tab_context: false,
+            spaced_ranges: false,
+            unspaced_headers: false,
+            skip_preamble: false,
+            context_format: false,
+            ed_format: false,
+            stats: false,
+            paths: None,
+        }
+    }
+}
+
+impl DiffParserBuilder {
+    /// The options `DiffParser::new()` uses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn strip_prefix(mut self, levels: usize) -> Self {
+        self.strip = levels;
+        self
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    pub fn with_terminator(mut self, byte: u8) -> Self {
+        crate::check_terminator(byte);
+        self.terminator = byte;
+        self
+    }
+
+    pub fn max_line_len(mut self, bytes: usize) -> Self {
+        self.max_line_len = Some(bytes);
+        self
+    }
+
+    pub fn verbatim(mut self) -> Self {
+        self.verbatim = true;
+        self
+    }
+
+    pub fn file_events(mut self) -> Self {
+        self.file_events = true;
+        self
+    }
+
+    pub fn tab_context(mut self) -> Self {
+        self.tab_context = true;
+        self
+    }
+
+    pub fn spaced_ranges(mut self) -> Self {
+        self.spaced_ranges = true;
+        self
+    }
+
+    pub fn unspaced_headers(mut self) -> Self {
+        self.unspaced_headers = true;
+        self
+    }
+
+    pub fn skip_preamble(mut self) -> Self {
+        self.skip_preamble = true;
+        self
+    }
+
+    pub fn context_format(mut self) -> Self {
+        self.context_format = true;
+        self
+    }
+
+    pub fn ed_format(mut self) -> Self {
+        self.ed_format = true;
+        self
+    }
+
+    pub fn with_stats(mut self) -> Self {
+        self.stats = true;
+        self
+    }
+
+    pub fn filter_paths(mut self, paths: Vec<Vec<u8>>) -> Self {
+        self.paths = Some(paths);
+        self
+    }
+
+    /// A parser reading from `inner` with these options.
+    pub fn build<R: BufRead>(&self, inner: R) -> DiffParser<R> {
+        let mut parser = DiffParser::new(inner)
+            .strip_prefix(self.strip)
+            .with_terminator(self.terminator);
+        if let Some(bytes) = self.max_line_len {
+            parser = parser.max_line_len(bytes);
+        }
+        if self.strict {
+            parser = parser.strict();
+        }
+        if self.verbatim {
+            parser = parser.verbatim();
+        }
+        if self.file_events {
+            parser = parser.file_events();
+        }
+        if self.tab_context {
+            parser = parser.tab_context();
+        }
+        if self.spaced_ranges {
+            parser = parser.spaced_ranges();
+        }
+        if self.unspaced_headers {
+            parser = parser.unspaced_headers();
+        }
+        if self.skip_preamble {
+            parser = parser.skip_preamble();
+        }
+        if self.context_format {
+            parser = parser.context_format();
+        }
+        if self.ed_format {
+            parser = parser.ed_format();
+        }
+        if self.stats {
+            parser = parser.with_stats();
+        }
+        if let Some(paths) = &self.paths {
+            parser = parser.filter_paths(paths.clone());
+        }
+        parser
+    }
+}
+
+#[test]
+fn test_builder() {
+    let diff = b"From: someone\n\n--- a/foo\n+++ b/foo\n@@ -1,2 +1,2 @@\n\tctx\n-a\n+b\n--- a/bar\n+++ b/bar\n@@ -1 +1 @@\n-c\n+d\n";
+    let builder = DiffParserBuilder::new()
+        .strip_prefix(1)
+        .skip_preamble()
+        .tab_context()
+        .with_stats()
+        .filter_paths(vec![b"foo".to_vec()]);
+
+    let mut parser = builder.build(&diff[..]);
+    let mut lines = vec![];
+    while let Some(line) = parser.next_line() {
+        lines.push(line.unwrap().to_string());
+    }
+    assert_eq!(
+        lines,
+        vec!["--- foo", "+++ foo", "@@ -1,2 +1,2 @@", " ctx", "-a", "+b"]
+    );
+    assert_eq!(parser.stats().files, 1);
+
+    // The builder can be used again, and defaults to `DiffParser::new()`.
+    let lines = builder.build(&diff[..]).count();
+    assert_eq!(lines, 6);
+    let lines = DiffParserBuilder::new().build(&diff[..]).count();
+    assert_eq!(lines, DiffParser::new(&diff[..]).count());
+}
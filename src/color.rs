@@ -0,0 +1,129 @@
+use crate::{fmt_context, DiffLine};
+
+use std::fmt;
+
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[m";
+
+/// A `DiffLine` displayed with ANSI colors, returned by `DiffLine::colored()`.
+pub struct ColoredDiffLine<'a> {
+    line: &'a DiffLine<'a>,
+    enabled: bool,
+}
+
+impl<'a> DiffLine<'a> {
+    /// Display the line colored like `git diff` does: file headers in bold,
+    /// hunk headers in cyan, and deletions and insertions in red and green.
+    /// If `enabled` is false this displays exactly as the line itself does.
+    pub fn colored(&'a self, enabled: bool) -> ColoredDiffLine<'a> {
+        ColoredDiffLine {
+            line: self,
+            enabled,
+        }
+    }
+}
+
+impl fmt::Display for ColoredDiffLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.enabled {
+            return write!(f, "{}", self.line);
+        }
+
+        // Only the ranges of hunk headers are colored, not their context.
+        match self.line {
+            DiffLine::Hunk(hi) => {
+                write!(f, "{}", CYAN)?;
+                hi.fmt_ranges(f, "@@")?;
+                write!(f, "{}", RESET)?;
+                return fmt_context(f, hi.context);
+            }
+            DiffLine::PropHunk(hi) => {
+                write!(f, "{}", CYAN)?;
+                hi.fmt_ranges(f, "##")?;
+                write!(f, "{}", RESET)?;
+                return fmt_context(f, hi.context);
+            }
+            DiffLine::CombinedHunk(hi) => {
+                write!(f, "{}", CYAN)?;
+                hi.fmt_ranges(f)?;
+                write!(f, "{}", RESET)?;
+                return fmt_context(f, hi.context);
+            }
+            _ => (),
+        }
+
+        let color = match self.line {
+            DiffLine::GitHeader { .. }
+            | DiffLine::Index { .. }
+            | DiffLine::NewFileMode(_)
+            | DiffLine::DeletedFileMode(_)
+            | DiffLine::OldMode(_)
+            | DiffLine::NewMode(_)
+            | DiffLine::SimilarityIndex(_)
+            | DiffLine::RenameFrom(_)
+            | DiffLine::RenameTo(_)
+            | DiffLine::CopyFrom(_)
+            | DiffLine::CopyTo(_)
+            | DiffLine::OldFile(_)
+            | DiffLine::NewFile(_)
+            | DiffLine::SvnIndex(_)
+            | DiffLine::SvnSeparator(_)
+            | DiffLine::GitBinaryPatch => BOLD,
+            DiffLine::ContextHunk
+            | DiffLine::OldRange(..)
+            | DiffLine::NewRange(..)
+            | DiffLine::EdChange(..)
+            | DiffLine::EdDelete(..)
+            | DiffLine::EdAppend(_) => CYAN,
+            DiffLine::Inserted(..) => GREEN,
+            DiffLine::Deleted(..) => RED,
+            DiffLine::Modified(..) => YELLOW,
+            DiffLine::CombinedDelta { markers, .. } if markers.contains(&b'-') => RED,
+            DiffLine::CombinedDelta { markers, .. } if markers.contains(&b'+') => GREEN,
+            line => return write!(f, "{}", line),
+        };
+
+        write!(f, "{}{}{}", color, self.line, RESET)
+    }
+}
+
+#[test]
+fn test_colored() {
+    let colored = |diff: &[u8], enabled| {
+        let mut parser = crate::DiffParser::new(diff);
+        let mut out = vec![];
+        while let Some(line) = parser.next_line() {
+            out.push(line.unwrap().colored(enabled).to_string());
+        }
+        out
+    };
+
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1,2 @@\tfn main()\n foo\n-bar\n+baz\n";
+    assert_eq!(
+        colored(diff, true),
+        vec![
+            "\x1b[1m--- a\x1b[m",
+            "\x1b[1m+++ b\x1b[m",
+            "\x1b[36m@@ -1,2 +1,2 @@\x1b[m\tfn main()",
+            " foo",
+            "\x1b[31m-bar\x1b[m",
+            "\x1b[32m+baz\x1b[m",
+        ]
+    );
+
+    assert_eq!(
+        colored(diff, false),
+        vec![
+            "--- a",
+            "+++ b",
+            "@@ -1,2 +1,2 @@\tfn main()",
+            " foo",
+            "-bar",
+            "+baz",
+        ]
+    );
+}
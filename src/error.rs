@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseErrorKind {
+    ExpectedNewFile,
+    ExpectedHunk,
+    ExpectedHunkLine,
+    HunkTooLong,
+    UnexpectedEof,
+    LineTooLong,
+}
+
+/// A line a strict `DiffParser` couldn't make sense of, or one longer than
+/// its `max_line_len()`.
+///
+/// These are returned from `next_line()` as the inner error of an
+/// `io::ErrorKind::InvalidData` error, and can be recovered with
+/// `err.get_ref().and_then(|e| e.downcast_ref::<ParseError>())`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub line_number: u64,
+    pub line: Vec<u8>,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::ExpectedNewFile => write!(f, "expected a +++ header"),
+            ParseErrorKind::ExpectedHunk => write!(f, "expected a hunk header"),
+            ParseErrorKind::ExpectedHunkLine => write!(f, "expected a hunk line"),
+            ParseErrorKind::HunkTooLong => write!(f, "more lines than the hunk header allows"),
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of diff"),
+            ParseErrorKind::LineTooLong => write!(f, "line too long"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line_number, self.kind)?;
+        if self.kind != ParseErrorKind::UnexpectedEof {
+            write!(
+                f,
+                ", found {:?}",
+                String::from_utf8_lossy(crate::line::chomp(&self.line))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for io::Error {
+    fn from(err: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[test]
+fn test_display() {
+    let err = ParseError {
+        kind: ParseErrorKind::ExpectedHunk,
+        line_number: 3,
+        line: b"@@ -1,2 +1\n".to_vec(),
+    };
+    assert_eq!(
+        err.to_string(),
+        "line 3: expected a hunk header, found \"@@ -1,2 +1\""
+    );
+
+    let err = ParseError {
+        kind: ParseErrorKind::UnexpectedEof,
+        line_number: 5,
+        line: vec![],
+    };
+    assert_eq!(err.to_string(), "line 5: unexpected end of diff");
+
+    let err = io::Error::from(err);
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        err.get_ref()
+            .and_then(|e| e.downcast_ref::<ParseError>())
+            .map(|e| e.line_number),
+        Some(5)
+    );
+}
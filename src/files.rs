@@ -0,0 +1,741 @@
+use crate::{DiffLine, DiffParser, OwnedDiffLine, OwnedFileInfo, OwnedHunkInfo, Side, State};
+
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::iter;
+
+/// One file's part of a diff: its `---` and `+++` headers and the hunks
+/// following them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileDiff {
+    pub old: OwnedFileInfo,
+    pub new: OwnedFileInfo,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hunk {
+    pub info: OwnedHunkInfo,
+    /// The `Context`, `Inserted`, `Deleted` and `Modified` lines of the hunk,
+    /// and any `NoNewlineAtEof` between them.
+    pub lines: Vec<OwnedDiffLine>,
+}
+
+/// An iterator over the files in a diff, returned by `files()` and
+/// `DiffParser::files()`.
+///
+/// Only files with `---` and `+++` headers are yielded, and only their `@@`
+/// hunks are collected.  Anything else, like junk between files or git
+/// headers, is skipped.
+pub struct Files<R> {
+    parser: DiffParser<R>,
+    old: Option<OwnedFileInfo>,
+    file: Option<FileDiff>,
+}
+
+/// Group the lines of the diff read from `r` by file and hunk.
+pub fn files<R: BufRead>(r: R) -> Files<R> {
+    DiffParser::new(r).files()
+}
+
+/// An iterator over the `@@` hunks of one file, returned by
+/// `DiffParser::file_hunks()`.
+pub struct FileHunks<'a, R> {
+    parser: &'a mut DiffParser<R>,
+    hunk: Option<Hunk>,
+}
+
+/// A content line of a hunk, with its line numbers in the old and new files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Delta {
+    /// The line's number in the old file, unless it's `Inserted`.
+    pub old_line_no: Option<u64>,
+    /// The line's number in the new file, unless it's `Deleted`.
+    pub new_line_no: Option<u64>,
+    /// A `Context`, `Inserted`, `Deleted` or `Modified` line.
+    pub line: OwnedDiffLine,
+}
+
+/// An iterator over the content lines of the `@@` hunks in a diff, returned by
+/// `DiffParser::deltas()`.
+pub struct Deltas<R> {
+    parser: DiffParser<R>,
+}
+
+/// Split `diff` into a diff for each file, so they can be parsed separately.
+///
+/// A file starts at its `diff --git` or svn `Index:` header, or otherwise its
+/// `---` header, and runs up to the next.  Anything before the first file, like
+/// an email's preamble, is left out.
+pub fn split_files(diff: &[u8]) -> Vec<&[u8]> {
+    let mut parser = DiffParser::new(diff);
+    let mut starts = vec![];
+
+    // Whether the last file started with an svn `Index:` line and hasn't
+    // reached its `---` yet.
+    let mut in_svn_header = false;
+
+    loop {
+        let in_git_header = matches!(parser.state, State::GitHeader);
+        // Reading from a slice can't fail.
+        let starts_file = match parser.next_line() {
+            Some(Ok(DiffLine::GitHeader { .. })) => true,
+            Some(Ok(DiffLine::SvnIndex(_))) => {
+                in_svn_header = true;
+                true
+            }
+            Some(Ok(DiffLine::OldFile(_))) | Some(Ok(DiffLine::Binaries(..))) => {
+                !in_git_header && !std::mem::replace(&mut in_svn_header, false)
+            }
+            Some(_) => false,
+            None => break,
+        };
+        if starts_file {
+            starts.push(parser.byte_offset() as usize);
+        }
+    }
+
+    let ends = starts.iter().skip(1).copied().chain(iter::once(diff.len()));
+    starts
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| &diff[start..end])
+        .collect()
+}
+
+/// The context and inserted lines of the file named `path` in the diff read
+/// from `r`, joined together: the new file as far as its hunks show it.
+/// `path` is compared with the filenames in the `---` and `+++` headers.
+pub fn new_side<R: BufRead>(r: R, path: &[u8]) -> io::Result<Vec<u8>> {
+    side_text(r, path, Side::New)
+}
+
+/// The context and deleted lines of the file named `path`, as for
+/// `new_side()`.
+pub fn old_side<R: BufRead>(r: R, path: &[u8]) -> io::Result<Vec<u8>> {
+    side_text(r, path, Side::Old)
+}
+
+fn side_text<R: BufRead>(r: R, path: &[u8], side: Side) -> io::Result<Vec<u8>> {
+    let mut text = vec![];
+
+    for file in files(r) {
+        let file = file?;
+        if &file.old.filename[..] != path && &file.new.filename[..] != path {
+            continue;
+        }
+
+        for line in file.hunks.iter().flat_map(|hunk| &hunk.lines) {
+            match line {
+                OwnedDiffLine::Context(l, _) => text.extend_from_slice(l),
+                OwnedDiffLine::Inserted(l, _) if side == Side::New => text.extend_from_slice(l),
+                OwnedDiffLine::Deleted(l, _) if side == Side::Old => text.extend_from_slice(l),
+                OwnedDiffLine::NoNewlineAtEof { side: s, .. }
+                    if (*s == side || *s == Side::Both) && text.ends_with(b"\n") =>
+                {
+                    text.pop();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    Ok(text)
+}
+
+#[test]
+fn test_sides() {
+    let diff = b"--- a/foo
++++ b/foo
+@@ -1,3 +1,3 @@
+ one
+-two
++2
+ three
+@@ -9,2 +9,2 @@
+ nine
+-ten
+\\ No newline at end of file
++10
+--- a/bar
++++ b/bar
+@@ -1 +1 @@
+-bar
++baz
+";
+
+    assert_eq!(
+        new_side(&diff[..], b"b/foo").unwrap(),
+        b"one\n2\nthree\nnine\n10\n".to_vec()
+    );
+    assert_eq!(
+        old_side(&diff[..], b"a/foo").unwrap(),
+        b"one\ntwo\nthree\nnine\nten".to_vec()
+    );
+    assert_eq!(new_side(&diff[..], b"b/bar").unwrap(), b"baz\n".to_vec());
+    assert!(old_side(&diff[..], b"quux").unwrap().is_empty());
+}
+
+// A 64-bit FNV-1a hash of everything written to it.
+struct Fnv(u64);
+
+impl Write for Fnv {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &b in buf {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x100_0000_01b3);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash the hunks of each file in the diff read from `r`, to tell whether
+/// the same changes have been seen before.  Files are named by their `+++`
+/// header, or `---` for deleted files, and their metadata, like timestamps,
+/// is left out.  The hash is FNV-1a, so it's the same from run to run.
+pub fn file_hashes<R: BufRead>(r: R) -> io::Result<Vec<(Vec<u8>, u64)>> {
+    let mut hashes = vec![];
+
+    for file in files(r) {
+        let file = file?;
+        let mut hash = Fnv(0xcbf2_9ce4_8422_2325);
+        for hunk in &file.hunks {
+            writeln!(hash, "{}", hunk.info)?;
+            for line in &hunk.lines {
+                line.as_diff_line().writeln(&mut hash)?;
+            }
+        }
+
+        let path = if file.new.is_dev_null() {
+            file.old.filename
+        } else {
+            file.new.filename
+        };
+        hashes.push((path, hash.0));
+    }
+
+    Ok(hashes)
+}
+
+#[test]
+fn test_file_hashes() {
+    let diff = b"--- a/foo\t2019-01-01 00:00:00
++++ b/foo\t2019-01-02 00:00:00
+@@ -1 +1 @@
+-foo
++bar
+--- a/gone\t2019-01-01 00:00:00
++++ /dev/null\t1970-01-01 00:00:00
+@@ -1 +0,0 @@
+-gone
+";
+    let hashes = file_hashes(&diff[..]).unwrap();
+    assert_eq!(hashes.len(), 2);
+    assert_eq!(hashes[0].0, b"b/foo".to_vec());
+    assert_eq!(hashes[1].0, b"a/gone".to_vec());
+    assert_ne!(hashes[0].1, hashes[1].1);
+
+    // Only the timestamps differ.
+    let retimed = b"--- a/foo\t2020-06-01 12:00:00
++++ b/foo\t2020-06-01 12:30:00
+@@ -1 +1 @@
+-foo
++bar
+--- a/gone
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+    assert_eq!(file_hashes(&retimed[..]).unwrap(), hashes);
+
+    let changed = b"--- a/foo\n+++ b/foo\n@@ -1 +1 @@\n-foo\n+baz\n";
+    assert_ne!(file_hashes(&changed[..]).unwrap()[0].1, hashes[0].1);
+    let moved = b"--- a/foo\n+++ b/foo\n@@ -2 +2 @@\n-foo\n+bar\n";
+    assert_ne!(file_hashes(&moved[..]).unwrap()[0].1, hashes[0].1);
+}
+
+/// Merge each run of hunks which abut, with no unchanged lines between them,
+/// into one hunk.  A merged hunk keeps the context of the first in its run.
+pub fn coalesce_hunks(hunks: &[Hunk]) -> Vec<Hunk> {
+    // The first line of a side of a hunk, and the line after it.  A side with
+    // no lines is at the line its changes come after.
+    let span = |line_no: u64, line_len: u64| {
+        let start = if line_len == 0 { line_no + 1 } else { line_no };
+        (start, start + line_len)
+    };
+
+    let mut merged: Vec<Hunk> = vec![];
+    for hunk in hunks {
+        if let Some(last) = merged.last_mut() {
+            let (last_old, next_old) = span(last.info.old_line_no, last.info.old_line_len);
+            let (last_new, next_new) = span(last.info.new_line_no, last.info.new_line_len);
+            let (old, _) = span(hunk.info.old_line_no, hunk.info.old_line_len);
+            let (new, _) = span(hunk.info.new_line_no, hunk.info.new_line_len);
+
+            if (old, new) == (next_old, next_new) {
+                let info = &mut last.info;
+                info.old_line_len += hunk.info.old_line_len;
+                info.new_line_len += hunk.info.new_line_len;
+                if info.old_line_len > 0 {
+                    info.old_line_no = last_old;
+                }
+                if info.new_line_len > 0 {
+                    info.new_line_no = last_new;
+                }
+                last.lines.extend_from_slice(&hunk.lines);
+                continue;
+            }
+        }
+
+        merged.push(hunk.clone());
+    }
+
+    merged
+}
+
+#[test]
+fn test_coalesce_hunks() {
+    let hunk = |old_line_no, old_line_len, new_line_no, new_line_len, lines: &[&str]| Hunk {
+        info: OwnedHunkInfo {
+            old_line_no,
+            old_line_len,
+            new_line_no,
+            new_line_len,
+            context: None,
+        },
+        lines: lines
+            .iter()
+            .map(|line| crate::line::parse_delta(line.as_bytes()).to_owned())
+            .collect(),
+    };
+
+    // Abutting hunks, including an insertion after the end of the first.
+    let hunks = [
+        hunk(1, 2, 1, 1, &[" a\n", "-b\n"]),
+        hunk(3, 1, 2, 2, &["-c\n", "+C\n", "+D\n"]),
+        hunk(3, 0, 4, 1, &["+e\n"]),
+    ];
+    assert_eq!(
+        coalesce_hunks(&hunks),
+        [hunk(
+            1,
+            3,
+            1,
+            4,
+            &[" a\n", "-b\n", "-c\n", "+C\n", "+D\n", "+e\n"]
+        )]
+    );
+
+    // An insertion at the start of a file, followed by a change to its first
+    // line.
+    let hunks = [
+        hunk(0, 0, 1, 1, &["+a\n"]),
+        hunk(1, 1, 2, 1, &["-b\n", "+B\n"]),
+    ];
+    assert_eq!(
+        coalesce_hunks(&hunks),
+        [hunk(1, 1, 1, 2, &["+a\n", "-b\n", "+B\n"])]
+    );
+
+    // An unchanged line lies between these, and the sides don't agree on
+    // whether the second pair abut.
+    let hunks = [
+        hunk(1, 1, 1, 1, &["-a\n", "+A\n"]),
+        hunk(3, 1, 3, 1, &["-c\n", "+C\n"]),
+        hunk(4, 1, 5, 1, &["-d\n", "+D\n"]),
+    ];
+    assert_eq!(coalesce_hunks(&hunks), hunks);
+    assert_eq!(coalesce_hunks(&[]), []);
+}
+
+impl<R: BufRead> DiffParser<R> {
+    /// Group the lines from this parser by file and hunk.
+    pub fn files(self) -> Files<R> {
+        Files {
+            parser: self,
+            old: None,
+            file: None,
+        }
+    }
+
+    /// Skip everything but the content lines of `@@` hunks, numbering each
+    /// as it's counted from its hunk's header.
+    pub fn deltas(self) -> Deltas<R> {
+        Deltas { parser: self }
+    }
+
+    /// Collect the hunks of the current file, with the parser just past its
+    /// `+++` header.  This stops before the next file starts, which is left
+    /// for `next_line()`.
+    pub fn file_hunks(&mut self) -> FileHunks<'_, R> {
+        FileHunks {
+            parser: self,
+            hunk: None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for FileHunks<'_, R> {
+    type Item = io::Result<Hunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.parser.peek() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => {
+                    // A peeked line is only read when it's consumed.
+                    self.parser.next_line();
+                    return Some(Err(e));
+                }
+                None => return self.hunk.take().map(Ok),
+            };
+
+            match line {
+                // The next hunk is left to start the next call.
+                DiffLine::Hunk(_) if self.hunk.is_some() => return self.hunk.take().map(Ok),
+                DiffLine::Hunk(ref hi) => {
+                    self.hunk = Some(Hunk {
+                        info: hi.into(),
+                        lines: vec![],
+                    })
+                }
+                DiffLine::Context(..)
+                | DiffLine::Inserted(..)
+                | DiffLine::Deleted(..)
+                | DiffLine::Modified(..)
+                | DiffLine::NoNewlineAtEof { .. } => {
+                    if let Some(hunk) = &mut self.hunk {
+                        hunk.lines.push(line.to_owned());
+                    }
+                }
+                DiffLine::GitHeader { .. }
+                | DiffLine::OldFile(_)
+                | DiffLine::NewFile(_)
+                | DiffLine::Binaries(..)
+                | DiffLine::OnlyIn { .. }
+                | DiffLine::FilesDiffer(..)
+                | DiffLine::SvnIndex(_)
+                | DiffLine::PropertyChanges(_) => return self.hunk.take().map(Ok),
+                _ => (),
+            }
+
+            self.parser.next_line();
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Files<R> {
+    type Item = io::Result<FileDiff>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(line) = self.parser.next_line() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+
+            match line {
+                DiffLine::OldFile(ref fi) => self.old = Some(fi.into()),
+                DiffLine::NewFile(ref fi) => {
+                    if let Some(old) = self.old.take() {
+                        let file = FileDiff {
+                            old,
+                            new: fi.into(),
+                            hunks: vec![],
+                        };
+                        if let Some(done) = self.file.replace(file) {
+                            return Some(Ok(done));
+                        }
+                    }
+                }
+                DiffLine::Hunk(ref hi) => {
+                    if let Some(file) = &mut self.file {
+                        file.hunks.push(Hunk {
+                            info: hi.into(),
+                            lines: vec![],
+                        });
+                    }
+                }
+                DiffLine::Context(..)
+                | DiffLine::Inserted(..)
+                | DiffLine::Deleted(..)
+                | DiffLine::Modified(..)
+                | DiffLine::NoNewlineAtEof { .. } => {
+                    if let Some(hunk) = self.file.as_mut().and_then(|f| f.hunks.last_mut()) {
+                        hunk.lines.push(line.to_owned());
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        self.file.take().map(Ok)
+    }
+}
+
+impl<R: BufRead> Iterator for Deltas<R> {
+    type Item = io::Result<Delta>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(line) = self.parser.next_line() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let line = match line {
+                DiffLine::Context(..)
+                | DiffLine::Inserted(..)
+                | DiffLine::Deleted(..)
+                | DiffLine::Modified(..) => line.to_owned(),
+                _ => continue,
+            };
+            let (old, new) = match self.parser.current_line_numbers() {
+                Some(numbers) => numbers,
+                None => continue,
+            };
+
+            return Some(Ok(Delta {
+                old_line_no: Some(old).filter(|_| !matches!(line, OwnedDiffLine::Inserted(..))),
+                new_line_no: Some(new).filter(|_| !matches!(line, OwnedDiffLine::Deleted(..))),
+                line,
+            }));
+        }
+
+        None
+    }
+}
+
+#[test]
+fn test_files() {
+    let diff = b"diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+@@ -10,2 +10 @@
+ baz
+-qux
+some junk
+--- bar
++++ bar
+@@ -1 +1 @@
+-a
+\\ No newline at end of file
++b
+";
+
+    let files = files(&diff[..])
+        .collect::<io::Result<Vec<_>>>()
+        .expect("read error");
+
+    let fi = |name: &[u8]| OwnedFileInfo {
+        filename: name.to_vec(),
+        metadata: None,
+    };
+    let hi = |old_line_no, old_line_len, new_line_no, new_line_len| OwnedHunkInfo {
+        old_line_no,
+        old_line_len,
+        new_line_no,
+        new_line_len,
+        context: None,
+    };
+
+    assert_eq!(
+        files,
+        vec![
+            FileDiff {
+                old: fi(b"a/foo"),
+                new: fi(b"b/foo"),
+                hunks: vec![
+                    Hunk {
+                        info: hi(1, 1, 1, 1),
+                        lines: vec![
+                            OwnedDiffLine::Deleted(b"foo\n".to_vec(), 1),
+                            OwnedDiffLine::Inserted(b"bar\n".to_vec(), 1),
+                        ],
+                    },
+                    Hunk {
+                        info: hi(10, 2, 10, 1),
+                        lines: vec![
+                            OwnedDiffLine::Context(b"baz\n".to_vec(), 1),
+                            OwnedDiffLine::Deleted(b"qux\n".to_vec(), 1),
+                        ],
+                    },
+                ],
+            },
+            FileDiff {
+                old: fi(b"bar"),
+                new: fi(b"bar"),
+                hunks: vec![Hunk {
+                    info: hi(1, 1, 1, 1),
+                    lines: vec![
+                        OwnedDiffLine::Deleted(b"a\n".to_vec(), 1),
+                        OwnedDiffLine::NoNewlineAtEof {
+                            side: crate::Side::Old,
+                            marker: b"\\ No newline at end of file\n".to_vec(),
+                        },
+                        OwnedDiffLine::Inserted(b"b\n".to_vec(), 1),
+                    ],
+                }],
+            },
+        ]
+    );
+
+    let files = DiffParser::new(&diff[..])
+        .strip_prefix(1)
+        .files()
+        .map(|file| file.unwrap().new.filename)
+        .collect::<Vec<_>>();
+    assert_eq!(files, vec![b"foo".to_vec(), b"bar".to_vec()]);
+}
+
+#[test]
+fn test_split_files() {
+    let diff = b"From 1234567 Mon Sep 17 00:00:00 2001
+Subject: [PATCH] Change things
+
+diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1,2 +1 @@
+-foo
+--- not a header
+diff --git a/img.png b/img.png
+index 1234567..89abcde 100644
+Binary files a/img.png and b/img.png differ
+--- bar
++++ bar
+@@ -1 +1 @@
+-a
++b
+";
+
+    let files = split_files(diff);
+    assert_eq!(files.len(), 3);
+    assert!(files[0].starts_with(b"diff --git a/foo b/foo\n"));
+    assert!(files[0].ends_with(b"--- not a header\n"));
+    assert!(files[1].starts_with(b"diff --git a/img.png"));
+    assert!(files[1].ends_with(b"differ\n"));
+    assert!(files[2].starts_with(b"--- bar\n"));
+    // Only the preamble is left out.
+    let preamble = diff.windows(4).position(|w| w == b"diff").unwrap();
+    assert_eq!(files.concat(), &diff[preamble..]);
+
+    assert!(split_files(b"just junk\n").is_empty());
+}
+
+#[test]
+fn test_file_hunks() {
+    let diff = b"--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+@@ -10,2 +10 @@
+ baz
+-qux
+@@ -20 +19,2 @@
+ quux
++corge
+\\ No newline at end of file
+--- a/bar
++++ b/bar
+@@ -1 +1 @@
+-a
++b
+";
+
+    let mut parser = DiffParser::new(&diff[..]);
+    for _ in 0..2 {
+        parser.next_line().unwrap().unwrap();
+    }
+
+    let hunks = parser
+        .file_hunks()
+        .collect::<io::Result<Vec<_>>>()
+        .expect("read error");
+    assert_eq!(hunks.len(), 3);
+    assert_eq!(hunks[0].info.old_line_no, 1);
+    assert_eq!(hunks[1].lines.len(), 2);
+    assert_eq!(hunks[2].info.new_line_len, 2);
+    assert_eq!(
+        hunks[2].lines[1],
+        OwnedDiffLine::Inserted(b"corge\n".to_vec(), 1)
+    );
+    assert!(matches!(
+        hunks[2].lines[2],
+        OwnedDiffLine::NoNewlineAtEof { .. }
+    ));
+
+    assert!(matches!(
+        parser.next_line().unwrap().unwrap(),
+        DiffLine::OldFile(_)
+    ));
+
+    // An error moves past the line it's for.
+    let diff = b"--- a/foo\n+++ b/foo\n@@ -1,2 +1,2 @@\n-foo\ngarbage\n+bar\n";
+    let mut parser = DiffParser::new(&diff[..]).strict();
+    for _ in 0..2 {
+        parser.next_line().unwrap().unwrap();
+    }
+    let hunks = parser.file_hunks().take(3).collect::<Vec<_>>();
+    assert_eq!(hunks.len(), 2);
+    assert!(hunks[0].is_err());
+    assert!(hunks[1].is_ok());
+}
+
+#[test]
+fn test_deltas() {
+    let diff = b"diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1,3 +1,3 @@
+ one
+-two
++2
+ three
+@@ -10,3 +10,4 @@
+ ten
++10.5
++10.75
+-eleven
+ twelve
+--- a/bar
++++ b/bar
+@@ -5,0 +6 @@
++six
+";
+
+    let deltas = DiffParser::new(&diff[..])
+        .deltas()
+        .map(|delta| {
+            let delta = delta.unwrap();
+            (delta.old_line_no, delta.new_line_no, delta.line.to_string())
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        deltas,
+        vec![
+            (Some(1), Some(1), " one".to_string()),
+            (Some(2), None, "-two".to_string()),
+            (None, Some(2), "+2".to_string()),
+            (Some(3), Some(3), " three".to_string()),
+            (Some(10), Some(10), " ten".to_string()),
+            (None, Some(11), "+10.5".to_string()),
+            (None, Some(12), "+10.75".to_string()),
+            (Some(11), None, "-eleven".to_string()),
+            (Some(12), Some(13), " twelve".to_string()),
+            (None, Some(6), "+six".to_string()),
+        ]
+    );
+}
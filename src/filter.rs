@@ -0,0 +1,251 @@
+use crate::line::strip_path;
+use crate::{DiffLine, DiffParser, State};
+
+use std::io;
+use std::io::BufRead;
+
+pub(crate) struct PathFilter {
+    paths: Vec<Vec<u8>>,
+    matching: bool,
+    // A `---` line for a file which might still match by its `+++` line, and
+    // the parser state from before it.
+    holding: bool,
+    held_state: State,
+    held: Vec<u8>,
+}
+
+impl PathFilter {
+    fn matches(&self, path: &[u8]) -> bool {
+        self.paths.iter().any(|p| p == path)
+    }
+}
+
+enum Action {
+    Keep,
+    Skip,
+    Hold,
+    Release,
+}
+
+impl<R: BufRead> DiffParser<R> {
+    /// Only return the lines of files with an old or new path in `paths`,
+    /// skipping everything else.  Paths are compared after `strip_prefix()`,
+    /// including those in `diff --git` headers.
+    pub fn filter_paths(mut self, paths: Vec<Vec<u8>>) -> Self {
+        self.filter = Some(PathFilter {
+            paths,
+            matching: false,
+            holding: false,
+            held_state: State::Junk,
+            held: vec![],
+        });
+        self
+    }
+
+    // Parse a line, returning whether it's filtered out.  Lines to be kept
+    // are set to be parsed again by `parse_line()` so they can be returned.
+    pub(crate) fn skip_line(&mut self) -> io::Result<bool> {
+        let (state, after_delta) = (self.state, self.after_delta);
+        // Lines are counted when they're returned.
+        let (stats, junk_count, in_junk) = (self.stats.clone(), self.junk_count, self.in_junk);
+        let file_events = self.file_events.clone();
+        let submodule = (self.subproject.clone(), self.submodule_due);
+        let strip = self.strip;
+        let mut filter = self.filter.take().expect("no filter");
+        let holding = std::mem::replace(&mut filter.holding, false);
+
+        let action = match self.parse_line() {
+            Some(Ok(line)) => match line {
+                DiffLine::GitHeader { old_path, new_path } => {
+                    filter.matching = filter.matches(strip_path(old_path, strip))
+                        || filter.matches(strip_path(new_path, strip));
+                    if filter.matching {
+                        Action::Keep
+                    } else {
+                        Action::Skip
+                    }
+                }
+                DiffLine::SvnIndex(path) => {
+                    filter.matching = filter.matches(strip_path(path, strip));
+                    if filter.matching {
+                        Action::Keep
+                    } else {
+                        Action::Skip
+                    }
+                }
+                DiffLine::OldFile(_) if matches!(state, State::GitHeader) => {
+                    if filter.matching {
+                        Action::Keep
+                    } else {
+                        Action::Skip
+                    }
+                }
+                DiffLine::OldFile(fi) => {
+                    filter.matching = filter.matches(&fi.filename);
+                    if filter.matching {
+                        Action::Keep
+                    } else {
+                        Action::Hold
+                    }
+                }
+                DiffLine::Binaries(old, new) | DiffLine::FilesDiffer(old, new) => {
+                    filter.matching = filter.matches(strip_path(old, strip))
+                        || filter.matches(strip_path(new, strip));
+                    if filter.matching {
+                        Action::Keep
+                    } else {
+                        Action::Skip
+                    }
+                }
+                DiffLine::OnlyIn { dir, file } => {
+                    let mut path = dir.strip_suffix(b"/").unwrap_or(dir).to_vec();
+                    path.push(b'/');
+                    path.extend_from_slice(file);
+                    filter.matching = filter.matches(strip_path(&path, strip));
+                    if filter.matching {
+                        Action::Keep
+                    } else {
+                        Action::Skip
+                    }
+                }
+                DiffLine::NewFile(fi) if holding && filter.matches(&fi.filename) => {
+                    filter.matching = true;
+                    Action::Release
+                }
+                _ if filter.matching => Action::Keep,
+                _ => Action::Skip,
+            },
+            // Let parse_line() find the end of input again.
+            None => Action::Keep,
+            Some(Err(err)) => {
+                self.filter = Some(filter);
+                return Err(err);
+            }
+        };
+
+        let skip = match action {
+            Action::Keep => {
+                self.state = state;
+                self.after_delta = after_delta;
+                self.reparse = !self.line.is_empty();
+                false
+            }
+            Action::Skip => true,
+            Action::Hold => {
+                filter.holding = true;
+                filter.held_state = state;
+                filter.held.clear();
+                filter.held.extend_from_slice(&self.line);
+                true
+            }
+            // Go back to the `---` line, with the `+++` line to follow.
+            Action::Release => {
+                let new = std::mem::replace(&mut self.line, std::mem::take(&mut filter.held));
+                self.unread = Some(new);
+                self.state = filter.held_state;
+                self.after_delta = None;
+                self.line_number -= 1;
+                self.byte_offset -= self.line.len() as u64;
+                self.reparse = true;
+                false
+            }
+        };
+
+        self.stats = stats;
+        self.junk_count = junk_count;
+        self.in_junk = in_junk;
+        self.file_events = file_events;
+        (self.subproject, self.submodule_due) = submodule;
+        self.filter = Some(filter);
+        Ok(skip)
+    }
+}
+
+#[test]
+fn test_filter_paths() {
+    let diff = b"junk
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+diff --git a/bar b/bar
+--- a/bar
++++ b/bar
+@@ -1 +1 @@
+-bar
++baz
+--- /dev/null
++++ b/baz
+@@ -0,0 +1 @@
++baz
+Binary files a/img and b/img differ
+Only in a: zzz
+Files a/doc and b/doc differ
+";
+
+    let filtered = |paths: &[&[u8]]| {
+        let mut parser = DiffParser::new(&diff[..])
+            .strip_prefix(1)
+            .filter_paths(paths.iter().map(|p| p.to_vec()).collect());
+        let mut lines = vec![];
+        while let Some(line) = parser.next_line() {
+            let line = line.unwrap().to_string();
+            lines.push((parser.line_number(), line));
+        }
+        lines
+    };
+
+    let lines = filtered(&[b"foo"]);
+    assert_eq!(
+        lines,
+        vec![
+            (2, "--- foo".to_string()),
+            (3, "+++ foo".to_string()),
+            (4, "@@ -1 +1 @@".to_string()),
+            (5, "-foo".to_string()),
+            (6, "+bar".to_string()),
+        ]
+    );
+
+    let lines = filtered(&[b"bar"]);
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], (7, "diff --git a/bar b/bar".to_string()));
+    assert_eq!(lines[5], (12, "+baz".to_string()));
+
+    let lines = filtered(&[b"baz"]);
+    assert_eq!(
+        lines,
+        vec![
+            (13, "--- /dev/null".to_string()),
+            (14, "+++ baz".to_string()),
+            (15, "@@ -0,0 +1 @@".to_string()),
+            (16, "+baz".to_string()),
+        ]
+    );
+
+    // These lines are files of their own, and don't follow the one before.
+    let lines = filtered(&[b"img"]);
+    assert_eq!(
+        lines,
+        vec![(17, "Binary files a/img and b/img differ".to_string())]
+    );
+    assert_eq!(
+        filtered(&[b"zzz"]),
+        vec![(18, "Only in a: zzz".to_string())]
+    );
+    assert_eq!(
+        filtered(&[b"doc"]),
+        vec![(19, "Files a/doc and b/doc differ".to_string())]
+    );
+
+    // In a git diff they're matched the same as its header.
+    let diff = b"diff --git a/img b/img\nindex 1234567..89abcde 100644\nBinary files a/img and b/img differ\n";
+    let lines = DiffParser::new(&diff[..])
+        .filter_paths(vec![b"b/img".to_vec()])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+
+    assert!(filtered(&[b"quux"]).is_empty());
+}
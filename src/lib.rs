@@ -1,58 +1,858 @@
+use std::borrow::Cow;
 use std::fmt;
+use std::ops::Range;
+use std::str;
+use std::str::Utf8Error;
 
-#[derive(Debug, PartialEq)]
+mod apply;
+#[cfg(feature = "tokio")]
+mod async_parser;
+mod builder;
+mod color;
+mod error;
+mod files;
+mod filter;
+pub mod line;
+mod owned;
+mod side_by_side;
+mod stat;
+mod writer;
+
+pub use apply::{apply, HunkResult};
+#[cfg(feature = "tokio")]
+pub use async_parser::AsyncDiffParser;
+pub use builder::DiffParserBuilder;
+pub use color::ColoredDiffLine;
+pub use error::{ParseError, ParseErrorKind};
+pub use files::{
+    coalesce_hunks, file_hashes, files, new_side, old_side, split_files, Delta, Deltas, FileDiff,
+    FileHunks, Files, Hunk,
+};
+use filter::PathFilter;
+use line::{
+    chomp, parse_binary_data, parse_binary_patch, parse_combined_delta, parse_combined_hunk,
+    parse_context_delta, parse_context_hunk, parse_context_new_file, parse_context_old_file,
+    parse_delta, parse_ed_command, parse_extended_header, parse_file_start, parse_hunk,
+    parse_hunk_info, parse_mode, parse_new_file, parse_prop_hunk, parse_property,
+    parse_spaced_hunk, parse_subproject, parse_svn_separator, parse_unspaced_new_file,
+    parse_unspaced_old_file, strip_path,
+};
+pub use owned::{OwnedCombinedHunkInfo, OwnedDiffLine, OwnedFileInfo, OwnedHunkInfo};
+pub use side_by_side::side_by_side;
+pub use stat::{changed_ranges, per_file_stats, quick_stats, DiffStat, FileStat};
+pub use writer::DiffWriter;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct FileInfo<'a> {
-    pub filename: &'a [u8],
+    /// The filename, unquoted if git quoted it.
+    pub filename: Cow<'a, [u8]>,
     pub metadata: Option<&'a [u8]>,
 }
 
-#[derive(Debug, Default, PartialEq)]
+impl FileInfo<'_> {
+    /// Whether this is the `/dev/null` placeholder used for the missing side
+    /// of a created or deleted file.
+    pub fn is_dev_null(&self) -> bool {
+        *self.filename == b"/dev/null"[..]
+    }
+
+    /// The filename as a `str`, or an error if it isn't UTF-8, where
+    /// `Display` would replace the invalid bytes.
+    pub fn filename_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(&self.filename)
+    }
+
+    /// The whole header after the `--- ` or `+++ `, as an opaque label.
+    /// `diff --label` can give any text, which may hold a tab and so be split
+    /// into filename and metadata, and this joins them again.
+    pub fn label(&self) -> Cow<'_, [u8]> {
+        match self.metadata {
+            Some(metadata) => Cow::Owned([&self.filename[..], metadata].join(&b'\t')),
+            None => Cow::Borrowed(&self.filename),
+        }
+    }
+
+    /// Whether both name the same file, whatever their metadata, such as
+    /// timestamps, says.
+    pub fn same_file(&self, other: &FileInfo) -> bool {
+        self.filename == other.filename
+    }
+
+    fn strip(self, levels: usize) -> Self {
+        let filename = match self.filename {
+            Cow::Borrowed(filename) => Cow::Borrowed(strip_path(filename, levels)),
+            Cow::Owned(filename) => Cow::Owned(strip_path(&filename, levels).to_vec()),
+        };
+        Self { filename, ..self }
+    }
+}
+
+#[test]
+fn test_is_dev_null() {
+    let fi = |filename: &'static [u8]| FileInfo {
+        filename: filename.into(),
+        metadata: None,
+    };
+
+    assert!(fi(b"/dev/null").is_dev_null());
+    assert!(!fi(b"/dev/null-ish").is_dev_null());
+    assert!(!fi(b"a/dev/null").is_dev_null());
+    assert!(!fi(b"dev/null").is_dev_null());
+}
+
+#[test]
+fn test_label() {
+    // From `diff --label "old version" --label "$(printf 'new\tversion 2')"`.
+    let diff = b"--- old version\n+++ new\tversion 2\n@@ -1 +1 @@\n-a\n+b\n";
+    let mut parser = DiffParser::new(&diff[..]);
+
+    match parser.next_line() {
+        Some(Ok(DiffLine::OldFile(fi))) => {
+            assert_eq!(&fi.filename[..], b"old version");
+            assert_eq!(&fi.label()[..], b"old version");
+        }
+        line => panic!("unexpected {:?}", line),
+    }
+    match parser.next_line() {
+        Some(Ok(DiffLine::NewFile(fi))) => {
+            assert_eq!(&fi.filename[..], b"new");
+            assert_eq!(fi.metadata, Some(&b"version 2"[..]));
+            assert_eq!(&fi.label()[..], b"new\tversion 2");
+        }
+        line => panic!("unexpected {:?}", line),
+    }
+}
+
+#[test]
+fn test_same_file() {
+    let fi = |filename: &'static [u8], metadata: &'static [u8]| FileInfo {
+        filename: filename.into(),
+        metadata: Some(metadata),
+    };
+
+    let old = fi(b"foo.c", b"2019-01-01 00:00:00");
+    let new = fi(b"foo.c", b"2019-01-02 12:00:00");
+    assert_ne!(old, new);
+    assert!(old.same_file(&new));
+    assert!(!old.same_file(&fi(b"bar.c", b"2019-01-01 00:00:00")));
+
+    let owned = OwnedFileInfo::from(&old);
+    assert!(owned.same_file(&OwnedFileInfo::from(&new)));
+    assert!(!owned.same_file(&OwnedFileInfo {
+        filename: b"foo.h".to_vec(),
+        metadata: None
+    }));
+}
+
+#[test]
+fn test_filename_str() {
+    let fi = |filename: &'static [u8]| FileInfo {
+        filename: filename.into(),
+        metadata: None,
+    };
+
+    assert_eq!(fi(b"foo.c").filename_str(), Ok("foo.c"));
+    assert_eq!(fi("caf\u{e9}".as_bytes()).filename_str(), Ok("caf\u{e9}"));
+    let err = fi(b"caf\xe9").filename_str().unwrap_err();
+    assert_eq!(err.valid_up_to(), 3);
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct HunkInfo<'a> {
-    pub old_line_no: u32,
-    pub old_line_len: u32,
-    pub new_line_no: u32,
-    pub new_line_len: u32,
+    pub old_line_no: u64,
+    pub old_line_len: u64,
+    pub new_line_no: u64,
+    pub new_line_len: u64,
     pub context: Option<&'a [u8]>,
 }
 
-#[derive(Debug, PartialEq)]
+/// The header of a hunk in a combined diff of a merge, which has a range for
+/// each parent.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CombinedHunkInfo<'a> {
+    pub old_ranges: Vec<(u64, u64)>,
+    pub new_line_no: u64,
+    pub new_line_len: u64,
+    pub context: Option<&'a [u8]>,
+}
+
+/// The kind of diff a `DiffParser` has found itself reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffFormat {
+    Unified,
+    /// From `diff -c`.
+    Context,
+    Git,
+    /// An ed script, from `diff -e`.
+    Ed,
+}
+
+/// What a `DiffParser` expects to read next, from `DiffParser::state()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParserState {
+    /// Before the first file, as in an email's preamble.
+    Preamble,
+    /// Between files, where anything but a header is junk.
+    BetweenFiles,
+    /// In the extended headers after a `diff --git` line.
+    GitHeader,
+    /// In the headers after an svn `Index:` line.
+    SvnHeader,
+    /// In an svn `Property changes on:` block.
+    Properties,
+    /// After a `---` or context diff `***` header.
+    ExpectNewFile,
+    /// After a `+++` header, or a context diff's `---`.
+    ExpectHunk,
+    /// In a `@@` hunk, with the number of old and new lines still to come.
+    InHunk {
+        old_left: u64,
+        new_left: u64,
+    },
+    InCombinedHunk,
+    InContextHunk,
+    /// After a complete hunk, where another may start.
+    AfterHunk,
+    /// In the text of an ed `a` or `c` command.
+    InEdText,
+    InBinaryPatch,
+}
+
+/// The modes of a file before and after a diff, from its git headers.  A
+/// new file has no old mode, and a deleted one no new mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FileModeChange {
+    pub old: Option<u32>,
+    pub new: Option<u32>,
+}
+
+/// Which side of a diff something applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Old,
+    New,
+    Both,
+}
+
+/// How the data in a section of a `GIT binary patch` gives the new file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryPatchKind {
+    /// The whole file.
+    Literal,
+    /// A delta from the old file.
+    Delta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PropertyChange {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A line of a diff.  Lines display as they'd appear in one, without a
+/// trailing newline even if they were read with it: use `writeln()` or
+/// `writeln!` to write them out a line at a time.
+#[derive(Debug, Clone, PartialEq)]
 pub enum DiffLine<'a> {
+    GitHeader {
+        old_path: &'a [u8],
+        new_path: &'a [u8],
+    },
+    Index {
+        old_oid: &'a [u8],
+        new_oid: &'a [u8],
+        mode: Option<&'a [u8]>,
+    },
+    NewFileMode(u32),
+    DeletedFileMode(u32),
+    /// `old mode 100644`, for a file whose mode changed.
+    OldMode(u32),
+    NewMode(u32),
+    SimilarityIndex(u8),
+    RenameFrom(&'a [u8]),
+    RenameTo(&'a [u8]),
+    CopyFrom(&'a [u8]),
+    CopyTo(&'a [u8]),
     OldFile(FileInfo<'a>),
     NewFile(FileInfo<'a>),
     Binaries(&'a [u8], &'a [u8]),
+    /// `GIT binary patch`, followed by a `BinaryPatch` with its data for the
+    /// new file, and usually another for the old.
+    GitBinaryPatch,
+    /// `literal 123` or `delta 123`, with the size of the data decoded.
+    BinaryPatch {
+        kind: BinaryPatchKind,
+        size: u64,
+    },
+    /// A line of a `BinaryPatch`'s base85 data, without its line ending.
+    BinaryData(&'a [u8]),
+    /// `Only in dir: file` from `diff -r`, for a file on only one side.
+    OnlyIn {
+        dir: &'a [u8],
+        file: &'a [u8],
+    },
+    /// `Files a and b differ`, from `diff -rq`.
+    FilesDiffer(&'a [u8], &'a [u8]),
     Hunk(HunkInfo<'a>),
-    Context(&'a [u8]),
-    Inserted(&'a [u8]),
-    Deleted(&'a [u8]),
-    Modified(&'a [u8]),
-    NoNewlineAtEof,
+    /// The `***************` line starting a hunk in a context diff, which is
+    /// followed by an `OldRange` and its lines, then a `NewRange` and its
+    /// lines.  Either set of lines is left out if it has no changes.
+    ContextHunk,
+    /// The first and last line numbers of the old side of a context diff hunk.
+    OldRange(u64, u64),
+    /// The first and last line numbers of the new side of a context diff hunk.
+    NewRange(u64, u64),
+    /// An ed script's `5c` or `2,4c` command, replacing the lines from the
+    /// first to the last with the `Inserted` lines which follow, up to an
+    /// `EdEnd`.
+    EdChange(u64, u64),
+    /// An ed script's `2,4d`, deleting the lines from the first to the last.
+    EdDelete(u64, u64),
+    /// An ed script's `5a`, adding the `Inserted` lines which follow after
+    /// that line, up to an `EdEnd`.
+    EdAppend(u64),
+    /// The `.` which ends the text of an `EdChange` or `EdAppend`.
+    EdEnd,
+    /// `Index: path` from svn and cvs, which comes before a file's headers.
+    /// Unrelated to git's `Index`.
+    SvnIndex(&'a [u8]),
+    /// The line of `=` following an `SvnIndex`.
+    SvnSeparator(&'a [u8]),
+    /// svn's `Property changes on: path`, which starts a block of `Property`
+    /// lines each followed by `PropHunk`s.
+    PropertyChanges(&'a [u8]),
+    Property {
+        change: PropertyChange,
+        name: &'a [u8],
+    },
+    PropHunk(HunkInfo<'a>),
+    CombinedHunk(CombinedHunkInfo<'a>),
+    /// A line in a combined hunk, with a ` `, `+` or `-` marker for each
+    /// parent describing how it differs from that parent.
+    CombinedDelta {
+        markers: &'a [u8],
+        line: &'a [u8],
+    },
+    /// The lines of a hunk, with the width of the marker before them: one
+    /// byte in unified diffs, two in context diffs, and none for a blank
+    /// context line or the text of an ed script.
+    Context(&'a [u8], u8),
+    Inserted(&'a [u8], u8),
+    Deleted(&'a [u8], u8),
+    Modified(&'a [u8], u8),
+    /// `\\ No newline at end of file`, for the side or sides of the line
+    /// before it.  `marker` is the whole line, which is sometimes worded
+    /// differently, keeping its newline.
+    NoNewlineAtEof {
+        side: Side,
+        marker: &'a [u8],
+    },
+    /// Not a line of input, but emitted by `DiffParser` when a hunk has fewer
+    /// or more lines than its header says.  For short hunks this comes before
+    /// the line that cut it short.  For long ones it replaces the first line
+    /// too many, which is then parsed again as if the hunk had ended.
+    MalformedHunk {
+        expected_old: u64,
+        expected_new: u64,
+        actual_old: u64,
+        actual_new: u64,
+    },
+    /// Not a line of input, but emitted by a `file_events()` parser after a
+    /// file's `+++` header, with its new path.
+    FileStart(FileInfo<'a>),
+    /// Not a line of input either, emitted after the last line of a file
+    /// started by `FileStart` and with the same `FileInfo`.
+    FileEnd(FileInfo<'a>),
+    /// Not a line of input, but emitted after the `-Subproject commit` and
+    /// `+Subproject commit` lines of a git submodule's hunk, with the commits.
+    Submodule {
+        old: &'a [u8],
+        new: &'a [u8],
+    },
+    /// A line of junk before the first file header, such as the commit
+    /// message and diffstat of a `git format-patch` email.
+    Preamble(&'a [u8]),
     Junk(&'a [u8]),
 }
 
-impl fmt::Display for HunkInfo<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "@@ -{}", self.old_line_no)?;
-        if self.old_line_len > 1 {
-            write!(f, ",{}", self.old_line_len)?;
+/// Which variant a `DiffLine` is, without its payload, from `DiffLine::kind()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiffLineKind {
+    GitHeader,
+    Index,
+    NewFileMode,
+    DeletedFileMode,
+    OldMode,
+    NewMode,
+    SimilarityIndex,
+    RenameFrom,
+    RenameTo,
+    CopyFrom,
+    CopyTo,
+    OldFile,
+    NewFile,
+    Binaries,
+    GitBinaryPatch,
+    BinaryPatch,
+    BinaryData,
+    OnlyIn,
+    FilesDiffer,
+    Hunk,
+    ContextHunk,
+    OldRange,
+    NewRange,
+    EdChange,
+    EdDelete,
+    EdAppend,
+    EdEnd,
+    SvnIndex,
+    SvnSeparator,
+    PropertyChanges,
+    Property,
+    PropHunk,
+    CombinedHunk,
+    CombinedDelta,
+    Context,
+    Inserted,
+    Deleted,
+    Modified,
+    NoNewlineAtEof,
+    MalformedHunk,
+    FileStart,
+    FileEnd,
+    Submodule,
+    Preamble,
+    Junk,
+}
+
+impl DiffLineKind {
+    /// Whether the kind starts or describes a file or hunk: a git, svn or
+    /// `---`/`+++` header, a line saying binary or only-in files differ, or a
+    /// hunk header of any format.
+    pub fn is_header(self) -> bool {
+        use DiffLineKind::*;
+        matches!(
+            self,
+            GitHeader
+                | Index
+                | NewFileMode
+                | DeletedFileMode
+                | OldMode
+                | NewMode
+                | SimilarityIndex
+                | RenameFrom
+                | RenameTo
+                | CopyFrom
+                | CopyTo
+                | OldFile
+                | NewFile
+                | Binaries
+                | GitBinaryPatch
+                | BinaryPatch
+                | OnlyIn
+                | FilesDiffer
+                | Hunk
+                | ContextHunk
+                | OldRange
+                | NewRange
+                | EdChange
+                | EdDelete
+                | EdAppend
+                | SvnIndex
+                | SvnSeparator
+                | PropertyChanges
+                | Property
+                | PropHunk
+                | CombinedHunk
+        )
+    }
+
+    /// Whether the kind is a line of a hunk's content, including those of
+    /// combined diffs.
+    pub fn is_content(self) -> bool {
+        use DiffLineKind::*;
+        matches!(
+            self,
+            Context | Inserted | Deleted | Modified | CombinedDelta
+        )
+    }
+
+    /// Whether the kind is something besides the diff itself, or a marker
+    /// about its content, which layouts can usually leave out.
+    pub fn is_noise(self) -> bool {
+        use DiffLineKind::*;
+        matches!(self, NoNewlineAtEof | Preamble | Junk)
+    }
+}
+
+#[test]
+fn test_kind_classes() {
+    use DiffLineKind::*;
+    let headers = [
+        GitHeader,
+        Index,
+        NewFileMode,
+        DeletedFileMode,
+        OldMode,
+        NewMode,
+        SimilarityIndex,
+        RenameFrom,
+        RenameTo,
+        CopyFrom,
+        CopyTo,
+        OldFile,
+        NewFile,
+        Binaries,
+        GitBinaryPatch,
+        BinaryPatch,
+        OnlyIn,
+        FilesDiffer,
+        Hunk,
+        ContextHunk,
+        OldRange,
+        NewRange,
+        EdChange,
+        EdDelete,
+        EdAppend,
+        SvnIndex,
+        SvnSeparator,
+        PropertyChanges,
+        Property,
+        PropHunk,
+        CombinedHunk,
+    ];
+    let content = [Context, Inserted, Deleted, Modified, CombinedDelta];
+    let noise = [NoNewlineAtEof, Preamble, Junk];
+    // Binary data, the end of an ed insertion, and the lines made up by the
+    // parser.
+    let others = [
+        BinaryData,
+        EdEnd,
+        MalformedHunk,
+        FileStart,
+        FileEnd,
+        Submodule,
+    ];
+
+    let classes = |kind: DiffLineKind| (kind.is_header(), kind.is_content(), kind.is_noise());
+    for kind in headers {
+        assert_eq!(classes(kind), (true, false, false), "{:?}", kind);
+    }
+    for kind in content {
+        assert_eq!(classes(kind), (false, true, false), "{:?}", kind);
+    }
+    for kind in noise {
+        assert_eq!(classes(kind), (false, false, true), "{:?}", kind);
+    }
+    for kind in others {
+        assert_eq!(classes(kind), (false, false, false), "{:?}", kind);
+    }
+
+    // Every kind is in one of the lists.
+    let every = headers.len() + content.len() + noise.len() + others.len();
+    assert_eq!(every, Junk as usize + 1);
+
+    assert!(DiffLine::Hunk(HunkInfo::default()).is_header());
+    assert!(DiffLine::Inserted(b"foo\n", 1).is_content());
+    assert!(DiffLine::Junk(b"foo\n").is_noise());
+}
+
+fn fmt_range(f: &mut fmt::Formatter, sign: char, line_no: u64, line_len: u64) -> fmt::Result {
+    write!(f, " {}{}", sign, line_no)?;
+    if line_len != 1 {
+        write!(f, ",{}", line_len)?;
+    }
+    Ok(())
+}
+
+fn fmt_context(f: &mut fmt::Formatter, context: Option<&[u8]>) -> fmt::Result {
+    if let Some(ctx) = context {
+        write!(f, "\t{}", String::from_utf8_lossy(ctx))?;
+    }
+    Ok(())
+}
+
+impl<'a> HunkInfo<'a> {
+    /// Parse a `@@ -1,2 +1,3 @@` hunk header, or return `None` if `line`
+    /// isn't one.
+    pub fn parse(line: &'a [u8]) -> Option<Self> {
+        parse_hunk_info(line, b"@@")
+    }
+
+    /// Swap the old and new ranges, as for a diff applied in reverse.
+    pub fn reverse(self) -> Self {
+        Self {
+            old_line_no: self.new_line_no,
+            old_line_len: self.new_line_len,
+            new_line_no: self.old_line_no,
+            new_line_len: self.old_line_len,
+            context: self.context,
         }
+    }
+
+    /// The lines of the old file in the hunk.  A hunk with none, such as an
+    /// insertion, has an empty range at the line its changes come after.
+    pub fn old_range(&self) -> Range<u64> {
+        self.old_line_no..self.old_line_no + self.old_line_len
+    }
+
+    /// The lines of the new file in the hunk, as for `old_range()`.
+    pub fn new_range(&self) -> Range<u64> {
+        self.new_line_no..self.new_line_no + self.new_line_len
+    }
+
+    /// How many lines longer the hunk makes the file, or shorter if
+    /// negative.
+    pub fn net_change(&self) -> i64 {
+        self.new_line_len as i64 - self.old_line_len as i64
+    }
+
+    /// The section heading after the ranges, usually the line starting the
+    /// function or class the hunk is in.  This is the same as `context`.
+    pub fn section(&self) -> Option<&'a [u8]> {
+        self.context
+    }
+
+    /// A best guess at the name of the function or type in the section
+    /// heading: the identifier before its first `(`, or `{` if it has none,
+    /// with any `<...>` generics after it passed over.
+    pub fn section_symbol(&self) -> Option<&'a [u8]> {
+        let section = self.context?;
+        let end = section
+            .iter()
+            .position(|&b| b == b'(')
+            .or_else(|| section.iter().position(|&b| b == b'{'))
+            .unwrap_or(section.len());
+        let mut head = section[..end].trim_ascii_end();
 
-        write!(f, " +{}", self.new_line_no)?;
-        if self.new_line_len > 1 {
-            write!(f, ",{}", self.new_line_len)?;
+        if head.ends_with(b">") {
+            let mut depth = 0;
+            for (i, &b) in head.iter().enumerate().rev() {
+                match b {
+                    b'>' => depth += 1,
+                    b'<' => depth -= 1,
+                    _ => (),
+                }
+                if depth == 0 {
+                    head = head[..i].trim_ascii_end();
+                    break;
+                }
+            }
         }
-        write!(f, " @@")?;
 
-        if let Some(ctx) = self.context {
-            write!(f, "\t{}", String::from_utf8_lossy(ctx))?;
+        let start = head
+            .iter()
+            .rposition(|&b| !(b.is_ascii_alphanumeric() || b == b'_'))
+            .map_or(0, |pos| pos + 1);
+        Some(&head[start..]).filter(|name| name.first().is_some_and(|b| !b.is_ascii_digit()))
+    }
+
+    /// Move the hunk's old side by `delta` lines, stopping at line 0 or
+    /// `u64::MAX` rather than wrapping.
+    pub fn shift_old(&mut self, delta: i64) {
+        self.old_line_no = self.old_line_no.saturating_add_signed(delta);
+    }
+
+    /// Move the hunk's new side by `delta` lines, as for `shift_old()`.
+    pub fn shift_new(&mut self, delta: i64) {
+        self.new_line_no = self.new_line_no.saturating_add_signed(delta);
+    }
+
+    /// Whether the line numbers make sense.  A side with lines must start at
+    /// line 1 or later, and its last line must fit in a `u64`.  A side without
+    /// any can be at line 0, as its number is the line its changes follow.
+    pub fn is_valid(&self) -> bool {
+        let valid = |line_no: u64, line_len: u64| {
+            line_len == 0 || (line_no >= 1 && line_no.checked_add(line_len - 1).is_some())
+        };
+        valid(self.old_line_no, self.old_line_len) && valid(self.new_line_no, self.new_line_len)
+    }
+
+    fn fmt_ranges(&self, f: &mut fmt::Formatter, marker: &str) -> fmt::Result {
+        write!(f, "{}", marker)?;
+        fmt_range(f, '-', self.old_line_no, self.old_line_len)?;
+        fmt_range(f, '+', self.new_line_no, self.new_line_len)?;
+        write!(f, " {}", marker)
+    }
+
+    fn fmt_with_marker(&self, f: &mut fmt::Formatter, marker: &str) -> fmt::Result {
+        self.fmt_ranges(f, marker)?;
+        fmt_context(f, self.context)
+    }
+}
+
+#[test]
+fn test_hunk_info_section() {
+    let symbol = |line: &'static [u8]| HunkInfo::parse(line).unwrap().section_symbol();
+
+    let hi =
+        HunkInfo::parse(b"@@ -1 +1 @@ static int parse_args(int argc, char **argv)\n").unwrap();
+    assert_eq!(
+        hi.section(),
+        Some(&b"static int parse_args(int argc, char **argv)"[..])
+    );
+    assert_eq!(hi.section_symbol(), Some(&b"parse_args"[..]));
+
+    assert_eq!(symbol(b"@@ -1 +1 @@ int main (void)\n"), Some(&b"main"[..]));
+    assert_eq!(symbol(b"@@ -1 +1 @@ struct point {\n"), Some(&b"point"[..]));
+    assert_eq!(
+        symbol(b"@@ -1 +1 @@ pub fn next_line(&mut self) -> Option<io::Result<DiffLine>> {\n"),
+        Some(&b"next_line"[..])
+    );
+    assert_eq!(
+        symbol(b"@@ -1 +1 @@ pub fn new<R: BufRead>(inner: R) -> Self {\n"),
+        Some(&b"new"[..])
+    );
+    assert_eq!(
+        symbol(b"@@ -1 +1 @@ impl<R: BufRead> DiffParser<R> {\n"),
+        Some(&b"DiffParser"[..])
+    );
+    assert_eq!(symbol(b"@@ -1 +1 @@ }\n"), None);
+    assert_eq!(symbol(b"@@ -1 +1 @@\n"), None);
+}
+
+#[test]
+fn test_hunk_info_shift() {
+    let mut hi = HunkInfo::parse(b"@@ -10,2 +20,3 @@\n").unwrap();
+    hi.shift_new(5);
+    hi.shift_old(-4);
+    assert_eq!((hi.old_line_no, hi.new_line_no), (6, 25));
+    assert_eq!((hi.old_line_len, hi.new_line_len), (2, 3));
+
+    hi.shift_old(-7);
+    hi.shift_new(-25);
+    assert_eq!((hi.old_line_no, hi.new_line_no), (0, 0));
+
+    hi.shift_new(i64::MAX);
+    hi.shift_new(i64::MAX);
+    hi.shift_new(i64::MAX);
+    assert_eq!(hi.new_line_no, u64::MAX);
+    hi.shift_old(i64::MIN);
+    assert_eq!(hi.old_line_no, 0);
+}
+
+#[test]
+fn test_hunk_info_parse() {
+    assert_eq!(HunkInfo::parse(b""), None);
+    assert_eq!(HunkInfo::parse(b"-foo\n"), None);
+    assert_eq!(HunkInfo::parse(b"## -1 +1 ##\n"), None);
+    assert_eq!(HunkInfo::parse(b"@@ -1,2 +1\n"), None);
+
+    for (line, text) in &[
+        (&b"@@ -1 +1 @@\n"[..], "@@ -1 +1 @@"),
+        (b"@@ -12,34 +56,78 @@\n", "@@ -12,34 +56,78 @@"),
+        (
+            b"@@ -12,34 +56,78 @@\tfoo bar\n",
+            "@@ -12,34 +56,78 @@\tfoo bar",
+        ),
+        (b"@@ -0,0 +1,2 @@\n", "@@ -0,0 +1,2 @@"),
+    ] {
+        let hunk = HunkInfo::parse(line).expect("not a hunk");
+        assert_eq!(hunk.to_string(), *text);
+        assert_eq!(HunkInfo::parse(text.as_bytes()), Some(hunk));
+    }
+
+    let hunk = HunkInfo::parse(b"@@ -12,34 +56,78 @@\tfoo bar\n").unwrap();
+    assert_eq!(hunk.old_range(), 12..46);
+    assert_eq!(hunk.new_range(), 56..134);
+    assert_eq!(hunk.context, Some(&b"foo bar"[..]));
+}
+
+#[test]
+fn test_hunk_ranges() {
+    let hunk = |old_line_no, old_line_len, new_line_no, new_line_len| HunkInfo {
+        old_line_no,
+        old_line_len,
+        new_line_no,
+        new_line_len,
+        context: None,
+    };
+
+    assert_eq!(hunk(10, 3, 12, 4).old_range(), 10..13);
+    assert_eq!(hunk(10, 3, 12, 4).new_range(), 12..16);
+    assert_eq!(hunk(7, 1, 7, 1).old_range(), 7..8);
+    assert_eq!(hunk(0, 0, 1, 2).old_range(), 0..0);
+    assert_eq!(hunk(0, 0, 1, 2).new_range(), 1..3);
+    assert_eq!(hunk(5, 2, 4, 0).new_range(), 4..4);
+    assert!(hunk(5, 2, 4, 0).new_range().is_empty());
+}
+
+#[test]
+fn test_net_change() {
+    let hunk = |old_line_len, new_line_len| HunkInfo {
+        old_line_no: 1,
+        old_line_len,
+        new_line_no: 1,
+        new_line_len,
+        context: None,
+    };
+
+    assert_eq!(hunk(3, 5).net_change(), 2);
+    assert_eq!(hunk(5, 3).net_change(), -2);
+    assert_eq!(hunk(4, 4).net_change(), 0);
+    assert_eq!(hunk(0, 1).net_change(), 1);
+    assert_eq!(hunk(1, 0).net_change(), -1);
+}
+
+#[test]
+fn test_hunk_is_valid() {
+    let hunk = |old_line_no, old_line_len, new_line_no, new_line_len| HunkInfo {
+        old_line_no,
+        old_line_len,
+        new_line_no,
+        new_line_len,
+        context: None,
+    };
+
+    assert!(hunk(1, 1, 1, 1).is_valid());
+    assert!(hunk(0, 0, 1, 2).is_valid());
+    assert!(hunk(1, 2, 0, 0).is_valid());
+    assert!(hunk(5, 0, 6, 0).is_valid());
+    assert!(hunk(u64::MAX, 1, 1, 1).is_valid());
+    assert!(!hunk(1, 1, 0, 2).is_valid());
+    assert!(!hunk(0, 1, 1, 1).is_valid());
+    assert!(!hunk(u64::MAX, 2, 1, 1).is_valid());
+}
+
+impl CombinedHunkInfo<'_> {
+    fn fmt_ranges(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let marker = "@".repeat(self.old_ranges.len() + 1);
+        write!(f, "{}", marker)?;
+        for &(line_no, line_len) in &self.old_ranges {
+            fmt_range(f, '-', line_no, line_len)?;
         }
+        fmt_range(f, '+', self.new_line_no, self.new_line_len)?;
+        write!(f, " {}", marker)
+    }
+}
 
-        Ok(())
+impl fmt::Display for CombinedHunkInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_ranges(f)?;
+        fmt_context(f, self.context)
+    }
+}
+
+impl fmt::Display for HunkInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_marker(f, "@@")
+    }
+}
+
+impl fmt::Display for PropertyChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertyChange::Added => write!(f, "Added"),
+            PropertyChange::Modified => write!(f, "Modified"),
+            PropertyChange::Deleted => write!(f, "Deleted"),
+        }
     }
 }
 
 impl fmt::Display for FileInfo<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", String::from_utf8_lossy(self.filename))?;
+        write!(f, "{}", String::from_utf8_lossy(&self.filename))?;
         if let Some(md) = self.metadata {
             write!(f, "\t{}", String::from_utf8_lossy(md))?;
         }
@@ -60,386 +860,4196 @@ impl fmt::Display for FileInfo<'_> {
     }
 }
 
+// An ed command on a line, or a range of lines as `first,last`.
+fn fmt_ed_command(f: &mut fmt::Formatter, first: u64, last: u64, command: char) -> fmt::Result {
+    if last == first {
+        write!(f, "{}{}", first, command)
+    } else {
+        write!(f, "{},{}{}", first, last, command)
+    }
+}
+
+// Lines keep the newline they were read with, which isn't displayed.
+fn fmt_payload(f: &mut fmt::Formatter, marker: &str, line: &[u8]) -> fmt::Result {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    write!(f, "{}{}", marker, String::from_utf8_lossy(line))
+}
+
 impl fmt::Display for DiffLine<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            DiffLine::GitHeader { old_path, new_path } => write!(
+                f,
+                "diff --git {} {}",
+                String::from_utf8_lossy(old_path),
+                String::from_utf8_lossy(new_path)
+            ),
+            DiffLine::Index {
+                old_oid,
+                new_oid,
+                mode,
+            } => {
+                write!(
+                    f,
+                    "index {}..{}",
+                    String::from_utf8_lossy(old_oid),
+                    String::from_utf8_lossy(new_oid)
+                )?;
+                if let Some(mode) = mode {
+                    write!(f, " {}", String::from_utf8_lossy(mode))?;
+                }
+                Ok(())
+            }
+            DiffLine::NewFileMode(mode) => write!(f, "new file mode {:06o}", mode),
+            DiffLine::DeletedFileMode(mode) => write!(f, "deleted file mode {:06o}", mode),
+            DiffLine::OldMode(mode) => write!(f, "old mode {:06o}", mode),
+            DiffLine::NewMode(mode) => write!(f, "new mode {:06o}", mode),
+            DiffLine::SimilarityIndex(pct) => write!(f, "similarity index {}%", pct),
+            DiffLine::RenameFrom(p) => write!(f, "rename from {}", String::from_utf8_lossy(p)),
+            DiffLine::RenameTo(p) => write!(f, "rename to {}", String::from_utf8_lossy(p)),
+            DiffLine::CopyFrom(p) => write!(f, "copy from {}", String::from_utf8_lossy(p)),
+            DiffLine::CopyTo(p) => write!(f, "copy to {}", String::from_utf8_lossy(p)),
             DiffLine::OldFile(fi) => write!(f, "--- {}", fi),
             DiffLine::NewFile(fi) => write!(f, "+++ {}", fi),
-            DiffLine::Binaries(a, b) => writeln!(
+            DiffLine::Binaries(a, b) => write!(
                 f,
                 "Binary files {} and {} differ",
                 String::from_utf8_lossy(a),
                 String::from_utf8_lossy(b)
             ),
+            DiffLine::GitBinaryPatch => write!(f, "GIT binary patch"),
+            DiffLine::BinaryPatch { kind, size } => match kind {
+                BinaryPatchKind::Literal => write!(f, "literal {}", size),
+                BinaryPatchKind::Delta => write!(f, "delta {}", size),
+            },
+            DiffLine::BinaryData(data) => fmt_payload(f, "", data),
+            DiffLine::OnlyIn { dir, file } => write!(
+                f,
+                "Only in {}: {}",
+                String::from_utf8_lossy(dir),
+                String::from_utf8_lossy(file)
+            ),
+            DiffLine::FilesDiffer(a, b) => write!(
+                f,
+                "Files {} and {} differ",
+                String::from_utf8_lossy(a),
+                String::from_utf8_lossy(b)
+            ),
             DiffLine::Hunk(hi) => write!(f, "{}", hi),
-            DiffLine::Context(l) => write!(f, " {}", String::from_utf8_lossy(l)),
-            DiffLine::Inserted(l) => write!(f, "+{}", String::from_utf8_lossy(l)),
-            DiffLine::Deleted(l) => write!(f, "-{}", String::from_utf8_lossy(l)),
-            DiffLine::Modified(l) => write!(f, "!{}", String::from_utf8_lossy(l)),
-            DiffLine::NoNewlineAtEof => writeln!(f, "\\ No newline at end of file"),
-            DiffLine::Junk(l) => write!(f, "{}", String::from_utf8_lossy(l)),
+            DiffLine::ContextHunk => write!(f, "***************"),
+            DiffLine::OldRange(first, last) => {
+                write!(f, "*** {}", first)?;
+                if last != first {
+                    write!(f, ",{}", last)?;
+                }
+                write!(f, " ****")
+            }
+            DiffLine::NewRange(first, last) => {
+                write!(f, "--- {}", first)?;
+                if last != first {
+                    write!(f, ",{}", last)?;
+                }
+                write!(f, " ----")
+            }
+            DiffLine::EdChange(first, last) => fmt_ed_command(f, *first, *last, 'c'),
+            DiffLine::EdDelete(first, last) => fmt_ed_command(f, *first, *last, 'd'),
+            DiffLine::EdAppend(line) => write!(f, "{}a", line),
+            DiffLine::EdEnd => write!(f, "."),
+            DiffLine::SvnIndex(p) => write!(f, "Index: {}", String::from_utf8_lossy(p)),
+            DiffLine::SvnSeparator(sep) => write!(f, "{}", String::from_utf8_lossy(sep)),
+            DiffLine::PropertyChanges(p) => {
+                write!(f, "Property changes on: {}", String::from_utf8_lossy(p))
+            }
+            DiffLine::Property { change, name } => {
+                write!(f, "{}: {}", change, String::from_utf8_lossy(name))
+            }
+            DiffLine::PropHunk(hi) => hi.fmt_with_marker(f, "##"),
+            DiffLine::CombinedHunk(hi) => write!(f, "{}", hi),
+            DiffLine::CombinedDelta { markers, line } => {
+                fmt_payload(f, &String::from_utf8_lossy(markers), line)
+            }
+            DiffLine::Context(l, _) => fmt_payload(f, " ", l),
+            DiffLine::Inserted(l, _) => fmt_payload(f, "+", l),
+            DiffLine::Deleted(l, _) => fmt_payload(f, "-", l),
+            DiffLine::Modified(l, _) => fmt_payload(f, "!", l),
+            DiffLine::NoNewlineAtEof { marker, .. } => fmt_payload(f, "", marker),
+            DiffLine::MalformedHunk { .. }
+            | DiffLine::FileStart(_)
+            | DiffLine::FileEnd(_)
+            | DiffLine::Submodule { .. } => Ok(()),
+            DiffLine::Preamble(l) | DiffLine::Junk(l) => fmt_payload(f, "", l),
+        }
+    }
+}
+
+impl<'a> DiffLine<'a> {
+    /// The line as it would appear in a diff going the other way, with
+    /// insertions and deletions swapped.
+    ///
+    /// `OldFile` and `NewFile` swap too, so a reversed `---`/`+++` pair needs
+    /// putting back in order before it's written out.  Combined diffs have no
+    /// single reverse, and context diff ranges can't be reversed a line at a
+    /// time, so these are left as they are.
+    pub fn reverse(self) -> Self {
+        match self {
+            DiffLine::GitHeader { old_path, new_path } => DiffLine::GitHeader {
+                old_path: new_path,
+                new_path: old_path,
+            },
+            DiffLine::Index {
+                old_oid,
+                new_oid,
+                mode,
+            } => DiffLine::Index {
+                old_oid: new_oid,
+                new_oid: old_oid,
+                mode,
+            },
+            DiffLine::NewFileMode(mode) => DiffLine::DeletedFileMode(mode),
+            DiffLine::DeletedFileMode(mode) => DiffLine::NewFileMode(mode),
+            DiffLine::OldMode(mode) => DiffLine::NewMode(mode),
+            DiffLine::NewMode(mode) => DiffLine::OldMode(mode),
+            DiffLine::RenameFrom(p) => DiffLine::RenameTo(p),
+            DiffLine::RenameTo(p) => DiffLine::RenameFrom(p),
+            DiffLine::CopyFrom(p) => DiffLine::CopyTo(p),
+            DiffLine::CopyTo(p) => DiffLine::CopyFrom(p),
+            DiffLine::OldFile(fi) => DiffLine::NewFile(fi),
+            DiffLine::NewFile(fi) => DiffLine::OldFile(fi),
+            DiffLine::Binaries(a, b) => DiffLine::Binaries(b, a),
+            DiffLine::FilesDiffer(a, b) => DiffLine::FilesDiffer(b, a),
+            DiffLine::NoNewlineAtEof { side, marker } => DiffLine::NoNewlineAtEof {
+                side: match side {
+                    Side::Old => Side::New,
+                    Side::New => Side::Old,
+                    Side::Both => Side::Both,
+                },
+                marker,
+            },
+            DiffLine::Hunk(hi) => DiffLine::Hunk(hi.reverse()),
+            DiffLine::Property { change, name } => DiffLine::Property {
+                change: match change {
+                    PropertyChange::Added => PropertyChange::Deleted,
+                    PropertyChange::Deleted => PropertyChange::Added,
+                    PropertyChange::Modified => PropertyChange::Modified,
+                },
+                name,
+            },
+            DiffLine::PropHunk(hi) => DiffLine::PropHunk(hi.reverse()),
+            DiffLine::Inserted(l, width) => DiffLine::Deleted(l, width),
+            DiffLine::Deleted(l, width) => DiffLine::Inserted(l, width),
+            DiffLine::Submodule { old, new } => DiffLine::Submodule { old: new, new: old },
+            DiffLine::MalformedHunk {
+                expected_old,
+                expected_new,
+                actual_old,
+                actual_new,
+            } => DiffLine::MalformedHunk {
+                expected_old: expected_new,
+                expected_new: expected_old,
+                actual_old: actual_new,
+                actual_new: actual_old,
+            },
+            line => line,
         }
     }
+
+    /// The length of the line this came from, including its markers and line
+    /// ending, for lines which keep it whole.  Headers are `None`, as are
+    /// lines `DiffParser` makes up, like `MalformedHunk`.
+    pub fn source_len(&self) -> Option<usize> {
+        match self {
+            DiffLine::Context(l, width)
+            | DiffLine::Inserted(l, width)
+            | DiffLine::Deleted(l, width)
+            | DiffLine::Modified(l, width) => Some(*width as usize + l.len()),
+            DiffLine::CombinedDelta { markers, line } => Some(markers.len() + line.len()),
+            DiffLine::Preamble(l) | DiffLine::Junk(l) => Some(l.len()),
+            _ => None,
+        }
+    }
+
+    /// The payload of a `Context`, `Inserted`, `Deleted` or `Modified` line
+    /// without its line ending.  Other lines are `None`.
+    pub fn content(&self) -> Option<&'a [u8]> {
+        match *self {
+            DiffLine::Context(l, _)
+            | DiffLine::Inserted(l, _)
+            | DiffLine::Deleted(l, _)
+            | DiffLine::Modified(l, _) => Some(chomp(l)),
+            _ => None,
+        }
+    }
+
+    /// The text of a `Context`, `Inserted`, `Deleted` or `Modified` line, or
+    /// an error if it isn't UTF-8.  Other lines are `None`.
+    pub fn payload_str(&self) -> Option<Result<&'a str, Utf8Error>> {
+        match *self {
+            DiffLine::Context(l, _)
+            | DiffLine::Inserted(l, _)
+            | DiffLine::Deleted(l, _)
+            | DiffLine::Modified(l, _) => Some(str::from_utf8(l)),
+            _ => None,
+        }
+    }
+
+    /// Write the line to `out` followed by a newline, as `writeln!` would,
+    /// except for lines `DiffParser` makes up, which aren't written at all.
+    pub fn writeln<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        if self.is_made_up() {
+            return Ok(());
+        }
+        writeln!(out, "{}", self)
+    }
+
+    /// Write the line to `out` as git would, to make a patch to apply,
+    /// unlike the `Display` used by `writeln()`.  Payloads are written as
+    /// they are, keeping their own line endings, and `---` and `+++` paths
+    /// are quoted again if they need to be.  A hunk header's context follows
+    /// it after a space, rather than a tab.  Lines `DiffParser` makes up
+    /// aren't written.
+    ///
+    /// Only unified diffs are written this way.  Lines only found in context
+    /// diffs and ed scripts are an `InvalidInput` error, as there's no telling
+    /// their file headers from unified ones to write those back as they were.
+    pub fn write_patch<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let (marker, line): (&[u8], &[u8]) = match self {
+            DiffLine::OldFile(fi) | DiffLine::NewFile(fi) => {
+                let marker = if let DiffLine::OldFile(_) = self {
+                    b"--- "
+                } else {
+                    b"+++ "
+                };
+                out.write_all(marker)?;
+                out.write_all(&line::quote_path(&fi.filename))?;
+                if let Some(metadata) = fi.metadata {
+                    out.write_all(b"\t")?;
+                    out.write_all(metadata)?;
+                }
+                return out.write_all(b"\n");
+            }
+            DiffLine::Hunk(hi) => {
+                write!(
+                    out,
+                    "{}",
+                    HunkInfo {
+                        context: None,
+                        ..*hi
+                    }
+                )?;
+                match hi.context {
+                    Some(context) => (b" ", context),
+                    None => return out.write_all(b"\n"),
+                }
+            }
+            DiffLine::CombinedHunk(hi) => {
+                let ranges = CombinedHunkInfo {
+                    context: None,
+                    ..hi.clone()
+                };
+                write!(out, "{}", ranges)?;
+                match hi.context {
+                    Some(context) => (b" ", context),
+                    None => return out.write_all(b"\n"),
+                }
+            }
+            DiffLine::GitHeader { old_path, new_path } => {
+                out.write_all(b"diff --git ")?;
+                out.write_all(old_path)?;
+                (b" ", new_path)
+            }
+            DiffLine::RenameFrom(p) => (b"rename from ", p),
+            DiffLine::RenameTo(p) => (b"rename to ", p),
+            DiffLine::CopyFrom(p) => (b"copy from ", p),
+            DiffLine::CopyTo(p) => (b"copy to ", p),
+            DiffLine::CombinedDelta { markers, line } => (markers, line),
+            DiffLine::Context(l, _) => (b" ", l),
+            DiffLine::Inserted(l, _) => (b"+", l),
+            DiffLine::Deleted(l, _) => (b"-", l),
+            DiffLine::ContextHunk
+            | DiffLine::OldRange(..)
+            | DiffLine::NewRange(..)
+            | DiffLine::Modified(..)
+            | DiffLine::EdChange(..)
+            | DiffLine::EdDelete(..)
+            | DiffLine::EdAppend(_)
+            | DiffLine::EdEnd => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("can't write {:?} as a unified diff", self.kind()),
+                ));
+            }
+            DiffLine::BinaryData(l)
+            | DiffLine::NoNewlineAtEof { marker: l, .. }
+            | DiffLine::Preamble(l)
+            | DiffLine::Junk(l) => (b"", l),
+            line => return line.writeln(out),
+        };
+
+        out.write_all(marker)?;
+        out.write_all(line)?;
+        if !line.ends_with(b"\n") {
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// The variant of the line, to match or compare without its payload.
+    pub fn kind(&self) -> DiffLineKind {
+        match self {
+            DiffLine::GitHeader { .. } => DiffLineKind::GitHeader,
+            DiffLine::Index { .. } => DiffLineKind::Index,
+            DiffLine::NewFileMode(_) => DiffLineKind::NewFileMode,
+            DiffLine::DeletedFileMode(_) => DiffLineKind::DeletedFileMode,
+            DiffLine::OldMode(_) => DiffLineKind::OldMode,
+            DiffLine::NewMode(_) => DiffLineKind::NewMode,
+            DiffLine::SimilarityIndex(_) => DiffLineKind::SimilarityIndex,
+            DiffLine::RenameFrom(_) => DiffLineKind::RenameFrom,
+            DiffLine::RenameTo(_) => DiffLineKind::RenameTo,
+            DiffLine::CopyFrom(_) => DiffLineKind::CopyFrom,
+            DiffLine::CopyTo(_) => DiffLineKind::CopyTo,
+            DiffLine::OldFile(_) => DiffLineKind::OldFile,
+            DiffLine::NewFile(_) => DiffLineKind::NewFile,
+            DiffLine::Binaries(..) => DiffLineKind::Binaries,
+            DiffLine::GitBinaryPatch => DiffLineKind::GitBinaryPatch,
+            DiffLine::BinaryPatch { .. } => DiffLineKind::BinaryPatch,
+            DiffLine::BinaryData(_) => DiffLineKind::BinaryData,
+            DiffLine::OnlyIn { .. } => DiffLineKind::OnlyIn,
+            DiffLine::FilesDiffer(..) => DiffLineKind::FilesDiffer,
+            DiffLine::Hunk(_) => DiffLineKind::Hunk,
+            DiffLine::ContextHunk => DiffLineKind::ContextHunk,
+            DiffLine::OldRange(..) => DiffLineKind::OldRange,
+            DiffLine::NewRange(..) => DiffLineKind::NewRange,
+            DiffLine::EdChange(..) => DiffLineKind::EdChange,
+            DiffLine::EdDelete(..) => DiffLineKind::EdDelete,
+            DiffLine::EdAppend(_) => DiffLineKind::EdAppend,
+            DiffLine::EdEnd => DiffLineKind::EdEnd,
+            DiffLine::SvnIndex(_) => DiffLineKind::SvnIndex,
+            DiffLine::SvnSeparator(_) => DiffLineKind::SvnSeparator,
+            DiffLine::PropertyChanges(_) => DiffLineKind::PropertyChanges,
+            DiffLine::Property { .. } => DiffLineKind::Property,
+            DiffLine::PropHunk(_) => DiffLineKind::PropHunk,
+            DiffLine::CombinedHunk(_) => DiffLineKind::CombinedHunk,
+            DiffLine::CombinedDelta { .. } => DiffLineKind::CombinedDelta,
+            DiffLine::Context(..) => DiffLineKind::Context,
+            DiffLine::Inserted(..) => DiffLineKind::Inserted,
+            DiffLine::Deleted(..) => DiffLineKind::Deleted,
+            DiffLine::Modified(..) => DiffLineKind::Modified,
+            DiffLine::NoNewlineAtEof { .. } => DiffLineKind::NoNewlineAtEof,
+            DiffLine::MalformedHunk { .. } => DiffLineKind::MalformedHunk,
+            DiffLine::FileStart(_) => DiffLineKind::FileStart,
+            DiffLine::FileEnd(_) => DiffLineKind::FileEnd,
+            DiffLine::Submodule { .. } => DiffLineKind::Submodule,
+            DiffLine::Preamble(_) => DiffLineKind::Preamble,
+            DiffLine::Junk(_) => DiffLineKind::Junk,
+        }
+    }
+
+    /// Whether the line is a header, as `DiffLineKind::is_header()` says.
+    pub fn is_header(&self) -> bool {
+        self.kind().is_header()
+    }
+
+    /// Whether the line is part of a hunk's content, as
+    /// `DiffLineKind::is_content()` says.
+    pub fn is_content(&self) -> bool {
+        self.kind().is_content()
+    }
+
+    /// Whether the line is noise, as `DiffLineKind::is_noise()` says.
+    pub fn is_noise(&self) -> bool {
+        self.kind().is_noise()
+    }
+
+    // Whether `DiffParser` made the line up, rather than reading it.
+    fn is_made_up(&self) -> bool {
+        matches!(
+            self,
+            DiffLine::MalformedHunk { .. }
+                | DiffLine::FileStart(_)
+                | DiffLine::FileEnd(_)
+                | DiffLine::Submodule { .. }
+        )
+    }
+}
+
+#[test]
+fn test_write_patch() {
+    let diff = b"diff --git a/bin b/bin
+index bdc955b7b2e610ad5a72302b139a2e6cb325519a..8835708590a9afa236e1bbad18df9d23de82ccd3 100644
+GIT binary patch
+literal 2
+JcmZQz0ssI600RI3
+
+literal 2
+JcmZQz1ONa700IC2
+
+diff --git \"a/caf\\303\\251.txt\" \"b/caf\\303\\251.txt\"
+index 422c2b7..33d5d3b 100644
+--- \"a/caf\\303\\251.txt\"
++++ \"b/caf\\303\\251.txt\"
+@@ -1,2 +1,2 @@
+ a
+-b
++B
+\\ No newline at end of file
+diff --git a/foo bar b/foo bar
+index 587be6b..975fbec 100644
+--- a/foo bar\t
++++ b/foo bar\t
+@@ -1 +1 @@
+-x
++y
+diff --git a/main.c b/main.c
+index dad112f..c3e9113 100644
+--- a/main.c
++++ b/main.c
+@@ -4,7 +4,7 @@ int main(void)
+ \ttwo();
+ \tthree();
+ \tfour();
+-\tfive();
++\tFIVE();
+ \tsix();
+ \treturn 0;
+ }
+diff --git a/old.sh b/new.sh
+old mode 100644
+new mode 100755
+similarity index 66%
+rename from old.sh
+rename to new.sh
+index 243154c..c28569a
+--- a/old.sh
++++ b/new.sh
+@@ -1,2 +1,3 @@
+ keep
+ me
++too
+";
+
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut patch = vec![];
+    while let Some(line) = parser.next_line() {
+        line.unwrap().write_patch(&mut patch).unwrap();
+    }
+    assert_eq!(
+        String::from_utf8_lossy(&patch),
+        String::from_utf8_lossy(diff)
+    );
+    assert_eq!(patch, &diff[..]);
+
+    // Paths are quoted again after being stripped.
+    let mut patch = vec![];
+    let fi = FileInfo {
+        filename: Cow::Borrowed("caf\u{e9}".as_bytes()),
+        metadata: Some(b"2024-01-01"),
+    };
+    DiffLine::OldFile(fi).write_patch(&mut patch).unwrap();
+    DiffLine::Hunk(HunkInfo::parse(b"@@ -1 +1 @@\tfoo\n").unwrap())
+        .write_patch(&mut patch)
+        .unwrap();
+    DiffLine::Inserted(b"no newline", 1)
+        .write_patch(&mut patch)
+        .unwrap();
+    DiffLine::MalformedHunk {
+        expected_old: 1,
+        expected_new: 1,
+        actual_old: 0,
+        actual_new: 0,
+    }
+    .write_patch(&mut patch)
+    .unwrap();
+    assert_eq!(
+        patch,
+        b"--- \"caf\\303\\251\"\t2024-01-01\n@@ -1 +1 @@ foo\n+no newline\n"
+    );
+
+    // Context diffs aren't written in a mix of both forms.
+    let diff = b"*** a/ctx\n--- b/ctx\n***************\n*** 5,6 ****\n! five\n  six\n";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut patch = vec![];
+    let mut errors = vec![];
+    while let Some(line) = parser.next_line() {
+        if let Err(err) = line.unwrap().write_patch(&mut patch) {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            errors.push(err.to_string());
+        }
+    }
+    assert_eq!(errors[0], "can't write ContextHunk as a unified diff");
+    assert_eq!(errors.len(), 3);
+    assert_eq!(
+        DiffLine::EdEnd
+            .write_patch(&mut vec![])
+            .unwrap_err()
+            .to_string(),
+        "can't write EdEnd as a unified diff"
+    );
 }
 
-fn chomp(slice: &[u8]) -> &[u8] {
-    if slice.ends_with(b"\r\n") {
-        &slice[..slice.len() - 2]
-    } else if slice.ends_with(b"\n") {
-        &slice[..slice.len() - 1]
-    } else {
-        slice
+#[test]
+fn test_kind() {
+    let fi = || FileInfo {
+        filename: Cow::Borrowed(b"foo"),
+        metadata: None,
+    };
+    let lines = vec![
+        (
+            DiffLine::GitHeader {
+                old_path: b"a/foo",
+                new_path: b"b/foo",
+            },
+            DiffLineKind::GitHeader,
+        ),
+        (
+            DiffLine::Index {
+                old_oid: b"1234567",
+                new_oid: b"89abcde",
+                mode: None,
+            },
+            DiffLineKind::Index,
+        ),
+        (DiffLine::NewFileMode(0o100644), DiffLineKind::NewFileMode),
+        (
+            DiffLine::DeletedFileMode(0o100644),
+            DiffLineKind::DeletedFileMode,
+        ),
+        (DiffLine::OldMode(0o100644), DiffLineKind::OldMode),
+        (DiffLine::NewMode(0o100755), DiffLineKind::NewMode),
+        (DiffLine::SimilarityIndex(90), DiffLineKind::SimilarityIndex),
+        (DiffLine::RenameFrom(b"foo"), DiffLineKind::RenameFrom),
+        (DiffLine::RenameTo(b"bar"), DiffLineKind::RenameTo),
+        (DiffLine::CopyFrom(b"foo"), DiffLineKind::CopyFrom),
+        (DiffLine::CopyTo(b"bar"), DiffLineKind::CopyTo),
+        (DiffLine::OldFile(fi()), DiffLineKind::OldFile),
+        (DiffLine::NewFile(fi()), DiffLineKind::NewFile),
+        (DiffLine::Binaries(b"a", b"b"), DiffLineKind::Binaries),
+        (DiffLine::GitBinaryPatch, DiffLineKind::GitBinaryPatch),
+        (
+            DiffLine::BinaryPatch {
+                kind: BinaryPatchKind::Literal,
+                size: 1,
+            },
+            DiffLineKind::BinaryPatch,
+        ),
+        (
+            DiffLine::BinaryData(b"HcmV?d00001"),
+            DiffLineKind::BinaryData,
+        ),
+        (
+            DiffLine::OnlyIn {
+                dir: b"a",
+                file: b"b",
+            },
+            DiffLineKind::OnlyIn,
+        ),
+        (DiffLine::FilesDiffer(b"a", b"b"), DiffLineKind::FilesDiffer),
+        (DiffLine::Hunk(HunkInfo::default()), DiffLineKind::Hunk),
+        (DiffLine::ContextHunk, DiffLineKind::ContextHunk),
+        (DiffLine::OldRange(1, 2), DiffLineKind::OldRange),
+        (DiffLine::NewRange(1, 2), DiffLineKind::NewRange),
+        (DiffLine::EdChange(1, 2), DiffLineKind::EdChange),
+        (DiffLine::EdDelete(1, 2), DiffLineKind::EdDelete),
+        (DiffLine::EdAppend(1), DiffLineKind::EdAppend),
+        (DiffLine::EdEnd, DiffLineKind::EdEnd),
+        (DiffLine::SvnIndex(b"foo"), DiffLineKind::SvnIndex),
+        (DiffLine::SvnSeparator(b"==="), DiffLineKind::SvnSeparator),
+        (
+            DiffLine::PropertyChanges(b"foo"),
+            DiffLineKind::PropertyChanges,
+        ),
+        (
+            DiffLine::Property {
+                change: PropertyChange::Added,
+                name: b"svn:eol-style",
+            },
+            DiffLineKind::Property,
+        ),
+        (
+            DiffLine::PropHunk(HunkInfo::default()),
+            DiffLineKind::PropHunk,
+        ),
+        (
+            DiffLine::CombinedHunk(CombinedHunkInfo::default()),
+            DiffLineKind::CombinedHunk,
+        ),
+        (
+            DiffLine::CombinedDelta {
+                markers: b"+ ",
+                line: b"foo\n",
+            },
+            DiffLineKind::CombinedDelta,
+        ),
+        (DiffLine::Context(b"foo\n", 1), DiffLineKind::Context),
+        (DiffLine::Inserted(b"foo\n", 1), DiffLineKind::Inserted),
+        (DiffLine::Deleted(b"foo\n", 1), DiffLineKind::Deleted),
+        (DiffLine::Modified(b"foo\n", 1), DiffLineKind::Modified),
+        (
+            DiffLine::NoNewlineAtEof {
+                side: Side::Both,
+                marker: b"\\ No newline at end of file\n",
+            },
+            DiffLineKind::NoNewlineAtEof,
+        ),
+        (
+            DiffLine::MalformedHunk {
+                expected_old: 1,
+                expected_new: 1,
+                actual_old: 0,
+                actual_new: 0,
+            },
+            DiffLineKind::MalformedHunk,
+        ),
+        (DiffLine::FileStart(fi()), DiffLineKind::FileStart),
+        (DiffLine::FileEnd(fi()), DiffLineKind::FileEnd),
+        (
+            DiffLine::Submodule {
+                old: b"1234567",
+                new: b"89abcde",
+            },
+            DiffLineKind::Submodule,
+        ),
+        (
+            DiffLine::Preamble(b"Subject: foo\n"),
+            DiffLineKind::Preamble,
+        ),
+        (DiffLine::Junk(b"junk\n"), DiffLineKind::Junk),
+    ];
+
+    for (line, kind) in &lines {
+        assert_eq!(line.kind(), *kind);
+    }
+
+    // Each variant has a kind of its own.
+    let kinds = lines
+        .iter()
+        .map(|(_, kind)| *kind)
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(kinds.len(), lines.len());
+}
+
+#[test]
+fn test_clone() {
+    let diff = b"--- a/foo\t2019-01-01 00:00:00\n+++ b/foo\n@@ -1 +1 @@\tfn main()\n";
+    let mut parser = DiffParser::new(&diff[..]);
+    while let Some(line) = parser.next_line() {
+        let line = line.unwrap();
+        assert_eq!(line.clone(), line);
+    }
+
+    // Lines parsed from a slice borrow from it, so copies can be kept.
+    let line = line::parse_hunk(b"@@ -1,2 +1,3 @@\tfn main()\n");
+    let kept = line.clone();
+    assert_eq!(kept, line);
+    if let DiffLine::Hunk(hi) = line {
+        let copy = hi;
+        assert_eq!(copy, hi);
+        assert_eq!(copy.context, Some(&b"fn main()"[..]));
+    } else {
+        panic!("not a hunk: {:?}", line);
+    }
+}
+
+#[test]
+fn test_writeln() {
+    let diff = b"diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1,2 +1,2 @@\tfn main()
+ foo
+-bar
+\\ No newline at end of file
++baz
+\\ No newline at end of file
+Binary files a/bin and b/bin differ
+";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut out = vec![];
+    while let Some(line) = parser.next_line() {
+        let line = line.unwrap();
+        assert!(!line.to_string().contains('\n'), "{:?}", line);
+        line.writeln(&mut out).unwrap();
+    }
+    assert_eq!(String::from_utf8_lossy(&out), String::from_utf8_lossy(diff));
+}
+
+#[test]
+fn test_reverse() {
+    fn reversed(diff: &[u8]) -> Vec<OwnedDiffLine> {
+        let mut parser = DiffParser::new(diff);
+        let mut lines = vec![];
+        while let Some(line) = parser.next_line() {
+            let line = line.expect("read error").reverse().to_owned();
+            if let (Some(OwnedDiffLine::NewFile(_)), OwnedDiffLine::OldFile(_)) =
+                (lines.last(), &line)
+            {
+                let new = lines.pop().unwrap();
+                lines.push(line);
+                lines.push(new);
+            } else {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    fn render(lines: &[OwnedDiffLine]) -> Vec<u8> {
+        let mut out = vec![];
+        for line in lines {
+            line.as_diff_line().writeln(&mut out).unwrap();
+        }
+        out
+    }
+
+    let diff = b"diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1,3 +1,2 @@
+ one
+-two
+-three
++three
+\\ No newline at end of file
+";
+
+    let lines = reversed(diff);
+    assert_eq!(
+        render(&lines),
+        &b"diff --git b/foo a/foo
+index 89abcde..1234567 100644
+--- b/foo
++++ a/foo
+@@ -1,2 +1,3 @@
+ one
++two
++three
+-three
+\\ No newline at end of file
+"[..]
+    );
+
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut original = vec![];
+    while let Some(line) = parser.next_line() {
+        original.push(line.unwrap().to_owned());
+    }
+    assert_eq!(reversed(&render(&lines)), original);
+}
+
+#[test]
+fn test_source_len() {
+    let diff = b"junk\r\n--- a\n+++ b\n@@ -1,3 +1,3 @@\n context\n-old\r\n+new\n!mod\n\\ No newline
+--- c
++++ c
+@@@ -1 -1 +1 @@@
+ +merged\n";
+
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut checked = 0;
+    while let Some(parsed) = parser.next_line_raw() {
+        let (line, raw) = parsed.unwrap();
+        if let Some(len) = line.source_len() {
+            assert_eq!(len, raw.len());
+            checked += 1;
+        }
+    }
+    assert_eq!(checked, 6);
+
+    // Context diff markers are two bytes wide, and blank lines and ed text
+    // have none.
+    for (diff, deltas) in &[
+        (
+            &b"*** a\n--- b\n***************\n*** 5,6 ****\n! five\n  six\n--- 5,6 ----\n! 5\n\n"[..],
+            4,
+        ),
+        (b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n\n-a\n+b", 3),
+    ] {
+        let mut parser = DiffParser::new(&diff[..]);
+        let mut checked = 0;
+        while let Some(parsed) = parser.next_line_raw() {
+            let (line, raw) = parsed.unwrap();
+            if line.content().is_some() {
+                assert_eq!(line.source_len(), Some(raw.len()));
+                checked += 1;
+            }
+        }
+        assert_eq!(checked, *deltas);
+    }
+
+    let mut parser = DiffParser::new(&b"2c\ntwo\n.\n"[..]).ed_format();
+    parser.next_line();
+    let (line, raw) = parser.next_line_raw().unwrap().unwrap();
+    assert_eq!(line.source_len(), Some(raw.len()));
+
+    let malformed = DiffLine::MalformedHunk {
+        expected_old: 1,
+        expected_new: 1,
+        actual_old: 0,
+        actual_new: 0,
+    };
+    assert_eq!(malformed.source_len(), None);
+}
+
+#[test]
+fn test_content() {
+    for (line, content) in &[
+        (&b"foo\n"[..], &b"foo"[..]),
+        (b"foo\r\n", b"foo"),
+        (b"foo", b"foo"),
+    ] {
+        assert_eq!(DiffLine::Context(line, 1).content(), Some(*content));
+        assert_eq!(DiffLine::Inserted(line, 1).content(), Some(*content));
+        assert_eq!(DiffLine::Deleted(line, 1).content(), Some(*content));
+        assert_eq!(DiffLine::Modified(line, 1).content(), Some(*content));
+    }
+    assert_eq!(DiffLine::Context(b"\r\n", 1).content(), Some(&b""[..]));
+    assert_eq!(DiffLine::Junk(b"foo\n").content(), None);
+}
+
+#[test]
+fn test_payload_str() {
+    assert_eq!(
+        DiffLine::Inserted(b"foo\n", 1).payload_str(),
+        Some(Ok("foo\n"))
+    );
+    assert_eq!(
+        DiffLine::Context("na\u{ef}ve\n".as_bytes(), 1).payload_str(),
+        Some(Ok("na\u{ef}ve\n"))
+    );
+    assert!(matches!(
+        DiffLine::Deleted(b"na\xefve\n", 1).payload_str(),
+        Some(Err(_))
+    ));
+    assert_eq!(DiffLine::Junk(b"foo\n").payload_str(), None);
+    assert_eq!(DiffLine::ContextHunk.payload_str(), None);
+}
+
+// Line counts from a hunk header, and how many of each we've seen so far.
+#[derive(Debug, Clone, Copy)]
+struct HunkCounts {
+    old_len: u64,
+    new_len: u64,
+    old_seen: u64,
+    new_seen: u64,
+    property: bool,
+}
+
+impl HunkCounts {
+    fn new(info: &HunkInfo<'_>, property: bool) -> Self {
+        Self {
+            old_len: info.old_line_len,
+            new_len: info.new_line_len,
+            old_seen: 0,
+            new_seen: 0,
+            property,
+        }
+    }
+
+    fn end_state(&self) -> State {
+        if self.property {
+            State::Property
+        } else {
+            State::HunkEnd
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.old_seen == self.old_len && self.new_seen == self.new_len
+    }
+
+    // The state to read the hunk's lines in, or to leave it if it's empty.
+    fn start_state(self) -> State {
+        if self.is_complete() {
+            self.end_state()
+        } else {
+            State::Hunk(self)
+        }
+    }
+
+    // Count a hunk line, or report it overrunning the header's counts.
+    fn count(&mut self, old: u64, new: u64) -> Result<(), DiffLine<'static>> {
+        if self.old_seen + old > self.old_len || self.new_seen + new > self.new_len {
+            return Err(DiffLine::MalformedHunk {
+                expected_old: self.old_len,
+                expected_new: self.new_len,
+                actual_old: self.old_seen + old,
+                actual_new: self.new_seen + new,
+            });
+        }
+
+        self.old_seen += old;
+        self.new_seen += new;
+        Ok(())
+    }
+
+    fn short(&self) -> DiffLine<'static> {
+        DiffLine::MalformedHunk {
+            expected_old: self.old_len,
+            expected_new: self.new_len,
+            actual_old: self.old_seen,
+            actual_new: self.new_seen,
+        }
+    }
+}
+
+// Line counts for each side of a combined hunk, kept outside of `State` so
+// that can stay `Copy`.
+#[derive(Clone, Debug, Default)]
+struct CombinedCounts {
+    // (length, seen) for each parent, then the result.
+    old: Vec<(u64, u64)>,
+    new: (u64, u64),
+}
+
+impl CombinedCounts {
+    fn reset(&mut self, info: &CombinedHunkInfo<'_>) {
+        self.old.clear();
+        self.old
+            .extend(info.old_ranges.iter().map(|&(_, len)| (len, 0)));
+        self.new = (info.new_line_len, 0);
+    }
+
+    fn is_complete(&self) -> bool {
+        self.new.0 == self.new.1 && self.old.iter().all(|(len, seen)| len == seen)
+    }
+
+    // A line removed from any parent only exists in the parents it was
+    // removed from.  Anything else is in the result, and in each parent it
+    // wasn't added to.
+    fn count(&mut self, markers: &[u8]) -> Result<(), ParseErrorKind> {
+        let removed = markers.contains(&b'-');
+        let counted = |marker| {
+            if removed {
+                marker == b'-'
+            } else {
+                marker == b' '
+            }
+        };
+
+        let overrun = self
+            .old
+            .iter()
+            .zip(markers)
+            .any(|(&(len, seen), &marker)| counted(marker) && seen == len)
+            || (!removed && self.new.1 == self.new.0);
+
+        if overrun {
+            return Err(ParseErrorKind::HunkTooLong);
+        }
+
+        for ((_, seen), &marker) in self.old.iter_mut().zip(markers) {
+            if counted(marker) {
+                *seen += 1;
+            }
+        }
+
+        if !removed {
+            self.new.1 += 1;
+        }
+
+        Ok(())
+    }
+}
+
+// The malformed headers a parser has been asked to accept.
+#[derive(Clone, Copy, Default)]
+struct Leniency {
+    spaced_ranges: bool,
+    unspaced_headers: bool,
+}
+
+// Parse a line following a file header or a completed hunk, which may be
+// another hunk, or the start of a new file.
+fn after_hunk<'a>(
+    state: &mut State,
+    combined: &mut CombinedCounts,
+    leniency: Leniency,
+    line: &'a [u8],
+) -> DiffLine<'a> {
+    // The usual parser skips over the bits of a spaced out range it can't
+    // read, rather than failing, so try the spaced parser first.
+    let line = if leniency.spaced_ranges {
+        match parse_spaced_hunk(line) {
+            DiffLine::Junk(line) => parse_hunk(line),
+            hunk => hunk,
+        }
+    } else {
+        parse_hunk(line)
+    };
+    let line = match line {
+        DiffLine::Junk(line) => parse_combined_hunk(line),
+        hunk => hunk,
+    };
+
+    match line {
+        DiffLine::Hunk(ref info) => *state = HunkCounts::new(info, false).start_state(),
+        DiffLine::CombinedHunk(ref info) => {
+            combined.reset(info);
+            *state = if combined.is_complete() {
+                State::HunkEnd
+            } else {
+                State::CombinedHunk
+            };
+        }
+        DiffLine::Junk(line) => return start_file(state, State::Junk, leniency, line),
+        _ => unreachable!(),
+    }
+
+    line
+}
+
+// The file a `file_events()` parser is in, and which of its events are due.
+#[derive(Clone, Default)]
+struct FileEvents {
+    file: Option<OwnedFileInfo>,
+    start: bool,
+    open: bool,
+}
+
+#[derive(Clone, Copy)]
+enum FileEvent {
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Junk,
+    GitHeader,
+    OldFile,
+    NewFile,
+    Hunk(HunkCounts),
+    CombinedHunk,
+    HunkEnd,
+    EdText,
+    SvnIndex,
+    Property,
+    ContextOldFile,
+    ContextHunk,
+    ContextDelta(Side),
+    BinaryPatch,
+    BinaryData,
+}
+
+// Parse a line which may start a new file, moving the state machine into it.
+// Unrecognised lines leave it in `fallback`.
+fn start_file<'a>(
+    state: &mut State,
+    fallback: State,
+    leniency: Leniency,
+    line: &'a [u8],
+) -> DiffLine<'a> {
+    let line = match parse_file_start(line) {
+        DiffLine::Junk(line) if leniency.unspaced_headers => parse_unspaced_old_file(line),
+        line => line,
+    };
+
+    *state = match line {
+        DiffLine::GitHeader { .. } => State::GitHeader,
+        DiffLine::OldFile(_) => State::OldFile,
+        DiffLine::SvnIndex(_) => State::SvnIndex,
+        DiffLine::PropertyChanges(_) => State::Property,
+        DiffLine::Junk(_) => fallback,
+        _ => State::Junk,
+    };
+
+    line
+}
+
+// Context diffs start with a `*** ` header instead, but can be mixed with
+// anything else that starts a file.
+fn start_context_file<'a>(state: &mut State, leniency: Leniency, line: &'a [u8]) -> DiffLine<'a> {
+    match parse_context_old_file(line) {
+        DiffLine::Junk(line) => start_file(state, State::Junk, leniency, line),
+        old => {
+            *state = State::ContextOldFile;
+            old
+        }
+    }
+}
+
+// Context diff hunk lines which aren't part of either section.
+fn context_hunk<'a>(state: &mut State, leniency: Leniency, line: &'a [u8]) -> DiffLine<'a> {
+    let line = parse_context_hunk(line);
+
+    match line {
+        DiffLine::ContextHunk => *state = State::ContextHunk,
+        DiffLine::OldRange(..) => *state = State::ContextDelta(Side::Old),
+        DiffLine::NewRange(..) => *state = State::ContextDelta(Side::New),
+        DiffLine::Junk(line) => return start_context_file(state, leniency, line),
+        _ => unreachable!(),
+    }
+
+    line
+}
+
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+
+// Only these are stripped from the ends of lines, by `line::chomp()`.
+pub(crate) fn check_terminator(byte: u8) {
+    assert!(
+        matches!(byte, b'\n' | b'\0'),
+        "unsupported line terminator {:?}",
+        byte as char
+    );
+}
+
+// Pass over the rest of a line, returning how many bytes it had.
+fn skip_line<R: BufRead>(r: &mut R, terminator: u8) -> io::Result<u64> {
+    let mut skipped = 0;
+    loop {
+        let buf = r.fill_buf()?;
+        let (len, done) = match buf.iter().position(|&b| b == terminator) {
+            Some(pos) => (pos + 1, true),
+            None => (buf.len(), buf.is_empty()),
+        };
+        r.consume(len);
+        skipped += len as u64;
+        if done {
+            return Ok(skipped);
+        }
+    }
+}
+
+pub struct DiffParser<R> {
+    inner: R,
+    state: State,
+    strip: usize,
+    strict: bool,
+    format: Option<DiffFormat>,
+    terminator: u8,
+    max_line_len: Option<usize>,
+    line_number: u64,
+    byte_offset: u64,
+    reparse: bool,
+    // The side of the last line if it was part of a hunk.
+    after_delta: Option<Side>,
+    combined: CombinedCounts,
+    filter: Option<PathFilter>,
+    // A line to parse next, before reading any more.
+    unread: Option<Vec<u8>>,
+    verbatim: bool,
+    tab_context: bool,
+    leniency: Leniency,
+    // Whether no line but junk has been seen yet.
+    preamble: bool,
+    skip_preamble: bool,
+    counting: bool,
+    stats: DiffStat,
+    junk_count: u64,
+    // Whether the last line returned was junk, which later junk runs on from.
+    in_junk: bool,
+    // A copy of the last line returned, for `write_line()`.
+    last: Option<OwnedDiffLine>,
+    // Whether the line being parsed is only being peeked at.
+    peeking: bool,
+    file_mode: Option<FileModeChange>,
+    path: Option<Vec<u8>>,
+    final_newline: Option<bool>,
+    // Whether the last hunk had no lines on its new side, at line 0.
+    new_side_empty: bool,
+    // The old and new line numbers of the next line of the current hunk, and
+    // of the last line returned.
+    hunk_cursor: Option<(u64, u64)>,
+    line_numbers: Option<(u64, u64)>,
+    file_events: Option<FileEvents>,
+    // The old commit from a submodule's hunk, then both once it's complete
+    // and a `Submodule` is due.
+    subproject: Option<Vec<u8>>,
+    submodule: Option<(Vec<u8>, Vec<u8>)>,
+    submodule_due: bool,
+    pub line: Vec<u8>,
+}
+
+impl<R: BufRead> DiffParser<R> {
+    /// A parser with the default options, to be changed by the methods
+    /// below or set up with a `DiffParserBuilder`.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: State::Junk,
+            strip: 0,
+            strict: false,
+            format: None,
+            terminator: b'\n',
+            line_number: 0,
+            byte_offset: 0,
+            max_line_len: None,
+            reparse: false,
+            after_delta: None,
+            combined: CombinedCounts::default(),
+            filter: None,
+            unread: None,
+            verbatim: false,
+            tab_context: false,
+            leniency: Leniency::default(),
+            preamble: true,
+            skip_preamble: false,
+            counting: false,
+            stats: DiffStat::default(),
+            junk_count: 0,
+            in_junk: false,
+            last: None,
+            peeking: false,
+            file_mode: None,
+            path: None,
+            final_newline: None,
+            new_side_empty: false,
+            hunk_cursor: None,
+            line_numbers: None,
+            file_events: None,
+            subproject: None,
+            submodule: None,
+            submodule_due: false,
+            line: vec![],
+        }
+    }
+
+    /// Strip `levels` leading path components from `OldFile` and `NewFile`
+    /// filenames, like `patch -p`.  Filenames with fewer components are
+    /// reduced to their basename, and `/dev/null` is left alone.
+    pub fn strip_prefix(mut self, levels: usize) -> Self {
+        self.strip = levels;
+        self
+    }
+
+    /// Return a `ParseError` for lines which can't appear where they are,
+    /// such as a `---` header without a `+++`, or a hunk cut short, instead of
+    /// passing them on as `Junk`.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Split lines on `byte` rather than `\n`.  Lines keep their terminator
+    /// like they do their newline, and it's stripped from headers and
+    /// `content()` the same way.  Either way a trailing NUL or newline is
+    /// taken to be a terminator.
+    ///
+    /// Panics if `byte` isn't a NUL or a newline, as no other terminator is
+    /// recognised.
+    pub fn with_terminator(mut self, byte: u8) -> Self {
+        check_terminator(byte);
+        self.terminator = byte;
+        self
+    }
+
+    /// Return a `LineTooLong` error for a line of more than `bytes` bytes,
+    /// not counting its terminator, rather than reading all of it into
+    /// memory.  Only the start of it is kept for the error, and the rest is
+    /// skipped, so the parser can carry on with the next line.
+    pub fn max_line_len(mut self, bytes: usize) -> Self {
+        self.max_line_len = Some(bytes);
+        self
+    }
+
+    /// Keep a copy of each line returned, so `write_line()` can tell which
+    /// have been changed and write the rest exactly as they were read.
+    pub fn verbatim(mut self) -> Self {
+        self.verbatim = true;
+        self
+    }
+
+    /// Return a `FileStart` after each `+++` header, and a `FileEnd` after
+    /// the last line of its file, before whatever ends it.
+    pub fn file_events(mut self) -> Self {
+        self.file_events = Some(FileEvents::default());
+        self
+    }
+
+    /// Take hunk lines starting with a tab as `Context`, from tools that use
+    /// one instead of a space.  The tab is left out of the payload.
+    pub fn tab_context(mut self) -> Self {
+        self.tab_context = true;
+        self
+    }
+
+    /// Accept hunk headers with whitespace around the numbers in their
+    /// ranges, like `@@ -12 , 34 +56,78 @@`, as found in some hand-edited
+    /// patches.  Without this the numbers after the whitespace are skipped,
+    /// so the hunk is likely to come out malformed.
+    pub fn spaced_ranges(mut self) -> Self {
+        self.leniency.spaced_ranges = true;
+        self
+    }
+
+    /// Accept `---` and `+++` headers without the space after them, like
+    /// `---foo`, as some tools write them.  Without this they're `Junk`.
+    pub fn unspaced_headers(mut self) -> Self {
+        self.leniency.unspaced_headers = true;
+        self
+    }
+
+    /// Leave out `Preamble` lines, so the first line returned is the first
+    /// header, such as a `diff --git` or `---` line.
+    pub fn skip_preamble(mut self) -> Self {
+        self.skip_preamble = true;
+        self
+    }
+
+    /// Expect a context diff, as from `diff -c`, rather than detecting it.
+    /// The lines of each hunk are given as they appear, so context lines are
+    /// repeated in both its old and new sections.
+    pub fn context_format(mut self) -> Self {
+        self.format = Some(DiffFormat::Context);
+        self
+    }
+
+    /// Read an ed script, as from `diff -e`, rather than a diff.  Its text
+    /// lines are given as `Inserted`.
+    pub fn ed_format(mut self) -> Self {
+        self.format = Some(DiffFormat::Ed);
+        self
+    }
+
+    /// The format of the diff, as detected from the first file header, or
+    /// `None` before one's been read.  Context diff headers aren't looked for
+    /// once a diff is found to be unified or git.
+    pub fn format(&self) -> Option<DiffFormat> {
+        self.format
+    }
+
+    /// Where the parser is in the diff, after the last line returned, to
+    /// help tell why a diff parses the way it does.
+    pub fn state(&self) -> ParserState {
+        match self.state {
+            State::Junk if self.preamble => ParserState::Preamble,
+            State::Junk => ParserState::BetweenFiles,
+            State::GitHeader => ParserState::GitHeader,
+            State::SvnIndex => ParserState::SvnHeader,
+            State::Property => ParserState::Properties,
+            State::OldFile | State::ContextOldFile => ParserState::ExpectNewFile,
+            State::NewFile => ParserState::ExpectHunk,
+            State::Hunk(counts) => ParserState::InHunk {
+                old_left: counts.old_len - counts.old_seen,
+                new_left: counts.new_len - counts.new_seen,
+            },
+            State::CombinedHunk => ParserState::InCombinedHunk,
+            State::ContextHunk | State::ContextDelta(_) => ParserState::InContextHunk,
+            State::HunkEnd => ParserState::AfterHunk,
+            State::EdText => ParserState::InEdText,
+            State::BinaryPatch | State::BinaryData => ParserState::InBinaryPatch,
+        }
+    }
+
+    /// Whether the last line returned opened a hunk or was part of one, and
+    /// more of its lines are expected.
+    pub fn in_hunk(&self) -> bool {
+        matches!(self.state, State::Hunk(_) | State::CombinedHunk)
+    }
+
+    /// The number of old and new lines still expected in the current `@@`
+    /// hunk, or `None` outside of one.
+    pub fn remaining_hunk_lines(&self) -> Option<(u64, u64)> {
+        match self.state {
+            State::Hunk(counts) => Some((
+                counts.old_len - counts.old_seen,
+                counts.new_len - counts.new_seen,
+            )),
+            _ => None,
+        }
+    }
+
+    /// The 1-based number of the line most recently read, or 0 before the
+    /// first.  Each line counts once regardless of its line ending.
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// The modes of the current file, once its git headers give any.  A mode
+    /// from the `index` line is both the old and the new one.
+    pub fn current_file_mode(&self) -> Option<FileModeChange> {
+        self.file_mode
+    }
+
+    /// The new path of the current file, from its `+++` header, or its
+    /// `---` header if it's been deleted.  A git diff's file has one from
+    /// its `diff --git` header until then.  Paths are stripped as
+    /// `strip_prefix()` says.
+    pub fn current_path(&self) -> Option<&[u8]> {
+        self.path.as_deref()
+    }
+
+    /// Whether the current file's new side ends with a newline, as far as its
+    /// hunks tell.  It doesn't if a `NoNewlineAtEof` followed its last line.
+    /// It does if a hunk showed its last line without one, which is known
+    /// when the hunk shows the whole file, or reaches the end of the old one.
+    /// Otherwise, and for deleted files, this is `None`.
+    pub fn new_side_has_final_newline(&self) -> Option<bool> {
+        self.final_newline
+    }
+
+    /// The old and new line numbers of the line just returned, if it's a line
+    /// of a `@@` hunk, counted from the hunk's header.  An `Inserted` line
+    /// has the number of the old line that would follow it, and a `Deleted`
+    /// line that of the new line following it.  A `NoNewlineAtEof` keeps the
+    /// numbers of the line before it.
+    pub fn current_line_numbers(&self) -> Option<(u64, u64)> {
+        self.line_numbers
+    }
+
+    /// The bytes of the line most recently read, including its terminator.
+    /// This is the line the last `next_line()` was parsed from, which for a
+    /// `MalformedHunk` is the line that ended the hunk.  It's empty at the
+    /// end of the input.  It borrows the parser, so can only be called once
+    /// the line's dropped; `next_line_raw()` returns both together.
+    pub fn raw(&self) -> &[u8] {
+        &self.line
+    }
+
+    /// The number of `Junk` lines returned where more of a file was
+    /// expected, such as garbage in the middle of a hunk, which can be a sign
+    /// of a corrupt diff.  Of a run of junk lines, only the first counts.
+    pub fn junk_count(&self) -> u64 {
+        self.junk_count
+    }
+
+    /// The offset in bytes of the start of the line most recently read, which
+    /// spans `byte_offset()..byte_offset() + line.len()`.  After the end of
+    /// the input, this is its total length.
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+
+    /// Write `line` to `out`.  If it's the line last returned, unchanged, and
+    /// the parser is `verbatim()`, the bytes it was parsed from are written.
+    /// Otherwise the line's `Display` is, followed by a line terminator if
+    /// it doesn't have one.  Lines the parser makes up, like
+    /// `MalformedHunk`s, aren't written at all.
+    pub fn write_line<W: Write>(&self, line: &DiffLine, out: &mut W) -> io::Result<()> {
+        if line.is_made_up() {
+            return Ok(());
+        }
+
+        if let Some(last) = &self.last {
+            if last.as_diff_line() == *line {
+                return out.write_all(&self.line);
+            }
+        }
+
+        let text = line.to_string();
+        out.write_all(text.as_bytes())?;
+        if !text.as_bytes().ends_with(&[self.terminator]) {
+            out.write_all(&[self.terminator])?;
+        }
+        Ok(())
+    }
+
+    // Whether a line read was cut short at `max_line_len`.
+    pub(crate) fn is_too_long(&self, line: &[u8]) -> bool {
+        match self.max_line_len {
+            Some(max) => line.len() > max && line.last() != Some(&self.terminator),
+            None => false,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> io::Error {
+        ParseError {
+            kind,
+            line_number: self.line_number,
+            line: self.line.clone(),
+        }
+        .into()
+    }
+
+    pub fn next_line(&mut self) -> Option<io::Result<DiffLine<'_>>> {
+        self.next_line_raw()
+            .map(|parsed| parsed.map(|(line, _)| line))
+    }
+
+    /// Return the next line along with the bytes it was parsed from,
+    /// including its terminator, as `raw()` would once the line's dropped.
+    /// Lines `DiffParser` makes up, like `MalformedHunk`, come with none.
+    pub fn next_line_raw(&mut self) -> Option<io::Result<(DiffLine<'_>, &[u8])>> {
+        if self.submodule_due {
+            self.submodule_due = false;
+            return self.submodule_line().map(|line| Ok((line, &[][..])));
+        }
+
+        if let Some(event) = self.take_file_event() {
+            return self.event_line(event).map(|line| Ok((line, &[][..])));
+        }
+
+        if let Err(err) = self.skip_lines() {
+            return Some(Err(err));
+        }
+        self.parse_line_raw().map(|parsed| {
+            parsed.map(|(line, raw)| {
+                let raw = if line.is_made_up() { &[][..] } else { raw };
+                (line, raw)
+            })
+        })
+    }
+
+    /// Return the line `next_line()` will, without consuming it.  Nothing
+    /// but `line_number()` and `byte_offset()` moves on until it is.
+    pub fn peek(&mut self) -> Option<io::Result<DiffLine<'_>>> {
+        if self.submodule_due {
+            return self.submodule_line().map(Ok);
+        }
+
+        if let Some(event) = self.file_event() {
+            return self.event_line(event).map(Ok);
+        }
+        self.peek_line()
+    }
+
+    fn peek_line(&mut self) -> Option<io::Result<DiffLine<'_>>> {
+        if let Err(err) = self.skip_lines() {
+            return Some(Err(err));
+        }
+        self.peeking = true;
+        self.parse_line()
+    }
+
+    // The file event due before the next line, if any.  A file ends at
+    // anything that isn't part of it, including the end of input.
+    fn file_event(&mut self) -> Option<FileEvent> {
+        match &self.file_events {
+            Some(events) if events.start => return Some(FileEvent::Start),
+            Some(events) if events.open => (),
+            _ => return None,
+        }
+
+        let ends = match self.peek_line() {
+            Some(Ok(line)) => matches!(
+                line,
+                DiffLine::GitHeader { .. }
+                    | DiffLine::OldFile(_)
+                    | DiffLine::Binaries(..)
+                    | DiffLine::OnlyIn { .. }
+                    | DiffLine::FilesDiffer(..)
+                    | DiffLine::SvnIndex(_)
+                    | DiffLine::PropertyChanges(_)
+                    | DiffLine::Junk(_)
+            ),
+            // Leave an error to be returned by parsing the line again.
+            Some(Err(_)) => false,
+            None => true,
+        };
+        if ends {
+            Some(FileEvent::End)
+        } else {
+            None
+        }
+    }
+
+    // Like `file_event()`, but the event won't be due again.
+    pub(crate) fn take_file_event(&mut self) -> Option<FileEvent> {
+        let event = self.file_event()?;
+        if let Some(events) = &mut self.file_events {
+            match event {
+                FileEvent::Start => events.start = false,
+                FileEvent::End => events.open = false,
+            }
+        }
+        Some(event)
+    }
+
+    pub(crate) fn submodule_line(&self) -> Option<DiffLine<'_>> {
+        let (old, new) = self.submodule.as_ref()?;
+        Some(DiffLine::Submodule { old, new })
+    }
+
+    pub(crate) fn event_line(&self, event: FileEvent) -> Option<DiffLine<'_>> {
+        let fi = self.file_events.as_ref()?.file.as_ref()?.as_file_info();
+        Some(match event {
+            FileEvent::Start => DiffLine::FileStart(fi),
+            FileEvent::End => DiffLine::FileEnd(fi),
+        })
+    }
+
+    // Skip past any preamble and filtered out lines.
+    fn skip_lines(&mut self) -> io::Result<()> {
+        if self.skip_preamble {
+            while self.preamble && self.skip_preamble_line()? {}
+        }
+
+        if self.filter.is_some() {
+            while self.skip_line()? {}
+        }
+
+        Ok(())
+    }
+
+    // Parse a line, returning whether it's part of the preamble.  Any other
+    // line is set to be parsed again, like in `skip_line()`.
+    fn skip_preamble_line(&mut self) -> io::Result<bool> {
+        let (state, stats) = (self.state, self.stats.clone());
+
+        let skip = match self.parse_line() {
+            Some(Ok(DiffLine::Preamble(_))) => true,
+            Some(Err(err)) => return Err(err),
+            _ => false,
+        };
+
+        if !skip {
+            self.state = state;
+            self.after_delta = None;
+            self.reparse = !self.line.is_empty();
+        }
+        self.stats = stats;
+        Ok(skip)
+    }
+
+    fn parse_line(&mut self) -> Option<io::Result<DiffLine<'_>>> {
+        self.parse_line_raw()
+            .map(|parsed| parsed.map(|(line, _)| line))
+    }
+
+    fn parse_line_raw(&mut self) -> Option<io::Result<(DiffLine<'_>, &[u8])>> {
+        // A peeked line is parsed again when it's consumed, so leaves things
+        // as they were.
+        let peeked = if std::mem::replace(&mut self.peeking, false) {
+            Some((self.state, self.after_delta, self.combined.clone()))
+        } else {
+            None
+        };
+
+        if self.reparse {
+            // The last line ended a hunk early or late, so we reported that
+            // instead, and now parse it again outside of the hunk.  Or it's
+            // been let through a filter, and needs parsing again to return.
+            self.reparse = false;
+        } else {
+            self.byte_offset += self.line.len() as u64;
+            self.line.clear();
+
+            let from_inner = self.unread.is_none();
+            let parsed = match self.unread.take() {
+                Some(line) => {
+                    self.line = line;
+                    Ok(self.line.len())
+                }
+                None => match self.max_line_len {
+                    Some(max) => self
+                        .inner
+                        .by_ref()
+                        .take(max as u64 + 1)
+                        .read_until(self.terminator, &mut self.line),
+                    None => self.inner.read_until(self.terminator, &mut self.line),
+                },
+            };
+
+            if self.is_too_long(&self.line) {
+                if from_inner {
+                    match skip_line(&mut self.inner, self.terminator) {
+                        Ok(skipped) => self.byte_offset += skipped,
+                        Err(err) => return Some(Err(err)),
+                    }
+                }
+                self.line_number += 1;
+                return Some(Err(self.error(ParseErrorKind::LineTooLong)));
+            }
+
+            match parsed {
+                Ok(0) => {
+                    let state = std::mem::replace(&mut self.state, State::Junk);
+                    if peeked.is_some() {
+                        self.state = state;
+                    }
+                    return match (self.strict, state) {
+                        (
+                            true,
+                            State::OldFile | State::NewFile | State::Hunk(_) | State::CombinedHunk,
+                        ) => Some(Err(self.error(ParseErrorKind::UnexpectedEof))),
+                        (false, State::Hunk(counts)) => Some(Ok((counts.short(), &self.line[..]))),
+                        _ => None,
+                    };
+                }
+                Ok(_) => self.line_number += 1,
+                Err(err) => return Some(Err(err)),
+            };
+        }
+
+        let prior = self.state;
+        let mut cut_short = None;
+        let line = match self.state {
+            // The marker can follow the last line of a hunk, after we've left it.
+            _ if self.after_delta.is_some()
+                && self.line.starts_with(b"\\")
+                && !matches!(self.state, State::EdText) =>
+            {
+                DiffLine::NoNewlineAtEof {
+                    side: self.after_delta.unwrap_or(Side::Both),
+                    marker: &self.line,
+                }
+            }
+            State::Junk if self.format == Some(DiffFormat::Ed) => {
+                let line = parse_ed_command(&self.line);
+                if let DiffLine::EdChange(..) | DiffLine::EdAppend(_) = line {
+                    self.state = State::EdText;
+                }
+                line
+            }
+            State::EdText if chomp(&self.line) == b"." => {
+                self.state = State::Junk;
+                DiffLine::EdEnd
+            }
+            State::EdText => DiffLine::Inserted(&self.line, 0),
+            State::Junk if matches!(self.format, None | Some(DiffFormat::Context)) => {
+                start_context_file(&mut self.state, self.leniency, &self.line)
+            }
+            State::Junk => start_file(&mut self.state, State::Junk, self.leniency, &self.line),
+            // Extended headers we don't understand are left as Junk without
+            // losing track of the file block.
+            State::GitHeader => match parse_extended_header(&self.line) {
+                DiffLine::Junk(_) => {
+                    start_file(&mut self.state, State::GitHeader, self.leniency, &self.line)
+                }
+                DiffLine::GitBinaryPatch => {
+                    self.state = State::BinaryPatch;
+                    DiffLine::GitBinaryPatch
+                }
+                header => header,
+            },
+            State::BinaryPatch => match parse_binary_patch(&self.line) {
+                DiffLine::Junk(_) => {
+                    start_file(&mut self.state, State::Junk, self.leniency, &self.line)
+                }
+                line => {
+                    self.state = State::BinaryData;
+                    line
+                }
+            },
+            State::BinaryData => match parse_binary_data(&self.line) {
+                DiffLine::Junk(_) => {
+                    start_file(&mut self.state, State::Junk, self.leniency, &self.line)
+                }
+                DiffLine::BinaryData(data) if data.is_empty() => {
+                    self.state = State::BinaryPatch;
+                    DiffLine::BinaryData(data)
+                }
+                line => line,
+            },
+            State::OldFile => {
+                let line = match parse_new_file(&self.line[..]) {
+                    DiffLine::Junk(line) if self.leniency.unspaced_headers => {
+                        parse_unspaced_new_file(line)
+                    }
+                    line => line,
+                };
+
+                if let DiffLine::NewFile(_) = line {
+                    self.state = State::NewFile;
+                } else {
+                    self.state = State::Junk;
+                }
+
+                line
+            }
+            State::NewFile | State::HunkEnd => after_hunk(
+                &mut self.state,
+                &mut self.combined,
+                self.leniency,
+                &self.line,
+            ),
+            State::ContextOldFile => {
+                let line = parse_context_new_file(&self.line);
+
+                if let DiffLine::NewFile(_) = line {
+                    self.state = State::ContextHunk;
+                } else {
+                    self.state = State::Junk;
+                }
+
+                line
+            }
+            State::ContextHunk => context_hunk(&mut self.state, self.leniency, &self.line),
+            State::ContextDelta(_) => match parse_context_delta(&self.line) {
+                DiffLine::Junk(_) => context_hunk(&mut self.state, self.leniency, &self.line),
+                line => line,
+            },
+            State::SvnIndex => match parse_svn_separator(&self.line) {
+                DiffLine::Junk(_) => {
+                    start_file(&mut self.state, State::Junk, self.leniency, &self.line)
+                }
+                sep => {
+                    self.state = State::Junk;
+                    sep
+                }
+            },
+            State::Property => match parse_property(&self.line) {
+                DiffLine::Junk(_) => {
+                    let line = parse_prop_hunk(&self.line);
+
+                    if let DiffLine::PropHunk(ref info) = line {
+                        self.state = HunkCounts::new(info, true).start_state();
+                        line
+                    } else {
+                        start_file(&mut self.state, State::Property, self.leniency, &self.line)
+                    }
+                }
+                property => property,
+            },
+            State::Hunk(ref mut counts) => {
+                let line = match parse_delta(&self.line[..]) {
+                    DiffLine::Junk(l) if self.tab_context && l.starts_with(b"\t") => {
+                        DiffLine::Context(&l[1..], 1)
+                    }
+                    line => line,
+                };
+                let counted = match line {
+                    DiffLine::Context(..) | DiffLine::Modified(..) => counts.count(1, 1),
+                    DiffLine::Inserted(..) => counts.count(0, 1),
+                    DiffLine::Deleted(..) => counts.count(1, 0),
+                    DiffLine::NoNewlineAtEof { .. } => Ok(()),
+                    DiffLine::Junk(_) => Err(counts.short()),
+                    _ => unreachable!(),
+                };
+
+                match counted {
+                    Ok(()) => {
+                        if counts.is_complete() {
+                            self.state = counts.end_state();
+                        }
+                        line
+                    }
+                    Err(malformed) => {
+                        self.state = counts.end_state();
+                        self.reparse = true;
+                        malformed
+                    }
+                }
+            }
+            // There's no MalformedHunk for combined diffs, so a misplaced line
+            // just ends the hunk.
+            State::CombinedHunk => {
+                let line = parse_combined_delta(&self.line, self.combined.old.len());
+                let counted = match line {
+                    DiffLine::CombinedDelta { markers, .. } => self.combined.count(markers),
+                    DiffLine::NoNewlineAtEof { .. } => Ok(()),
+                    _ => Err(ParseErrorKind::ExpectedHunkLine),
+                };
+
+                match counted {
+                    Ok(()) => {
+                        if self.combined.is_complete() {
+                            self.state = State::HunkEnd;
+                        }
+                        line
+                    }
+                    Err(kind) => {
+                        cut_short = Some(kind);
+                        after_hunk(
+                            &mut self.state,
+                            &mut self.combined,
+                            self.leniency,
+                            &self.line,
+                        )
+                    }
+                }
+            }
+        };
+
+        // Context diffs give each side's lines separately.
+        let section = match prior {
+            State::ContextDelta(side) => side,
+            _ => Side::Both,
+        };
+        self.after_delta = match line {
+            DiffLine::Context(..) | DiffLine::Modified(..) => Some(section),
+            DiffLine::Inserted(..) => Some(Side::New),
+            DiffLine::Deleted(..) => Some(Side::Old),
+            DiffLine::CombinedDelta { markers, .. } if markers.contains(&b'-') => Some(Side::Old),
+            DiffLine::CombinedDelta { markers, .. } if markers.contains(&b'+') => Some(Side::New),
+            DiffLine::CombinedDelta { .. } => Some(Side::Both),
+            _ => None,
+        };
+
+        if self.format.is_none() && peeked.is_none() {
+            self.format = match (prior, &line) {
+                (_, DiffLine::GitHeader { .. }) => Some(DiffFormat::Git),
+                (State::OldFile, DiffLine::NewFile(_)) => Some(DiffFormat::Unified),
+                (State::ContextOldFile, DiffLine::NewFile(_)) => Some(DiffFormat::Context),
+                _ => None,
+            };
+        }
+
+        if self.strict {
+            let unexpected = match (prior, &line) {
+                (State::OldFile, DiffLine::NewFile(_)) => None,
+                (State::OldFile, _) => Some(ParseErrorKind::ExpectedNewFile),
+                (State::NewFile, DiffLine::Hunk(_) | DiffLine::CombinedHunk(_)) => None,
+                (State::NewFile, _) => Some(ParseErrorKind::ExpectedHunk),
+                (
+                    State::Hunk(_),
+                    DiffLine::MalformedHunk {
+                        expected_old,
+                        expected_new,
+                        actual_old,
+                        actual_new,
+                    },
+                ) if actual_old > expected_old || actual_new > expected_new => {
+                    Some(ParseErrorKind::HunkTooLong)
+                }
+                (State::Hunk(_), DiffLine::MalformedHunk { .. }) => {
+                    Some(ParseErrorKind::ExpectedHunkLine)
+                }
+                (State::CombinedHunk, _) => cut_short,
+                _ => None,
+            };
+
+            if let Some(kind) = unexpected {
+                self.reparse = false;
+                if let Some((state, after_delta, combined)) = peeked {
+                    self.state = state;
+                    self.after_delta = after_delta;
+                    self.combined = combined;
+                    self.reparse = true;
+                }
+                return Some(Err(self.error(kind)));
+            }
+        }
+
+        let line = match line {
+            DiffLine::OldFile(fi) => DiffLine::OldFile(fi.strip(self.strip)),
+            DiffLine::NewFile(fi) => DiffLine::NewFile(fi.strip(self.strip)),
+            DiffLine::Junk(l) if self.preamble => DiffLine::Preamble(l),
+            line => line,
+        };
+
+        if let Some((state, after_delta, combined)) = peeked {
+            self.state = state;
+            self.after_delta = after_delta;
+            self.combined = combined;
+            self.reparse = true;
+            return Some(Ok((line, &self.line)));
+        }
+
+        self.preamble &= matches!(line, DiffLine::Preamble(_));
+
+        // A submodule's hunk has just its old and new commits.
+        self.subproject = match (prior, &line) {
+            (State::Hunk(counts), DiffLine::Deleted(l, _))
+                if (counts.old_len, counts.new_len, counts.old_seen) == (1, 1, 0) =>
+            {
+                parse_subproject(l).map(<[u8]>::to_vec)
+            }
+            (State::Hunk(counts), DiffLine::Inserted(l, _))
+                if (counts.old_len, counts.new_len, counts.old_seen) == (1, 1, 1) =>
+            {
+                if let (Some(old), Some(new)) = (self.subproject.take(), parse_subproject(l)) {
+                    self.submodule = Some((old, new.to_vec()));
+                    self.submodule_due = true;
+                }
+                None
+            }
+            _ => None,
+        };
+
+        if let (Some(events), DiffLine::NewFile(fi)) = (&mut self.file_events, &line) {
+            *events = FileEvents {
+                file: Some(fi.into()),
+                start: true,
+                open: true,
+            };
+        }
+
+        self.line_numbers = match (&line, &mut self.hunk_cursor) {
+            (DiffLine::Hunk(hi), cursor) => {
+                *cursor = Some((hi.old_line_no, hi.new_line_no));
+                None
+            }
+            (DiffLine::Context(..) | DiffLine::Modified(..), Some((old, new))) => {
+                let numbers = (*old, *new);
+                *old += 1;
+                *new += 1;
+                Some(numbers)
+            }
+            (DiffLine::Deleted(..), Some((old, new))) => {
+                let numbers = (*old, *new);
+                *old += 1;
+                Some(numbers)
+            }
+            (DiffLine::Inserted(..), Some((old, new))) => {
+                let numbers = (*old, *new);
+                *new += 1;
+                Some(numbers)
+            }
+            (DiffLine::NoNewlineAtEof { .. }, Some(_)) => self.line_numbers,
+            (_, cursor) => {
+                *cursor = None;
+                None
+            }
+        };
+
+        match line {
+            DiffLine::GitHeader { new_path, .. } => {
+                self.file_mode = None;
+                self.path = Some(strip_path(new_path, self.strip).to_vec());
+                self.final_newline = None;
+            }
+            DiffLine::OldFile(ref fi) if !matches!(prior, State::GitHeader) => {
+                self.file_mode = None;
+                self.path = Some(fi.filename.to_vec());
+                self.final_newline = None;
+            }
+            DiffLine::NewFile(ref fi) if !fi.is_dev_null() => {
+                self.path = Some(fi.filename.to_vec());
+            }
+            DiffLine::Index {
+                mode: Some(mode), ..
+            } => {
+                self.file_mode = parse_mode(mode, b"").map(|mode| FileModeChange {
+                    old: Some(mode),
+                    new: Some(mode),
+                })
+            }
+            DiffLine::NewFileMode(mode) => {
+                self.file_mode = Some(FileModeChange {
+                    old: None,
+                    new: Some(mode),
+                })
+            }
+            DiffLine::DeletedFileMode(mode) => {
+                self.file_mode = Some(FileModeChange {
+                    old: Some(mode),
+                    new: None,
+                })
+            }
+            DiffLine::OldMode(mode) => {
+                self.file_mode.get_or_insert_with(Default::default).old = Some(mode)
+            }
+            DiffLine::NewMode(mode) => {
+                self.file_mode.get_or_insert_with(Default::default).new = Some(mode)
+            }
+            DiffLine::Hunk(ref hi) => {
+                self.new_side_empty = hi.new_line_no == 0 && hi.new_line_len == 0;
+                // A hunk from an empty old file shows the whole new one.
+                if hi.old_line_no == 0 && hi.old_line_len == 0 && hi.new_line_len > 0 {
+                    self.final_newline = Some(true);
+                }
+            }
+            // A hunk reaching the end of the old file reaches the end of the
+            // new one too, which has its newline unless a marker follows.
+            DiffLine::NoNewlineAtEof {
+                side: Side::Old, ..
+            } if !self.new_side_empty => {
+                self.final_newline.get_or_insert(true);
+            }
+            DiffLine::NoNewlineAtEof {
+                side: Side::New | Side::Both,
+                ..
+            } => self.final_newline = Some(false),
+            _ => (),
+        }
+
+        if self.counting {
+            self.stats.accumulate(&line);
+        }
+
+        let junk = matches!(line, DiffLine::Junk(_));
+        if junk && !self.in_junk && !matches!(prior, State::Junk) {
+            self.junk_count += 1;
+        }
+        self.in_junk = junk;
+
+        if self.verbatim {
+            self.last = Some((&line).into());
+        }
+
+        Some(Ok((line, &self.line)))
+    }
+}
+
+#[test]
+fn test_state() {
+    let diff = b"From: someone\n\n--- a\n+++ b\n@@ -1,2 +1 @@\n-foo\n-bar\n+baz\n@@ -5 +4 @@\n-x\n+y\njunk\n";
+    let mut parser = DiffParser::new(&diff[..]);
+    assert_eq!(parser.state(), ParserState::Preamble);
+
+    let mut states = vec![];
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+        states.push(parser.state());
+    }
+
+    let in_hunk = |old_left, new_left| ParserState::InHunk { old_left, new_left };
+    assert_eq!(
+        states,
+        [
+            ParserState::Preamble,
+            ParserState::Preamble,
+            ParserState::ExpectNewFile,
+            ParserState::ExpectHunk,
+            in_hunk(2, 1),
+            in_hunk(1, 1),
+            in_hunk(0, 1),
+            ParserState::AfterHunk,
+            in_hunk(1, 1),
+            in_hunk(0, 1),
+            ParserState::AfterHunk,
+            ParserState::BetweenFiles,
+        ]
+    );
+}
+
+#[test]
+fn test_current_line_numbers() {
+    let diff = b"--- a\n+++ b\n@@ -3,5 +3,4 @@\n ctx\n-old\n-older\n+new\n ctx\n+added\n-gone\n\\ No newline at end of file\n--- c\n";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut numbers = vec![];
+    while let Some(line) = parser.next_line() {
+        let line = line.unwrap().to_string();
+        numbers.push((line, parser.current_line_numbers()));
+    }
+
+    let expected = [
+        ("--- a", None),
+        ("+++ b", None),
+        ("@@ -3,5 +3,4 @@", None),
+        (" ctx", Some((3, 3))),
+        ("-old", Some((4, 4))),
+        ("-older", Some((5, 4))),
+        ("+new", Some((6, 4))),
+        (" ctx", Some((6, 5))),
+        ("+added", Some((7, 6))),
+        ("-gone", Some((7, 7))),
+        ("\\ No newline at end of file", Some((7, 7))),
+        ("--- c", None),
+    ];
+    let expected = expected
+        .iter()
+        .map(|&(line, numbers)| (line.to_string(), numbers))
+        .collect::<Vec<_>>();
+    assert_eq!(numbers, expected);
+}
+
+#[test]
+fn test_strip_prefix() {
+    let diff = b"--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-foo\n+bar\n\
+--- /dev/null\n+++ b/new\n@@ -0,0 +1 @@\n+foo\n";
+    let files = |parser: DiffParser<&[u8]>| {
+        parser
+            .filter_map(|line| match line.unwrap() {
+                OwnedDiffLine::OldFile(fi) | OwnedDiffLine::NewFile(fi) => Some(fi.filename),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        files(DiffParser::new(&diff[..])),
+        vec![
+            b"a/src/lib.rs".to_vec(),
+            b"b/src/lib.rs".to_vec(),
+            b"/dev/null".to_vec(),
+            b"b/new".to_vec()
+        ]
+    );
+    assert_eq!(
+        files(DiffParser::new(&diff[..]).strip_prefix(0)),
+        files(DiffParser::new(&diff[..]))
+    );
+    assert_eq!(
+        files(DiffParser::new(&diff[..]).strip_prefix(1)),
+        vec![
+            b"src/lib.rs".to_vec(),
+            b"src/lib.rs".to_vec(),
+            b"/dev/null".to_vec(),
+            b"new".to_vec()
+        ]
+    );
+    assert_eq!(
+        files(DiffParser::new(&diff[..]).strip_prefix(5)),
+        vec![
+            b"lib.rs".to_vec(),
+            b"lib.rs".to_vec(),
+            b"/dev/null".to_vec(),
+            b"new".to_vec()
+        ]
+    );
+}
+
+#[test]
+fn test_strict() {
+    fn parse(diff: &[u8]) -> Vec<Result<OwnedDiffLine, ParseError>> {
+        DiffParser::new(diff)
+            .strict()
+            .map(|line| {
+                line.map_err(|err| {
+                    *err.into_inner()
+                        .and_then(|err| err.downcast().ok())
+                        .expect("not a ParseError")
+                })
+            })
+            .collect()
+    }
+
+    let err = |kind, line_number, line: &[u8]| {
+        Err(ParseError {
+            kind,
+            line_number,
+            line: line.to_vec(),
+        })
+    };
+
+    let diff = b"junk\n--- a\n+++ b\n@@ -1 +1 @@\n-foo\n+bar\nmore junk\n";
+    assert!(parse(diff).iter().all(Result::is_ok));
+
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1\n-foo\n";
+    let lines = parse(diff);
+    assert_eq!(
+        lines[2],
+        err(ParseErrorKind::ExpectedHunk, 3, b"@@ -1,2 +1\n")
+    );
+    assert_eq!(
+        lines[2].as_ref().unwrap_err().to_string(),
+        "line 3: expected a hunk header, found \"@@ -1,2 +1\""
+    );
+
+    let lines = parse(b"--- a\njunk\n");
+    assert_eq!(lines[1], err(ParseErrorKind::ExpectedNewFile, 2, b"junk\n"));
+
+    let lines = parse(b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n-foo\njunk\n");
+    assert_eq!(
+        lines[4],
+        err(ParseErrorKind::ExpectedHunkLine, 5, b"junk\n")
+    );
+
+    let lines = parse(b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n-foo\n");
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[4], err(ParseErrorKind::UnexpectedEof, 4, b""));
+
+    let lenient = DiffParser::new(&b"--- a\n+++ b\n@@ -1,2 +1\n"[..]).collect::<Vec<_>>();
+    assert_eq!(
+        lenient.last().unwrap().as_ref().unwrap(),
+        &OwnedDiffLine::Junk(b"@@ -1,2 +1\n".to_vec())
+    );
+}
+
+#[test]
+fn test_dangling_old_file() {
+    // A `---` header cut short, followed by another file.
+    let diff = b"--- a/foo\t2019-01-01 00:00:00\n%$#\n--- a/bar\n+++ b/bar\n@@ -1 +1 @@\n-a\n+b\n";
+
+    let mut parser = DiffParser::new(&diff[..]).strict();
+    assert!(matches!(parser.next_line(), Some(Ok(DiffLine::OldFile(_)))));
+    let err = parser.next_line().unwrap().unwrap_err();
+    let err = err.get_ref().and_then(|e| e.downcast_ref::<ParseError>());
+    assert_eq!(
+        err,
+        Some(&ParseError {
+            kind: ParseErrorKind::ExpectedNewFile,
+            line_number: 2,
+            line: b"%$#\n".to_vec(),
+        })
+    );
+    assert_eq!(
+        err.unwrap().to_string(),
+        "line 2: expected a +++ header, found \"%$#\""
+    );
+
+    // Leniently, the junk is passed on and the next file is found.
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[1], OwnedDiffLine::Junk(b"%$#\n".to_vec()));
+    assert!(matches!(lines[2], OwnedDiffLine::OldFile(_)));
+    assert!(matches!(lines[3], OwnedDiffLine::NewFile(_)));
+    assert_eq!(lines.len(), 7);
+}
+
+#[test]
+fn test_recover_from_corrupt_file() {
+    let diff = b"diff --git a/one b/one
+--- a/one
++++ b/one
+@@ -1 +1 @@
+-a
++b
+diff --git a/two b/two
+--- a/two
++++ b/two
+@@ -1,3 +1,3 @@
+-c
+garbage where the rest of the hunk should be
+more garbage
+diff --git a/three b/three
+--- a/three
++++ b/three
+@@ -1 +1 @@
+-d
++e
+";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert!(matches!(lines[10], OwnedDiffLine::Deleted(..)));
+    assert!(matches!(lines[11], OwnedDiffLine::MalformedHunk { .. }));
+    assert_eq!(
+        lines[12],
+        OwnedDiffLine::Junk(b"garbage where the rest of the hunk should be\n".to_vec())
+    );
+    assert_eq!(lines[13], OwnedDiffLine::Junk(b"more garbage\n".to_vec()));
+    assert_eq!(
+        &lines[14..],
+        &[
+            OwnedDiffLine::GitHeader {
+                old_path: b"a/three".to_vec(),
+                new_path: b"b/three".to_vec()
+            },
+            OwnedDiffLine::OldFile(OwnedFileInfo {
+                filename: b"a/three".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::NewFile(OwnedFileInfo {
+                filename: b"b/three".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::Hunk(OwnedHunkInfo {
+                old_line_no: 1,
+                old_line_len: 1,
+                new_line_no: 1,
+                new_line_len: 1,
+                context: None
+            }),
+            OwnedDiffLine::Deleted(b"d\n".to_vec(), 1),
+            OwnedDiffLine::Inserted(b"e\n".to_vec(), 1),
+        ]
+    );
+
+    let files = files(&diff[..]).map(Result::unwrap).collect::<Vec<_>>();
+    assert_eq!(files.len(), 3);
+    assert_eq!(files[2].new.filename, b"b/three".to_vec());
+    assert_eq!(files[2].hunks[0].lines.len(), 2);
+}
+
+#[test]
+fn test_final_newline() {
+    let diff = b"--- a/lost
++++ b/lost
+@@ -1 +1 @@
+-a
++b
+\\ No newline at end of file
+--- a/middle
++++ b/middle
+@@ -5 +5 @@
+-c
++d
+--- a/added
++++ b/added
+@@ -1 +1 @@
+-e
+\\ No newline at end of file
++e
+--- /dev/null
++++ b/new
+@@ -0,0 +1,2 @@
++f
++g
+--- a/gone
++++ /dev/null
+@@ -1 +0,0 @@
+-h
+\\ No newline at end of file
+";
+    // The answer for each file once its hunks are done.
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut finals = vec![];
+    let mut current = None;
+    while let Some(line) = parser.next_line() {
+        if matches!(line.unwrap(), DiffLine::OldFile(_)) && parser.line_number() > 1 {
+            finals.push(current);
+        }
+        current = parser.new_side_has_final_newline();
+    }
+    finals.push(current);
+
+    assert_eq!(
+        finals,
+        vec![Some(false), None, Some(true), Some(true), None]
+    );
+}
+
+#[test]
+fn test_line_number() {
+    let diff = b"junk\r\n--- a\r\n+++ b\n@@ -1 +1 @@\r\n-foo\n+bar";
+    let mut parser = DiffParser::new(&diff[..]);
+    assert_eq!(parser.line_number(), 0);
+
+    let mut numbers = vec![];
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+        numbers.push(parser.line_number());
+    }
+
+    assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6]);
+    assert_eq!(parser.line_number(), 6);
+}
+
+#[test]
+fn test_byte_offset() {
+    let diff = b"junk\r\n--- a\r\n+++ b\n@@ -1 +1 @@\r\n-foo\n+bar";
+    let mut parser = DiffParser::new(&diff[..]);
+    assert_eq!(parser.byte_offset(), 0);
+
+    let mut spans = vec![];
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+        let start = parser.byte_offset();
+        spans.push((start, start + parser.line.len() as u64));
+    }
+
+    assert_eq!(
+        spans,
+        vec![(0, 6), (6, 13), (13, 19), (19, 32), (32, 37), (37, 41)]
+    );
+    assert!(spans.windows(2).all(|w| w[0].1 == w[1].0));
+    assert_eq!(parser.byte_offset(), diff.len() as u64);
+}
+
+#[test]
+fn test_with_terminator() {
+    let diff = b"junk\n\0--- a\0+++ b\0@@ -1 +1 @@\0-foo\0+bar\0";
+    let lines = DiffParser::new(&diff[..])
+        .with_terminator(0)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        lines,
+        vec![
+            OwnedDiffLine::Preamble(b"junk\n\0".to_vec()),
+            OwnedDiffLine::OldFile(OwnedFileInfo {
+                filename: b"a".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::NewFile(OwnedFileInfo {
+                filename: b"b".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::Hunk(OwnedHunkInfo {
+                old_line_no: 1,
+                old_line_len: 1,
+                new_line_no: 1,
+                new_line_len: 1,
+                context: None
+            }),
+            OwnedDiffLine::Deleted(b"foo\0".to_vec(), 1),
+            OwnedDiffLine::Inserted(b"bar\0".to_vec(), 1),
+        ]
+    );
+    assert_eq!(lines[4].as_diff_line().content(), Some(&b"foo"[..]));
+}
+
+#[test]
+#[should_panic(expected = "unsupported line terminator ';'")]
+fn test_with_other_terminator() {
+    let _ = DiffParser::new(&b""[..]).with_terminator(b';');
+}
+
+#[test]
+fn test_write_line() {
+    let diff = b"junk  \r\n\
+diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo\t2019-01-01  00:00:00\r
++++ b/foo\t2019-01-01  00:00:00\r
+@@ -1,1 +1,2 @@   fn main()
+-foo\r
++bar\r
++baz
+\\ No newline at end of file\r
+@@ -5 +5,2 @@
+ qux
+";
+
+    let rewrite = |verbatim: bool, change: bool| {
+        let mut parser = DiffParser::new(&diff[..]);
+        if verbatim {
+            parser = parser.verbatim();
+        }
+        let mut out = vec![];
+        while let Some(line) = parser.next_line() {
+            let line = match line.unwrap().to_owned() {
+                OwnedDiffLine::Inserted(l, _) if change && l == b"baz\n" => {
+                    OwnedDiffLine::Inserted(b"BAZ\n".to_vec(), 1)
+                }
+                line => line,
+            };
+            parser.write_line(&line.as_diff_line(), &mut out).unwrap();
+        }
+        out
+    };
+
+    assert_eq!(rewrite(true, false), diff.to_vec());
+    assert_ne!(rewrite(false, false), diff.to_vec());
+
+    let expected = String::from_utf8_lossy(diff).replace("+baz\n", "+BAZ\n");
+    assert_eq!(String::from_utf8_lossy(&rewrite(true, true)), expected);
+}
+
+#[test]
+fn test_malformed_hunk() {
+    let malformed =
+        |expected_old, expected_new, actual_old, actual_new| OwnedDiffLine::MalformedHunk {
+            expected_old,
+            expected_new,
+            actual_old,
+            actual_new,
+        };
+
+    let diff = b"--- a\n+++ b\n@@ -1,5 +1,5 @@\n foo\n-bar\n+baz\n qux\n@@ -10 +10 @@\n-x\n+y\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[7], malformed(5, 5, 3, 3));
+    assert!(matches!(lines[8], OwnedDiffLine::Hunk(_)));
+    assert_eq!(lines[10], OwnedDiffLine::Inserted(b"y\n".to_vec(), 1));
+    assert_eq!(lines.len(), 11);
+
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n-foo\n-bar\n-baz\n+qux\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[5], malformed(2, 2, 3, 0));
+    assert_eq!(lines[6], OwnedDiffLine::Junk(b"-baz\n".to_vec()));
+    assert_eq!(lines[7], OwnedDiffLine::Junk(b"+qux\n".to_vec()));
+
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1,3 @@\n foo\n+bar\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines.last(), Some(&malformed(2, 3, 1, 2)));
+
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n-foo\n-bar\n-baz\n+qux\n";
+    let err = DiffParser::new(&diff[..])
+        .strict()
+        .find_map(Result::err)
+        .unwrap();
+    let err = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<ParseError>())
+        .unwrap();
+    assert_eq!(err.kind, ParseErrorKind::HunkTooLong);
+    assert_eq!(err.line_number, 6);
+}
+
+#[test]
+fn test_junk_count() {
+    let junk_count = |diff: &[u8]| {
+        let mut parser = DiffParser::new(diff);
+        while let Some(line) = parser.next_line() {
+            line.unwrap();
+        }
+        parser.junk_count()
+    };
+
+    let diff = b"junk\n--- a\n+++ b\n@@ -1,2 +1,2 @@\n-foo\n+bar\n baz\n";
+    assert_eq!(junk_count(&diff[..]), 0);
+
+    let diff = b"junk\n--- a\n+++ b\n@@ -1,2 +1,2 @@\n-foo\n+bar\n%$#garbage\n baz\n";
+    assert_eq!(junk_count(&diff[..]), 1);
+
+    let diff = b"--- a\njunk\n";
+    assert_eq!(junk_count(&diff[..]), 1);
+
+    // Junk in a git header leaves the parser in it, but is still one run.
+    let diff = b"diff --git a/f b/f\nfoo\nbar\nbaz\n";
+    assert_eq!(junk_count(&diff[..]), 1);
+    let diff = b"diff --git a/f b/f\nfoo\nold mode 100644\nbar\n";
+    assert_eq!(junk_count(&diff[..]), 2);
+
+    let mut parser = DiffParser::new(&b"--- a\n+++ b\n@@ -1 +1 @@\n-a\n+b\n-c\n"[..])
+        .filter_paths(vec![b"b".to_vec()]);
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+    }
+    assert_eq!(parser.junk_count(), 1);
+}
+
+#[test]
+fn test_peek() {
+    let diff = b"junk\n--- a\n+++ b\n@@ -1,2 +1 @@\n-foo\n%$#garbage\n";
+    let mut parser = DiffParser::new(&diff[..]).strict().with_stats();
+    let mut lines = vec![];
+    loop {
+        let peeked = parser.peek().map(|line| line.map(|l| l.to_owned()));
+        let again = parser.peek().map(|line| line.map(|l| l.to_owned()));
+        let state = parser.state;
+        let format = parser.format();
+        let next = parser.next_line().map(|line| line.map(|l| l.to_owned()));
+
+        match (peeked, again, next) {
+            (Some(Ok(peeked)), Some(Ok(again)), Some(Ok(next))) => {
+                assert_eq!(peeked, next);
+                assert_eq!(again, next);
+                lines.push((state, format, next));
+            }
+            (Some(Err(_)), Some(Err(_)), Some(Err(err))) => {
+                assert_eq!(parser.line_number(), 6, "{}", err);
+                break;
+            }
+            (None, None, None) => break,
+            lines => panic!("{:?}", lines),
+        }
+    }
+
+    assert_eq!(lines.len(), 5);
+    assert!(matches!(
+        lines[2],
+        (State::OldFile, None, OwnedDiffLine::NewFile(_))
+    ));
+    assert!(matches!(
+        lines[3],
+        (
+            State::NewFile,
+            Some(DiffFormat::Unified),
+            OwnedDiffLine::Hunk(_)
+        )
+    ));
+    assert!(matches!(
+        lines[4],
+        (State::Hunk(_), _, OwnedDiffLine::Deleted(..))
+    ));
+    assert_eq!(parser.stats().files, 1);
+    assert_eq!(parser.stats().deletions, 1);
+}
+
+#[test]
+fn test_in_hunk() {
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1 @@\n-foo\n bar\n@@ -5 +4 @@\n-baz\n+qux\n";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut seen = vec![];
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+        seen.push((parser.in_hunk(), parser.remaining_hunk_lines()));
+    }
+
+    assert_eq!(
+        seen,
+        vec![
+            (false, None),
+            (false, None),
+            (true, Some((2, 1))),
+            (true, Some((1, 1))),
+            (false, None),
+            (true, Some((1, 1))),
+            (true, Some((0, 1))),
+            (false, None),
+        ]
+    );
+}
+
+#[test]
+fn test_raw() {
+    let diff = b"junk\r\n--- a\n+++ b\n@@ -1,2 +1 @@\n-foo\n%$#\n";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut raw = vec![];
+    while let Some(line) = parser.next_line() {
+        let line = line.unwrap().to_owned();
+        raw.push((line, parser.raw().to_vec()));
+    }
+
+    let lines = raw.iter().map(|(_, raw)| &raw[..]).collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            &b"junk\r\n"[..],
+            b"--- a\n",
+            b"+++ b\n",
+            b"@@ -1,2 +1 @@\n",
+            b"-foo\n",
+            b"%$#\n",
+            b"%$#\n",
+        ]
+    );
+    assert!(matches!(raw[5].0, OwnedDiffLine::MalformedHunk { .. }));
+    assert!(matches!(raw[6].0, OwnedDiffLine::Junk(_)));
+    assert_eq!(parser.raw(), b"");
+
+    // The bytes can be had while the line's still in use.
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut raw = vec![];
+    while let Some(parsed) = parser.next_line_raw() {
+        let (line, bytes) = parsed.unwrap();
+        raw.push((line.kind(), bytes.to_vec()));
+    }
+    assert_eq!(raw[4], (DiffLineKind::Deleted, b"-foo\n".to_vec()));
+    assert_eq!(raw[5], (DiffLineKind::MalformedHunk, vec![]));
+    assert_eq!(raw[6], (DiffLineKind::Junk, b"%$#\n".to_vec()));
+    assert_eq!(raw.len(), 7);
+}
+
+#[test]
+fn test_current_file_mode() {
+    let diff = b"diff --git a/new b/new
+new file mode 100644
+index 0000000..e69de29
+diff --git a/gone b/gone
+deleted file mode 100755
+index e69de29..0000000
+diff --git a/script b/script
+old mode 100644
+new mode 100755
+diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+--- plain
++++ plain
+@@ -1 +1 @@
+-foo
++bar
+";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut modes = vec![];
+    while let Some(line) = parser.next_line() {
+        if let DiffLine::GitHeader { .. } | DiffLine::OldFile(_) = line.unwrap() {
+            modes.push(vec![]);
+        }
+        if let Some(file) = modes.last_mut() {
+            file.push(parser.current_file_mode());
+        }
+    }
+
+    let mode = |old, new| Some(FileModeChange { old, new });
+    assert_eq!(
+        modes
+            .iter()
+            .map(|file| *file.last().unwrap())
+            .collect::<Vec<_>>(),
+        vec![
+            mode(None, Some(0o100644)),
+            mode(Some(0o100755), None),
+            mode(Some(0o100644), Some(0o100755)),
+            mode(Some(0o100644), Some(0o100644)),
+            mode(Some(0o100644), Some(0o100644)),
+            None,
+        ]
+    );
+    assert_eq!(modes[0][0], None);
+    assert_eq!(modes[2][1], mode(Some(0o100644), None));
+}
+
+#[test]
+fn test_unterminated_last_line() {
+    let line = |diff: &[u8], n| {
+        DiffParser::new(diff)
+            .map(Result::unwrap)
+            .nth(n)
+            .unwrap()
+            .as_diff_line()
+            .to_string()
+    };
+
+    assert_eq!(line(b"--- foo\n+++ foo", 1), "+++ foo");
+    assert_eq!(
+        line(b"--- foo\n+++ foo\t2019-01-01 00:00:00", 1),
+        "+++ foo\t2019-01-01 00:00:00"
+    );
+    assert_eq!(
+        line(b"--- foo\n+++ foo\n@@ -1 +1 @@\tfn main()", 2),
+        "@@ -1 +1 @@\tfn main()"
+    );
+    assert_eq!(line(b"--- foo\n+++ foo\n@@ -1 +1 @@\n-a\n+b", 4), "+b");
+    assert_eq!(
+        line(b"--- a\n+++ b\n@@@ -1 -1 +1 @@@", 2),
+        "@@@ -1 -1 +1 @@@"
+    );
+    assert_eq!(
+        line(b"*** a\n--- b\n***************\n*** 1 ****", 3),
+        "*** 1 ****"
+    );
+
+    // Single character names are long enough without a newline.
+    for diff in &[&b"--- x\n+++ x"[..], b"*** x\n--- x"] {
+        let kinds = DiffParser::new(*diff)
+            .map(|line| line.unwrap().as_diff_line().kind())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [DiffLineKind::OldFile, DiffLineKind::NewFile]);
+    }
+
+    let lines = DiffParser::new(&b"--- foo\n+++ foo"[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines[1],
+        OwnedDiffLine::NewFile(OwnedFileInfo {
+            filename: b"foo".to_vec(),
+            metadata: None,
+        })
+    );
+}
+
+#[test]
+fn test_current_path() {
+    let diff = b"diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+diff --git a/old b/new
+similarity index 100%
+rename from old
+rename to new
+diff --git a/gone b/gone
+deleted file mode 100644
+--- a/gone
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+--- plain
++++ plain.new
+@@ -1 +1 @@
+-foo
++bar
+--- deleted
++++ /dev/null
+@@ -1 +0,0 @@
+-deleted
+";
+    let paths = |mut parser: DiffParser<&[u8]>| {
+        let mut paths = vec![];
+        while let Some(line) = parser.next_line() {
+            let line = line.unwrap().to_owned();
+            paths.push((line, parser.current_path().map(<[u8]>::to_vec)));
+        }
+        paths
+    };
+
+    let lines = paths(DiffParser::new(&diff[..]));
+    let path_at = |n: usize| lines[n].1.as_deref();
+    assert_eq!(path_at(0), Some(&b"b/foo"[..]));
+    assert_eq!(path_at(6), Some(&b"b/foo"[..]));
+    assert_eq!(path_at(10), Some(&b"b/new"[..]));
+    assert_eq!(path_at(16), Some(&b"b/gone"[..]));
+    assert_eq!(path_at(18), Some(&b"plain.new"[..]));
+    assert_eq!(path_at(21), Some(&b"plain.new"[..]));
+    assert_eq!(path_at(22), Some(&b"deleted"[..]));
+    assert_eq!(path_at(24), Some(&b"deleted"[..]));
+
+    let lines = paths(DiffParser::new(&diff[..]).strip_prefix(1));
+    assert_eq!(lines[0].1.as_deref(), Some(&b"foo"[..]));
+    assert_eq!(lines[10].1.as_deref(), Some(&b"new"[..]));
+
+    assert_eq!(DiffParser::new(&diff[..]).current_path(), None);
+}
+
+#[test]
+fn test_file_events() {
+    let diff = b"diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+@@ -5 +5 @@
+-x
++y
+diff --git a/old b/new
+similarity index 100%
+rename from old
+rename to new
+--- bar
++++ bar
+@@ -1 +1 @@
+-a
++b
+-- \n2.30.0
+--- baz
++++ baz
+@@ -1,2 +1 @@
+-c
+";
+    let events = |parser: DiffParser<&[u8]>| {
+        parser
+            .map(Result::unwrap)
+            .map(|line| match line {
+                OwnedDiffLine::FileStart(fi) => format!("start {}", fi),
+                OwnedDiffLine::FileEnd(fi) => format!("end {}", fi),
+                line => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let lines = events(DiffParser::new(&diff[..]).file_events());
+    assert_eq!(&lines[3..6], &["+++ b/foo", "start b/foo", "@@ -1 +1 @@"]);
+    assert_eq!(&lines[11..13], &["end b/foo", "diff --git a/old b/new"]);
+    assert_eq!(&lines[16..19], &["--- bar", "+++ bar", "start bar"]);
+    assert_eq!(&lines[21..24], &["+b", "end bar", "-- "]);
+    assert_eq!(&lines[lines.len() - 3..], &["-c", "", "end baz"]);
+    assert_eq!(lines.iter().filter(|l| l.starts_with("start")).count(), 3);
+
+    let mut parser = DiffParser::new(&diff[..])
+        .file_events()
+        .strip_prefix(1)
+        .filter_paths(vec![b"foo".to_vec()]);
+    assert!(matches!(
+        parser.peek().unwrap().unwrap(),
+        DiffLine::GitHeader { .. }
+    ));
+    let lines = events(parser);
+    assert_eq!(lines.len(), 12);
+    assert_eq!(lines[4], "start foo");
+    assert_eq!(lines[11], "end foo");
+
+    let lines = events(DiffParser::new(&diff[..]));
+    assert!(!lines.iter().any(|l| l.starts_with("start")));
+}
+
+#[test]
+fn test_submodule() {
+    let diff = b"diff --git a/lib b/lib
+index 1234567..89abcde 160000
+--- a/lib
++++ b/lib
+@@ -1 +1 @@
+-Subproject commit 1234567890abcdef1234567890abcdef12345678
++Subproject commit 89abcdef0123456789abcdef0123456789abcdef
+diff --git a/foo b/foo
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-Subproject commit 1234567890abcdef1234567890abcdef12345678
++bar
+";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        lines[5],
+        OwnedDiffLine::Deleted(
+            b"Subproject commit 1234567890abcdef1234567890abcdef12345678\n".to_vec(),
+            1
+        )
+    );
+    assert_eq!(
+        lines[7],
+        OwnedDiffLine::Submodule {
+            old: b"1234567890abcdef1234567890abcdef12345678".to_vec(),
+            new: b"89abcdef0123456789abcdef0123456789abcdef".to_vec(),
+        }
+    );
+    assert!(matches!(lines[8], OwnedDiffLine::GitHeader { .. }));
+    assert_eq!(lines.len(), 14);
+    assert!(!lines[9..]
+        .iter()
+        .any(|line| matches!(line, OwnedDiffLine::Submodule { .. })));
+
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut out = vec![];
+    while let Some(line) = parser.next_line() {
+        line.unwrap().writeln(&mut out).unwrap();
     }
+    assert_eq!(out, &diff[..]);
 }
 
 #[test]
-fn test_chomp() {
-    assert_eq!(chomp(b""), b"");
-    assert_eq!(chomp(b"foo"), b"foo");
-    assert_eq!(chomp(b"foo\r\n"), b"foo");
-    assert_eq!(chomp(b"foo\n"), b"foo");
+fn test_tab_context() {
+    let diff = b"--- a\n+++ b\n@@ -1,3 +1,3 @@\n\tfoo\n-bar\n+baz\n\tqux\n";
+    let lines = DiffParser::new(&diff[..])
+        .tab_context()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[3], OwnedDiffLine::Context(b"foo\n".to_vec(), 1));
+    assert_eq!(lines[6], OwnedDiffLine::Context(b"qux\n".to_vec(), 1));
+    assert_eq!(lines.len(), 7);
+
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert!(matches!(lines[3], OwnedDiffLine::MalformedHunk { .. }));
+    assert_eq!(lines[4], OwnedDiffLine::Junk(b"\tfoo\n".to_vec()));
 }
 
-fn parse_u32(bytes: &[u8]) -> Option<u32> {
-    if bytes.is_empty() {
-        return None;
-    }
+#[test]
+fn test_spaced_ranges() {
+    let diff = b"--- a\n+++ b\n@@ -1 , 2 +1 , 2 @@\n foo\n-bar\n+baz\n@@ - 9 +9 @@\n-x\n+y\n";
+    let lines = DiffParser::new(&diff[..])
+        .spaced_ranges()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 9);
+    assert!(matches!(
+        lines[2],
+        OwnedDiffLine::Hunk(OwnedHunkInfo {
+            old_line_no: 1,
+            old_line_len: 2,
+            new_line_no: 1,
+            new_line_len: 2,
+            ..
+        })
+    ));
+    assert!(matches!(
+        lines[6],
+        OwnedDiffLine::Hunk(OwnedHunkInfo {
+            old_line_no: 9,
+            old_line_len: 1,
+            ..
+        })
+    ));
 
-    bytes.iter().try_fold(0u32, |acc, byte| {
-        acc.checked_mul(10).and_then(|acc| {
-            (*byte as char)
-                .to_digit(10)
-                .and_then(|digit| acc.checked_add(digit))
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    // Without it the spaced out bits of the ranges are skipped over.
+    assert_eq!(
+        lines[2],
+        OwnedDiffLine::Hunk(OwnedHunkInfo {
+            old_line_no: 1,
+            old_line_len: 1,
+            new_line_no: 1,
+            new_line_len: 1,
+            context: None,
         })
-    })
+    );
 }
 
 #[test]
-fn test_parse_u32() {
-    assert_eq!(parse_u32(b"0"), Some(0));
-    assert_eq!(parse_u32(b"10"), Some(10));
-    assert_eq!(parse_u32(b"42"), Some(42));
-    assert_eq!(parse_u32(b"4294967295"), Some(4294967295));
-    assert_eq!(parse_u32(b"4294967296"), None);
-    assert_eq!(parse_u32(b"12345six"), None);
-    assert_eq!(parse_u32(b"nope"), None);
-    assert_eq!(parse_u32(b""), None);
+fn test_unspaced_headers() {
+    let diff = b"---\n 1 file changed\n---a/foo\n+++b/foo\n@@ -1 +1 @@\n----\n++++\n";
+    let lines = DiffParser::new(&diff[..])
+        .unspaced_headers()
+        .strip_prefix(1)
+        .map(|line| line.unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "---",
+            " 1 file changed",
+            "--- foo",
+            "+++ foo",
+            "@@ -1 +1 @@",
+            "----",
+            "++++"
+        ]
+    );
+
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[2], OwnedDiffLine::Preamble(b"---a/foo\n".to_vec()));
+    assert_eq!(lines.len(), 7);
 }
 
-fn parse_range(bytes: &[u8]) -> Option<(u32, u32)> {
-    let mut bits = bytes.split(|&b| b == b',').flat_map(parse_u32);
+#[test]
+fn test_max_line_len() {
+    let long = "x".repeat(100);
+    let diff = format!("--- a\n+++ b\n@@ -1 +1 @@\n-{}\n+short\n", long);
+    let mut parser = DiffParser::new(diff.as_bytes()).max_line_len(20);
+
+    assert_eq!(parser.next_line().unwrap().unwrap().to_string(), "--- a");
+    assert_eq!(parser.next_line().unwrap().unwrap().to_string(), "+++ b");
+    assert_eq!(
+        parser.next_line().unwrap().unwrap().to_string(),
+        "@@ -1 +1 @@"
+    );
+
+    let err = parser.next_line().unwrap().unwrap_err();
+    let err = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+    assert_eq!(err.kind, ParseErrorKind::LineTooLong);
+    assert_eq!(err.line_number, 4);
+    assert_eq!(err.line, format!("-{}", &long[..20]).as_bytes());
 
-    Some((bits.next()?, bits.next().unwrap_or(1)))
+    // The rest of the line is skipped, and the next read as usual.
+    assert_eq!(parser.next_line().unwrap().unwrap().to_string(), "+short");
+    assert_eq!(parser.line_number(), 5);
+    assert_eq!(parser.byte_offset(), 6 + 6 + 12 + 102);
+
+    // A line of the limit exactly is fine, with or without its terminator.
+    let diff = b"0123456789\n0123456789";
+    let lines = DiffParser::new(&diff[..])
+        .max_line_len(10)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(lines.len(), 2);
 }
 
 #[test]
-fn test_parse_range() {
-    assert_eq!(parse_range(b"12,24"), Some((12, 24)));
-    assert_eq!(parse_range(b"12"), Some((12, 1)));
-    assert_eq!(parse_range(b"42,"), Some((42, 1)));
-    assert_eq!(parse_range(b","), None);
-    assert_eq!(parse_range(b""), None);
+fn test_blank_context_line() {
+    // A blank context line which lost its space to trailing whitespace
+    // stripping, and one with a CRLF.
+    let diff = b"--- a\n+++ b\n@@ -1,4 +1,4 @@\n foo\n\n-bar\n+baz\n\r\n--- c\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(lines[4], OwnedDiffLine::Context(b"\n".to_vec(), 0));
+    assert_eq!(lines[5], OwnedDiffLine::Deleted(b"bar\n".to_vec(), 1));
+    assert_eq!(lines[7], OwnedDiffLine::Context(b"\r\n".to_vec(), 0));
+    assert!(matches!(lines[8], OwnedDiffLine::OldFile(_)));
 }
 
-fn parse_fileinfo(line: &[u8]) -> FileInfo<'_> {
-    let eof = line
-        .iter()
-        .position(|&b| b == b'\t' || b == b'\r' || b == b'\n')
-        .unwrap_or_else(|| line.len());
+#[test]
+fn test_mixed_line_endings() {
+    // A hunk converting a file to LF line endings.
+    let diff =
+        b"--- a\r\n+++ b\n@@ -1,3 +1,3 @@\r\n-foo\r\n-bar\r\n+foo\n+bar\n baz\r\n--- c\n+++ c\r\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
 
-    FileInfo {
-        filename: &line[4..eof],
-        metadata: if eof != line.len() - 1 {
-            Some(&line[eof + 1..line.len() - 1])
-        } else {
-            None
-        },
-    }
-}
+    assert_eq!(lines[3], OwnedDiffLine::Deleted(b"foo\r\n".to_vec(), 1));
+    assert_eq!(lines[5], OwnedDiffLine::Inserted(b"foo\n".to_vec(), 1));
+    assert_eq!(lines[7], OwnedDiffLine::Context(b"baz\r\n".to_vec(), 1));
+    assert_eq!(
+        lines[8],
+        OwnedDiffLine::OldFile(OwnedFileInfo {
+            filename: b"c".to_vec(),
+            metadata: None
+        })
+    );
+    assert_eq!(
+        lines[9],
+        OwnedDiffLine::NewFile(OwnedFileInfo {
+            filename: b"c".to_vec(),
+            metadata: None
+        })
+    );
+    assert_eq!(lines[3].as_diff_line().content(), Some(&b"foo"[..]));
+    assert_eq!(lines[5].as_diff_line().content(), Some(&b"foo"[..]));
 
-fn parse_old_file(line: &[u8]) -> DiffLine<'_> {
-    if line.starts_with(b"Binary files ") && line.ends_with(b"differ\n") {
-        // Binary files sigh and blegh differ
-        let x = &line[b"Binary files ".len()..line.len() - b" differ\n".len()];
-        if let Some(pos) = x.windows(b" and ".len()).position(|win| win == b" and ") {
-            return DiffLine::Binaries(&x[0..pos], &x[pos + 5..]);
-        }
+    // Hunk lines are written back with the ending they had.
+    let mut out = vec![];
+    for line in &lines[3..8] {
+        line.as_diff_line().writeln(&mut out).unwrap();
     }
+    assert_eq!(out, b"-foo\r\n-bar\r\n+foo\n+bar\n baz\r\n".to_vec());
 
-    if line.len() >= "--- x\n".len() && line.starts_with(b"--- ") {
-        return DiffLine::OldFile(parse_fileinfo(line));
-    }
+    let (patched, _) = apply(b"foo\r\nbar\r\nbaz\r\n", &mut DiffParser::new(&diff[..])).unwrap();
+    assert_eq!(patched, b"foo\nbar\nbaz\r\n".to_vec());
 
-    DiffLine::Junk(line)
+    // A diff sent with CRLF endings throughout, where the last line had none.
+    let diff = b"--- a\r\n+++ b\r\n@@ -1 +1 @@\r\n-foo\r\n+bar\r\n\\ No newline at end of file\r\n";
+    let (patched, _) = apply(b"foo\r\n", &mut DiffParser::new(&diff[..])).unwrap();
+    assert_eq!(patched, b"bar".to_vec());
 }
 
 #[test]
-fn test_parse_old_file() {
-    assert_eq!(parse_old_file(b""), DiffLine::Junk(b""));
+fn test_empty_hunk() {
+    // A hunk with no lines ends as soon as it starts, so what follows isn't
+    // taken as one of its lines.
+    let diff =
+        b"--- a\n+++ b\n@@ -0,0 +0,0 @@\n@@ -1 +1 @@\n-foo\n+bar\n@@ -0,0 +0,0 @@\n--- c\n+++ d\n@@ -0,0 +1 @@\n+baz\n";
+    let lines = DiffParser::new(&diff[..])
+        .strict()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 11);
+    assert_eq!(lines[2], OwnedDiffLine::Hunk(OwnedHunkInfo::default()));
     assert_eq!(
-        parse_old_file(b"Binary files and differ\n"),
-        DiffLine::Junk(b"Binary files and differ\n")
+        lines[3],
+        OwnedDiffLine::Hunk(OwnedHunkInfo {
+            old_line_no: 1,
+            old_line_len: 1,
+            new_line_no: 1,
+            new_line_len: 1,
+            context: None
+        })
     );
+    assert_eq!(lines[4], OwnedDiffLine::Deleted(b"foo\n".to_vec(), 1));
+    assert_eq!(lines[6], OwnedDiffLine::Hunk(OwnedHunkInfo::default()));
+    assert!(matches!(lines[7], OwnedDiffLine::OldFile(_)));
+    assert!(matches!(lines[8], OwnedDiffLine::NewFile(_)));
+}
+
+#[test]
+fn test_large_line_numbers() {
+    let diff = b"--- a\n+++ b\n@@ -4294967296,2 +4294967297,2 @@\n-foo\n+bar\n baz\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[3], OwnedDiffLine::Deleted(b"foo\n".to_vec(), 1));
+    assert_eq!(lines[4], OwnedDiffLine::Inserted(b"bar\n".to_vec(), 1));
+    assert_eq!(lines[5], OwnedDiffLine::Context(b"baz\n".to_vec(), 1));
     assert_eq!(
-        parse_old_file(b"Binary files foo and bar differ\n"),
-        DiffLine::Binaries(b"foo", b"bar")
+        lines[2].as_diff_line().to_string(),
+        "@@ -4294967296,2 +4294967297,2 @@"
     );
+}
+
+#[test]
+fn test_svn_properties() {
+    let diff = b"Index: foo.c
+===================================================================
+--- foo.c\t(revision 1)
++++ foo.c\t(working copy)
+@@ -1 +1 @@
+-a
++b
+
+Property changes on: foo.c
+___________________________________________________________________
+Added: svn:eol-style
+## -0,0 +1 ##
++native
+Modified: svn:keywords
+## -1 +1 ##
+-Id
++Id Rev
+Index: bar.c
+===================================================================
+--- bar.c\t(revision 1)
++++ bar.c\t(working copy)
+@@ -1 +1 @@
+-c
++d
+";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(lines[0], OwnedDiffLine::SvnIndex(b"foo.c".to_vec()));
+    assert_eq!(lines[8], OwnedDiffLine::PropertyChanges(b"foo.c".to_vec()));
     assert_eq!(
-        parse_old_file(b"--- x\n"),
-        DiffLine::OldFile(FileInfo {
-            filename: b"x",
-            metadata: None
-        })
+        lines[9],
+        OwnedDiffLine::Junk(
+            b"___________________________________________________________________\n".to_vec()
+        )
     );
     assert_eq!(
-        parse_old_file(b"--- foo/bar\n"),
-        DiffLine::OldFile(FileInfo {
-            filename: b"foo/bar",
-            metadata: None
-        })
+        &lines[10..17],
+        &[
+            OwnedDiffLine::Property {
+                change: PropertyChange::Added,
+                name: b"svn:eol-style".to_vec()
+            },
+            OwnedDiffLine::PropHunk(OwnedHunkInfo {
+                old_line_no: 0,
+                old_line_len: 0,
+                new_line_no: 1,
+                new_line_len: 1,
+                context: None
+            }),
+            OwnedDiffLine::Inserted(b"native\n".to_vec(), 1),
+            OwnedDiffLine::Property {
+                change: PropertyChange::Modified,
+                name: b"svn:keywords".to_vec()
+            },
+            OwnedDiffLine::PropHunk(OwnedHunkInfo {
+                old_line_no: 1,
+                old_line_len: 1,
+                new_line_no: 1,
+                new_line_len: 1,
+                context: None
+            }),
+            OwnedDiffLine::Deleted(b"Id\n".to_vec(), 1),
+            OwnedDiffLine::Inserted(b"Id Rev\n".to_vec(), 1),
+        ]
     );
     assert_eq!(
-        parse_old_file(b"--- foo/bar\tfoo bar\n"),
-        DiffLine::OldFile(FileInfo {
-            filename: b"foo/bar",
-            metadata: Some(b"foo bar")
+        lines[19],
+        OwnedDiffLine::OldFile(OwnedFileInfo {
+            filename: b"bar.c".to_vec(),
+            metadata: Some(b"(revision 1)".to_vec())
         })
     );
+    assert_eq!(lines[23], OwnedDiffLine::Inserted(b"d\n".to_vec(), 1));
+
+    assert_eq!(lines[14].to_string(), "## -1 +1 ##");
+    assert_eq!(lines[13].to_string(), "Modified: svn:keywords");
+}
+
+#[test]
+fn test_svn_diff() {
+    let diff = b"Index: foo.c
+===================================================================
+--- foo.c\t(revision 1)
++++ foo.c\t(working copy)
+@@ -1 +1 @@
+-a
++b
+Index: new.c
+===================================================================
+--- new.c\t(nonexistent)
++++ new.c\t(working copy)
+@@ -0,0 +1 @@
++c
+";
+    let sep = b"===================================================================";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    assert_eq!(lines[0], OwnedDiffLine::SvnIndex(b"foo.c".to_vec()));
+    assert_eq!(lines[1], OwnedDiffLine::SvnSeparator(sep.to_vec()));
     assert_eq!(
-        parse_old_file(b"--- foo/bar\t\n"),
-        DiffLine::OldFile(FileInfo {
-            filename: b"foo/bar",
-            metadata: Some(b"")
+        lines[2],
+        OwnedDiffLine::OldFile(OwnedFileInfo {
+            filename: b"foo.c".to_vec(),
+            metadata: Some(b"(revision 1)".to_vec())
         })
     );
-    assert_eq!(parse_old_file(b"--- \n"), DiffLine::Junk(b"--- \n"));
+    assert_eq!(lines[7], OwnedDiffLine::SvnIndex(b"new.c".to_vec()));
+    assert_eq!(lines[8], OwnedDiffLine::SvnSeparator(sep.to_vec()));
+    assert_eq!(lines[12], OwnedDiffLine::Inserted(b"c\n".to_vec(), 1));
+    assert_eq!(lines.len(), 13);
+
+    assert_eq!(lines[0].to_string(), "Index: foo.c");
+    assert_eq!(lines[1].to_string(), str::from_utf8(sep).unwrap());
+
+    // A separator is only recognised after an `Index:` line.
+    let lines = DiffParser::new(&b"=====\nIndex: a\nfoo\n====\n"[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            OwnedDiffLine::Preamble(b"=====\n".to_vec()),
+            OwnedDiffLine::SvnIndex(b"a".to_vec()),
+            OwnedDiffLine::Junk(b"foo\n".to_vec()),
+            OwnedDiffLine::Junk(b"====\n".to_vec()),
+        ]
+    );
+
+    let files = split_files(diff);
+    assert_eq!(files.len(), 2);
+    assert!(files[1].starts_with(b"Index: new.c\n"));
 }
 
-fn parse_new_file(line: &[u8]) -> DiffLine<'_> {
-    if line.len() >= b"+++ x\n".len() && line.starts_with(b"+++ ") {
-        return DiffLine::NewFile(parse_fileinfo(line));
+#[test]
+fn test_ed_format() {
+    // From `diff -e`, which works from the end of the file back.
+    let diff = b"7,8c\nseven\n\\eight\n.\n4,5d\n0a\nzero\n.\n";
+    let mut parser = DiffParser::new(&diff[..]).ed_format().with_stats();
+    let mut lines = vec![];
+    while let Some(line) = parser.next_line() {
+        lines.push(OwnedDiffLine::from(&line.unwrap()));
     }
 
-    DiffLine::Junk(line)
+    assert_eq!(
+        lines,
+        vec![
+            OwnedDiffLine::EdChange(7, 8),
+            OwnedDiffLine::Inserted(b"seven\n".to_vec(), 0),
+            OwnedDiffLine::Inserted(b"\\eight\n".to_vec(), 0),
+            OwnedDiffLine::EdEnd,
+            OwnedDiffLine::EdDelete(4, 5),
+            OwnedDiffLine::EdAppend(0),
+            OwnedDiffLine::Inserted(b"zero\n".to_vec(), 0),
+            OwnedDiffLine::EdEnd,
+        ]
+    );
+    assert_eq!(parser.format(), Some(DiffFormat::Ed));
+    let stats = parser.stats();
+    assert_eq!((stats.hunks, stats.insertions, stats.deletions), (3, 3, 4));
+
+    assert_eq!(lines[0].to_string(), "7,8c");
+    assert_eq!(lines[3].to_string(), ".");
+    assert_eq!(lines[4].to_string(), "4,5d");
+    assert_eq!(lines[5].to_string(), "0a");
+
+    // Without ed_format() it's all junk.
+    assert!(DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .all(|line| matches!(line, OwnedDiffLine::Preamble(_))));
 }
 
 #[test]
-fn test_parse_new_file() {
-    assert_eq!(parse_new_file(b""), DiffLine::Junk(b""));
+fn test_combined_diff() {
+    let diff = b"diff --cc file
+index 1111111,2222222..3333333
+--- a/file
++++ b/file
+@@@ -1,3 -1,3 +1,3 @@@
+  common
+- ours
+ -theirs
+++merged
+  tail
+@@@ -10,2 -10,1 +10,2 @@@ fn foo()
+  same
+ +added to second
+diff --git a/other b/other
+--- a/other
++++ b/other
+@@ -1 +1 @@
+-a
++b
+";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    let delta = |markers: &[u8], line: &[u8]| OwnedDiffLine::CombinedDelta {
+        markers: markers.to_vec(),
+        line: line.to_vec(),
+    };
+
     assert_eq!(
-        parse_new_file(b"+++ x\n"),
-        DiffLine::NewFile(FileInfo {
-            filename: b"x",
-            metadata: None
-        })
+        &lines[4..13],
+        &[
+            OwnedDiffLine::CombinedHunk(OwnedCombinedHunkInfo {
+                old_ranges: vec![(1, 3), (1, 3)],
+                new_line_no: 1,
+                new_line_len: 3,
+                context: None
+            }),
+            delta(b"  ", b"common\n"),
+            delta(b"- ", b"ours\n"),
+            delta(b" -", b"theirs\n"),
+            delta(b"++", b"merged\n"),
+            delta(b"  ", b"tail\n"),
+            OwnedDiffLine::CombinedHunk(OwnedCombinedHunkInfo {
+                old_ranges: vec![(10, 2), (10, 1)],
+                new_line_no: 10,
+                new_line_len: 2,
+                context: Some(b"fn foo()".to_vec())
+            }),
+            delta(b"  ", b"same\n"),
+            delta(b" +", b"added to second\n"),
+        ]
     );
     assert_eq!(
-        parse_new_file(b"+++ foo/bar\n"),
-        DiffLine::NewFile(FileInfo {
-            filename: b"foo/bar",
-            metadata: None
-        })
+        lines[13],
+        OwnedDiffLine::GitHeader {
+            old_path: b"a/other".to_vec(),
+            new_path: b"b/other".to_vec()
+        }
     );
+    assert_eq!(lines[18], OwnedDiffLine::Inserted(b"b\n".to_vec(), 1));
+    assert_eq!(lines[4].to_string(), "@@@ -1,3 -1,3 +1,3 @@@");
+    assert_eq!(lines[8].to_string(), "++merged");
+
+    let diff = b"--- a/file\n+++ b/file\n@@@ -1,2 -1,2 +1,2 @@@\n  one\njunk\n";
+    let err = DiffParser::new(&diff[..])
+        .strict()
+        .find_map(Result::err)
+        .unwrap();
+    let err = err
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<ParseError>())
+        .unwrap();
+    assert_eq!(err.kind, ParseErrorKind::ExpectedHunkLine);
+    assert_eq!(err.line_number, 5);
+}
+
+#[test]
+fn test_after_delta() {
+    let diff =
+        b"--- a\n+++ b\n@@ -1,3 +1,3 @@\n foo\n-bar\n+baz\n\\ No newline at end of file\n qux\n";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut sides = vec![];
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+        sides.push(parser.after_delta);
+    }
+
     assert_eq!(
-        parse_new_file(b"+++ foo/bar\tfoo bar\n"),
-        DiffLine::NewFile(FileInfo {
-            filename: b"foo/bar",
-            metadata: Some(b"foo bar")
-        })
+        sides,
+        vec![
+            None,
+            None,
+            None,
+            Some(Side::Both),
+            Some(Side::Old),
+            Some(Side::New),
+            None,
+            Some(Side::Both),
+        ]
     );
+}
+
+#[test]
+fn test_no_newline_at_eof() {
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n foo\n-bar\n\\ No newline at end of file\n+baz\n\\ No newline at end of file\n@@ -5 +5 @@\n-x\n+y\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let no_newline = |side| OwnedDiffLine::NoNewlineAtEof {
+        side,
+        marker: b"\\ No newline at end of file\n".to_vec(),
+    };
+
+    assert_eq!(lines[5], no_newline(Side::Old));
+    assert_eq!(lines[7], no_newline(Side::New));
+    assert!(matches!(lines[8], OwnedDiffLine::Hunk(_)));
+    assert_eq!(lines[10], OwnedDiffLine::Inserted(b"y\n".to_vec(), 1));
+
+    let diff = b"--- a\n+++ b\n\\ No newline at end of file\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
     assert_eq!(
-        parse_new_file(b"+++ foo/bar\t\n"),
-        DiffLine::NewFile(FileInfo {
-            filename: b"foo/bar",
-            metadata: Some(b"")
-        })
+        lines[2],
+        OwnedDiffLine::Junk(b"\\ No newline at end of file\n".to_vec())
     );
-    assert_eq!(parse_new_file(b"+++ \n"), DiffLine::Junk(b"+++ \n"));
+
+    let diff = b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n-foo\n+bar\n baz\n\\ No newline at end of file\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[6], no_newline(Side::Both));
+
+    let diff = b"*** a\n--- b\n***************\n*** 1 ****\n! foo\n\\ No newline at end of file\n--- 1 ----\n! bar\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[5], no_newline(Side::Old));
+
+    // Other wordings and line endings are kept as they are.
+    for marker in [
+        &b"\\ No newline at end of file\r\n"[..],
+        b"\\ No newline at end of file \n",
+        b"\\ Kein Zeilenumbruch am Dateiende.\n",
+    ] {
+        let diff = [&b"--- a\n+++ b\n@@ -1 +1 @@\n-foo\n"[..], marker, b"+bar\n"].concat();
+        let mut parser = DiffParser::new(&diff[..]);
+        let mut out = vec![];
+        while let Some(line) = parser.next_line() {
+            let line = line.unwrap();
+            if let DiffLine::NoNewlineAtEof { side, marker: m } = line {
+                assert_eq!((side, m), (Side::Old, marker));
+            }
+            line.writeln(&mut out).unwrap();
+        }
+        assert_eq!(out, diff);
+    }
 }
 
-fn parse_hunk(line: &[u8]) -> DiffLine<'_> {
-    if line.len() > b"@@ -1 +1 @@".len() && line.starts_with(b"@@ -") {
-        // svn also has ## for properties
-        // @@ -1,1 +1,1 @@
-        // @@ -1 +1 @@
+/// Iterating a `DiffParser` yields owned lines.
+///
+/// `next_line()` returns a `DiffLine` borrowing the parser's line buffer, which
+/// is overwritten by the following call.  `Iterator` has no way to express that,
+/// so each line is copied into an `OwnedDiffLine` instead.  Prefer `next_line()`
+/// where the allocation matters.
+impl<R: BufRead> Iterator for DiffParser<R> {
+    type Item = io::Result<OwnedDiffLine>;
 
-        let mut hunk = HunkInfo::default();
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().map(|line| line.map(OwnedDiffLine::from))
+    }
+}
 
-        let mut chunks = line[3..]
-            .split(|&b| b == b' ')
-            .flat_map(|chunk| parse_range(&chunk[1..]));
+#[test]
+fn test_iterator() {
+    let diff = b"junk\n--- a\n+++ b\n@@ -1 +1 @@\n-foo\n+bar\n";
+    let mut lines = vec![];
 
-        if let (Some(old), Some(new)) = (chunks.next(), chunks.next()) {
-            hunk.old_line_no = old.0;
-            hunk.old_line_len = old.1;
-            hunk.new_line_no = new.0;
-            hunk.new_line_len = new.1;
+    for line in DiffParser::new(&diff[..]) {
+        lines.push(line.expect("read error"));
+    }
 
-            hunk.context = line.splitn(2, |&b| b == b'\t').nth(1).map(chomp);
+    assert_eq!(
+        lines,
+        vec![
+            OwnedDiffLine::Preamble(b"junk\n".to_vec()),
+            OwnedDiffLine::OldFile(OwnedFileInfo {
+                filename: b"a".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::NewFile(OwnedFileInfo {
+                filename: b"b".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::Hunk(OwnedHunkInfo {
+                old_line_no: 1,
+                old_line_len: 1,
+                new_line_no: 1,
+                new_line_len: 1,
+                context: None
+            }),
+            OwnedDiffLine::Deleted(b"foo\n".to_vec(), 1),
+            OwnedDiffLine::Inserted(b"bar\n".to_vec(), 1),
+        ]
+    );
+}
 
-            return DiffLine::Hunk(hunk);
-        }
+/// Parse all of `input` at once, for when it's already in memory.
+pub fn parse_all(input: &[u8]) -> Vec<io::Result<OwnedDiffLine>> {
+    DiffParser::new(input).collect()
+}
+
+#[test]
+fn test_parse_all() {
+    let diff = b"diff --git a/foo b/foo
+--- a/foo
++++ b/foo
+@@ -1,2 +1,2 @@
+ foo
+-bar
++baz
+\\ No newline at end of file
+junk
+";
 
-        return DiffLine::Junk(line);
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut expected = vec![];
+    while let Some(line) = parser.next_line() {
+        expected.push(line.unwrap().to_owned());
     }
 
-    DiffLine::Junk(line)
+    let lines = parse_all(diff)
+        .into_iter()
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(lines, expected);
+    assert_eq!(lines.len(), 9);
+
+    assert!(parse_all(b"").is_empty());
 }
 
 #[test]
-fn test_parse_hunk() {
-    assert_eq!(parse_hunk(b""), DiffLine::Junk(b""));
+fn test_mode_change() {
+    let diff = b"diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+diff --git a/foo b/foo
+old mode 100755
+new mode 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+";
+    let mut parser = DiffParser::new(&diff[..]).with_stats();
+    let mut lines = vec![];
+    while let Some(line) = parser.next_line() {
+        lines.push(line.unwrap().to_owned());
+    }
+
+    assert_eq!(lines[1], OwnedDiffLine::OldMode(0o100644));
+    assert_eq!(lines[2], OwnedDiffLine::NewMode(0o100755));
+    assert!(matches!(lines[3], OwnedDiffLine::GitHeader { .. }));
+    assert_eq!(lines[4], OwnedDiffLine::OldMode(0o100755));
+    assert!(matches!(lines[6], OwnedDiffLine::OldFile(_)));
+    assert_eq!(lines[2].as_diff_line().to_string(), "new mode 100755");
+    assert_eq!(parser.junk_count(), 0);
+    assert_eq!(parser.stats().files, 1);
     assert_eq!(
-        parse_hunk(b"@@ -1 +1 @@\n"),
-        DiffLine::Hunk(HunkInfo {
-            old_line_no: 1,
-            old_line_len: 1,
-            new_line_no: 1,
-            new_line_len: 1,
-            context: None
-        })
+        lines[1].as_diff_line().reverse(),
+        DiffLine::NewMode(0o100644)
     );
+}
+
+#[test]
+fn test_mode_and_content_change() {
+    let diff = b"diff --git a/foo.sh b/bar.sh
+old mode 100644
+new mode 100755
+similarity index 90%
+rename from foo.sh
+rename to bar.sh
+index 1234567..89abcde
+--- a/foo.sh
++++ b/bar.sh
+@@ -1,2 +1,2 @@
+ #!/bin/sh
+-echo foo
++echo bar
+@@ -10 +10 @@
+-exit 1
++exit 0
+";
+    let mut parser = DiffParser::new(&diff[..]).strip_prefix(1).strict();
+    let mut kinds = vec![];
+    while let Some(line) = parser.next_line() {
+        kinds.push(line.unwrap().kind());
+    }
+
     assert_eq!(
-        parse_hunk(b"@@ -12,34 +56,78 @@\n"),
-        DiffLine::Hunk(HunkInfo {
-            old_line_no: 12,
-            old_line_len: 34,
-            new_line_no: 56,
-            new_line_len: 78,
-            context: None
-        })
+        kinds,
+        [
+            DiffLineKind::GitHeader,
+            DiffLineKind::OldMode,
+            DiffLineKind::NewMode,
+            DiffLineKind::SimilarityIndex,
+            DiffLineKind::RenameFrom,
+            DiffLineKind::RenameTo,
+            DiffLineKind::Index,
+            DiffLineKind::OldFile,
+            DiffLineKind::NewFile,
+            DiffLineKind::Hunk,
+            DiffLineKind::Context,
+            DiffLineKind::Deleted,
+            DiffLineKind::Inserted,
+            DiffLineKind::Hunk,
+            DiffLineKind::Deleted,
+            DiffLineKind::Inserted,
+        ]
     );
     assert_eq!(
-        parse_hunk(b"@@ -12,34 +56,78 @@\tfoo bar\n"),
-        DiffLine::Hunk(HunkInfo {
-            old_line_no: 12,
-            old_line_len: 34,
-            new_line_no: 56,
-            new_line_len: 78,
-            context: Some(b"foo bar")
+        parser.current_file_mode(),
+        Some(FileModeChange {
+            old: Some(0o100644),
+            new: Some(0o100755),
         })
     );
+    assert_eq!(parser.current_path(), Some(&b"bar.sh"[..]));
+    assert_eq!(parser.junk_count(), 0);
+
+    let files = files(&diff[..]).collect::<io::Result<Vec<_>>>().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].hunks.len(), 2);
 }
 
-fn parse_delta(line: &[u8]) -> DiffLine<'_> {
-    match line[0] {
-        b'+' => DiffLine::Inserted(&line[1..]),
-        b'-' => DiffLine::Deleted(&line[1..]),
-        b'!' => DiffLine::Modified(&line[1..]),
-        b' ' => DiffLine::Context(&line[1..]),
-        b'\\' => DiffLine::NoNewlineAtEof,
-        _ => DiffLine::Junk(line),
+#[test]
+fn test_git_diff() {
+    let diff = b"diff --git a/foo bar b/foo bar
+index 1234567..89abcde 100644
+--- a/foo bar
++++ b/foo bar
+@@ -1 +1 @@
+-foo
++bar
+diff --git a/old b/new
+similarity index 100%
+rename from old
+rename to new
+diff --git a/src/main.rs b/src/bin/main.rs
+similarity index 95%
+rename from src/main.rs
+rename to src/bin/main.rs
+index 1234567..89abcde 100644
+--- a/src/main.rs
++++ b/src/bin/main.rs
+@@ -1 +1 @@
+-fn main() {}
++fn main() { }
+diff --git a/baz b/baz
+--- a/baz
++++ b/baz
+@@ -1 +1,2 @@
+ baz
++quux
+";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut lines = vec![];
+    while let Some(line) = parser.next_line() {
+        lines.push(line.unwrap().to_owned());
     }
+
+    assert_eq!(
+        lines[0],
+        OwnedDiffLine::GitHeader {
+            old_path: b"a/foo bar".to_vec(),
+            new_path: b"b/foo bar".to_vec()
+        }
+    );
+    assert_eq!(
+        lines[1],
+        OwnedDiffLine::Index {
+            old_oid: b"1234567".to_vec(),
+            new_oid: b"89abcde".to_vec(),
+            mode: Some(b"100644".to_vec())
+        }
+    );
+    assert_eq!(
+        lines[2],
+        OwnedDiffLine::OldFile(OwnedFileInfo {
+            filename: b"a/foo bar".to_vec(),
+            metadata: None
+        })
+    );
+    assert_eq!(lines[6], OwnedDiffLine::Inserted(b"bar\n".to_vec(), 1));
+    assert_eq!(
+        lines[7],
+        OwnedDiffLine::GitHeader {
+            old_path: b"a/old".to_vec(),
+            new_path: b"b/new".to_vec()
+        }
+    );
+    assert_eq!(lines[8], OwnedDiffLine::SimilarityIndex(100));
+    assert_eq!(lines[9], OwnedDiffLine::RenameFrom(b"old".to_vec()));
+    assert_eq!(lines[10], OwnedDiffLine::RenameTo(b"new".to_vec()));
+
+    assert_eq!(
+        &lines[11..20],
+        &[
+            OwnedDiffLine::GitHeader {
+                old_path: b"a/src/main.rs".to_vec(),
+                new_path: b"b/src/bin/main.rs".to_vec()
+            },
+            OwnedDiffLine::SimilarityIndex(95),
+            OwnedDiffLine::RenameFrom(b"src/main.rs".to_vec()),
+            OwnedDiffLine::RenameTo(b"src/bin/main.rs".to_vec()),
+            OwnedDiffLine::Index {
+                old_oid: b"1234567".to_vec(),
+                new_oid: b"89abcde".to_vec(),
+                mode: Some(b"100644".to_vec())
+            },
+            OwnedDiffLine::OldFile(OwnedFileInfo {
+                filename: b"a/src/main.rs".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::NewFile(OwnedFileInfo {
+                filename: b"b/src/bin/main.rs".to_vec(),
+                metadata: None
+            }),
+            OwnedDiffLine::Hunk(OwnedHunkInfo {
+                old_line_no: 1,
+                old_line_len: 1,
+                new_line_no: 1,
+                new_line_len: 1,
+                context: None
+            }),
+            OwnedDiffLine::Deleted(b"fn main() {}\n".to_vec(), 1),
+        ]
+    );
+
+    assert_eq!(
+        lines[21],
+        OwnedDiffLine::GitHeader {
+            old_path: b"a/baz".to_vec(),
+            new_path: b"b/baz".to_vec()
+        }
+    );
+    assert_eq!(
+        lines[22],
+        OwnedDiffLine::OldFile(OwnedFileInfo {
+            filename: b"a/baz".to_vec(),
+            metadata: None
+        })
+    );
+    assert_eq!(lines[26], OwnedDiffLine::Inserted(b"quux\n".to_vec(), 1));
+    assert_eq!(lines.len(), 27);
 }
 
 #[test]
-fn test_parse_delta() {
-    assert_eq!(parse_delta(b"+foo\n"), DiffLine::Inserted(b"foo\n"));
-    assert_eq!(parse_delta(b"-foo\n"), DiffLine::Deleted(b"foo\n"));
-    assert_eq!(parse_delta(b"!foo\n"), DiffLine::Modified(b"foo\n"));
-    assert_eq!(parse_delta(b" foo\n"), DiffLine::Context(b"foo\n"));
+fn test_git_binary_patch() {
+    let diff = b"diff --git a/img.png b/img.png
+index 1234567..89abcde 100644
+GIT binary patch
+literal 4
+LcmZ?wbNT-R0A&CI
+
+literal 0
+HcmV?d00001
+
+diff --git a/foo b/foo
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+";
+    let mut parser = DiffParser::new(&diff[..]).with_stats();
+    let mut lines = vec![];
+    while let Some(line) = parser.next_line() {
+        lines.push(line.unwrap().to_owned());
+    }
+
+    assert_eq!(lines[2], OwnedDiffLine::GitBinaryPatch);
+    assert_eq!(
+        lines[3],
+        OwnedDiffLine::BinaryPatch {
+            kind: BinaryPatchKind::Literal,
+            size: 4
+        }
+    );
     assert_eq!(
-        parse_delta(b"\\ No newline at end of file\n"),
-        DiffLine::NoNewlineAtEof
+        lines[4],
+        OwnedDiffLine::BinaryData(b"LcmZ?wbNT-R0A&CI".to_vec())
     );
-    assert_eq!(parse_delta(b"foo\n"), DiffLine::Junk(b"foo\n"));
+    assert_eq!(lines[5], OwnedDiffLine::BinaryData(vec![]));
+    assert_eq!(lines[7], OwnedDiffLine::BinaryData(b"HcmV?d00001".to_vec()));
+    assert_eq!(lines[8], OwnedDiffLine::BinaryData(vec![]));
+    assert!(matches!(lines[9], OwnedDiffLine::GitHeader { .. }));
+    assert_eq!(lines[14], OwnedDiffLine::Inserted(b"bar\n".to_vec(), 1));
+    assert_eq!(parser.junk_count(), 0);
+    assert_eq!(parser.stats().files, 2);
+
+    let text = lines
+        .iter()
+        .map(|line| line.as_diff_line().to_string() + "\n")
+        .take(9)
+        .collect::<String>();
+    assert!(diff.starts_with(text.as_bytes()));
 }
 
-#[derive(Debug)]
-enum State {
-    Junk,
-    OldFile,
-    NewFile,
-    Hunk(i32, i32),
+#[test]
+fn test_dev_null() {
+    let diff = b"diff --git a/new b/new
+new file mode 100644
+index 0000000..257cc56
+--- /dev/null
++++ b/new
+@@ -0,0 +1 @@
++foo
+diff --git a/old b/old
+deleted file mode 100644
+index 257cc56..0000000
+--- a/old
++++ /dev/null
+@@ -1 +0,0 @@
+-foo
+--- /dev/null\t1970-01-01 00:00:00.000000000 +0000
++++ plain\t2019-01-01 00:00:00.000000000 +0000
+@@ -0,0 +1 @@
++foo
+";
+    let mut parser = DiffParser::new(&diff[..]);
+    let mut created = vec![];
+    let mut deleted = 0;
+    let mut old_is_null = false;
+
+    while let Some(line) = parser.next_line() {
+        match line.unwrap() {
+            DiffLine::OldFile(fi) => old_is_null = fi.is_dev_null(),
+            DiffLine::NewFile(fi) if fi.is_dev_null() => deleted += 1,
+            DiffLine::NewFile(fi) if old_is_null => created.push(fi.filename.to_vec()),
+            _ => (),
+        }
+    }
+
+    assert_eq!(created, vec![b"b/new".to_vec(), b"plain".to_vec()]);
+    assert_eq!(deleted, 1);
 }
 
-use std::io;
-use std::io::BufRead;
+#[test]
+fn test_preamble() {
+    let diff = b"From 1234567 Mon Sep 17 00:00:00 2001
+From: A U Thor <author@example.com>
+Date: Mon, 1 Jan 2019 00:00:00 +0000
+Subject: [PATCH] Change foo
 
-pub struct DiffParser<R> {
-    inner: R,
-    state: State,
-    pub line: Vec<u8>,
+Say bar instead.
+---
+ foo | 2 +-
+ 1 file changed, 1 insertion(+), 1 deletion(-)
+
+diff --git a/foo b/foo
+index 1234567..89abcde 100644
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+-- 
+2.20.1
+";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    let preamble = lines
+        .iter()
+        .take_while(|line| matches!(line, OwnedDiffLine::Preamble(_)))
+        .map(|line| line.as_diff_line().to_string() + "\n")
+        .collect::<String>();
+    let start = diff.windows(5).position(|w| w == b"diff ").unwrap();
+    assert_eq!(preamble.as_bytes(), &diff[..start]);
+    assert!(matches!(lines[10], OwnedDiffLine::GitHeader { .. }));
+    assert_eq!(lines[17], OwnedDiffLine::Junk(b"-- \n".to_vec()));
+    assert_eq!(lines[18], OwnedDiffLine::Junk(b"2.20.1\n".to_vec()));
 }
 
-impl<R: BufRead> DiffParser<R> {
-    pub fn new(inner: R) -> Self {
-        Self {
-            inner,
-            state: State::Junk,
-            line: vec![],
-        }
+#[test]
+fn test_skip_preamble() {
+    let diff = b"Build log for #1234
+$ git diff
+some noise --- not a header
+diff --git a/foo b/foo
+--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+trailing junk
+";
+    let mut parser = DiffParser::new(&diff[..]).skip_preamble().with_stats();
+    let mut lines = vec![];
+    while let Some(line) = parser.next_line() {
+        let line = line.unwrap().to_owned();
+        lines.push((parser.line_number(), line));
     }
 
-    pub fn next_line(&mut self) -> Option<io::Result<DiffLine>> {
-        self.line.clear();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(lines[0].0, 4);
+    assert!(matches!(lines[0].1, OwnedDiffLine::GitHeader { .. }));
+    assert_eq!(
+        lines[6],
+        (10, OwnedDiffLine::Junk(b"trailing junk\n".to_vec()))
+    );
+    assert_eq!(parser.stats().files, 1);
 
-        let parsed = self.inner.read_until(b'\n', &mut self.line);
+    let lines = DiffParser::new(&b"just\nnoise\n"[..])
+        .skip_preamble()
+        .collect::<Vec<_>>();
+    assert!(lines.is_empty());
+}
 
-        match parsed {
-            Ok(0) => return None,
-            Ok(_) => (),
-            Err(err) => return Some(Err(err)),
-        };
+#[test]
+fn test_zero_length_hunks() {
+    let diff = b"--- /dev/null
++++ b/new
+@@ -0,0 +1,2 @@
++foo
++bar
+--- a/old
++++ /dev/null
+@@ -1,2 +0,0 @@
+-foo
+-bar
+--- a/empty
++++ b/empty
+@@ -0,0 +0,0 @@
+";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    let text = lines
+        .iter()
+        .map(|line| line.as_diff_line().to_string())
+        .collect::<Vec<_>>();
 
-        match self.state {
-            State::Junk => {
-                let line = parse_old_file(&self.line[..]);
-                if let DiffLine::OldFile(_) = line {
-                    self.state = State::OldFile;
-                }
+    assert_eq!(text[2], "@@ -0,0 +1,2 @@");
+    assert_eq!(lines[4], OwnedDiffLine::Inserted(b"bar\n".to_vec(), 1));
+    assert!(matches!(lines[5], OwnedDiffLine::OldFile(_)));
+    assert_eq!(text[7], "@@ -1,2 +0,0 @@");
+    assert_eq!(lines[9], OwnedDiffLine::Deleted(b"bar\n".to_vec(), 1));
+    assert!(matches!(lines[10], OwnedDiffLine::OldFile(_)));
+    assert_eq!(text[12], "@@ -0,0 +0,0 @@");
+    assert_eq!(lines.len(), 13);
+}
 
-                Some(Ok(line))
-            }
-            State::OldFile => {
-                let line = parse_new_file(&self.line[..]);
+#[test]
+fn test_recursive_diff() {
+    let diff = b"Only in a: gone
+diff -r a/foo b/foo
+1c1
+< foo
+---
+> bar
+Files a/bin and b/bin differ
+Only in b/sub: new
+";
 
-                if let DiffLine::NewFile(_) = line {
-                    self.state = State::NewFile;
-                } else {
-                    self.state = State::Junk;
-                }
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        OwnedDiffLine::OnlyIn {
+            dir: b"a".to_vec(),
+            file: b"gone".to_vec()
+        }
+    );
+    assert_eq!(
+        lines[6],
+        OwnedDiffLine::FilesDiffer(b"a/bin".to_vec(), b"b/bin".to_vec())
+    );
+    assert_eq!(
+        lines[7],
+        OwnedDiffLine::OnlyIn {
+            dir: b"b/sub".to_vec(),
+            file: b"new".to_vec()
+        }
+    );
+    assert_eq!(lines[7].to_string(), "Only in b/sub: new");
+}
 
-                Some(Ok(line))
-            }
-            State::NewFile => {
-                let line = parse_hunk(&self.line[..]);
+#[test]
+fn test_context_diff() {
+    let diff = b"*** a.txt\tWed Oct 14 11:00:40 2026
+--- b.txt\tWed Oct 14 11:00:40 2026
+***************
+*** 1,4 ****
+  one
+! two
+- three
+  four
+--- 1,4 ----
+  one
+! TWO
+  four
++ five
+***************
+*** 9,11 ****
+--- 10,13 ----
+  ten
+  eleven
+  twelve
++ thirteen
+";
 
-                if let DiffLine::Hunk(ref info) = line {
-                    self.state = State::Hunk(info.old_line_len as i32, info.new_line_len as i32);
-                } else {
-                    self.state = State::Junk;
-                }
+    let lines = DiffParser::new(&diff[..])
+        .context_format()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
 
-                Some(Ok(line))
-            }
-            State::Hunk(ref mut old, ref mut new) => {
-                let line = parse_delta(&self.line[..]);
-                match line {
-                    DiffLine::Context(_) | DiffLine::Modified(_) => {
-                        *old -= 1;
-                        *new -= 1;
-                    }
-                    DiffLine::Inserted(_) => {
-                        *new -= 1;
-                    }
-                    DiffLine::Deleted(_) => {
-                        *old -= 1;
-                    }
-                    DiffLine::NoNewlineAtEof => (),
-                    DiffLine::Junk(line) => {
-                        self.state = State::Junk;
-                        return Some(Ok(DiffLine::Junk(line)));
-                    }
-                    _ => unreachable!(),
-                };
+    assert_eq!(
+        lines[0],
+        OwnedDiffLine::OldFile(OwnedFileInfo {
+            filename: b"a.txt".to_vec(),
+            metadata: Some(b"Wed Oct 14 11:00:40 2026".to_vec())
+        })
+    );
+    assert_eq!(
+        lines[1],
+        OwnedDiffLine::NewFile(OwnedFileInfo {
+            filename: b"b.txt".to_vec(),
+            metadata: Some(b"Wed Oct 14 11:00:40 2026".to_vec())
+        })
+    );
+    assert_eq!(lines[2], OwnedDiffLine::ContextHunk);
+    assert_eq!(lines[3], OwnedDiffLine::OldRange(1, 4));
+    assert_eq!(lines[4], OwnedDiffLine::Context(b"one\n".to_vec(), 2));
+    assert_eq!(lines[5], OwnedDiffLine::Modified(b"two\n".to_vec(), 2));
+    assert_eq!(lines[6], OwnedDiffLine::Deleted(b"three\n".to_vec(), 2));
+    assert_eq!(lines[8], OwnedDiffLine::NewRange(1, 4));
+    assert_eq!(lines[10], OwnedDiffLine::Modified(b"TWO\n".to_vec(), 2));
+    assert_eq!(lines[12], OwnedDiffLine::Inserted(b"five\n".to_vec(), 2));
+    assert_eq!(lines[13], OwnedDiffLine::ContextHunk);
+    assert_eq!(lines[14], OwnedDiffLine::OldRange(9, 11));
+    assert_eq!(lines[15], OwnedDiffLine::NewRange(10, 13));
+    assert_eq!(
+        lines[19],
+        OwnedDiffLine::Inserted(b"thirteen\n".to_vec(), 2)
+    );
+    assert_eq!(lines.len(), 20);
 
-                if (*old < 0 || *new < 0) || (*old == 0 && *new == 0) {
-                    self.state = State::NewFile;
-                }
+    assert_eq!(lines[2].to_string(), "***************");
+    assert_eq!(lines[3].to_string(), "*** 1,4 ****");
+    assert_eq!(lines[15].to_string(), "--- 10,13 ----");
 
-                Some(Ok(line))
-            }
+    let detected = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(detected, lines);
+}
+
+#[test]
+fn test_format() {
+    let format = |diff: &[u8]| {
+        let mut parser = DiffParser::new(diff);
+        while let Some(line) = parser.next_line() {
+            line.unwrap();
         }
-    }
+        parser.format()
+    };
+
+    assert_eq!(format(b"junk\n"), None);
+    assert_eq!(
+        format(b"--- a\n+++ b\n@@ -1 +1 @@\n-a\n+b\n"),
+        Some(DiffFormat::Unified)
+    );
+    assert_eq!(
+        format(b"*** a\n--- b\n***************\n*** 1 ****\n! a\n--- 1 ----\n! b\n"),
+        Some(DiffFormat::Context)
+    );
+    assert_eq!(
+        format(b"diff --git a/a b/a\n--- a/a\n+++ b/a\n@@ -1 +1 @@\n-a\n+b\n"),
+        Some(DiffFormat::Git)
+    );
+
+    // "*** " isn't a header once we know it's a unified diff.
+    let diff = b"--- a\n+++ b\n@@ -1 +1 @@\n-a\n+b\n*** c\n--- d\n+++ d\n";
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(lines[5], OwnedDiffLine::Junk(b"*** c\n".to_vec()));
+    assert!(matches!(lines[7], OwnedDiffLine::NewFile(_)));
+
+    let parser = DiffParser::new(&b""[..]).context_format();
+    assert_eq!(parser.format(), Some(DiffFormat::Context));
 }
@@ -0,0 +1,1966 @@
+//! Stateless parsers for individual diff lines.
+//!
+//! `DiffParser` uses these to classify each line according to where it is in
+//! the diff.  They're exposed for callers who already know what kind of line
+//! they're looking at, and want to parse it without running the state machine.
+//! Anything unrecognised comes back as `DiffLine::Junk`.
+
+use crate::{
+    BinaryPatchKind, CombinedHunkInfo, DiffLine, FileInfo, HunkInfo, PropertyChange, Side,
+};
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+// Strip a line ending, which may be a NUL from `DiffParser::with_terminator()`.
+pub(crate) fn chomp(slice: &[u8]) -> &[u8] {
+    if slice.ends_with(b"\r\n") {
+        &slice[..slice.len() - 2]
+    } else if slice.ends_with(b"\n") || slice.ends_with(b"\0") {
+        &slice[..slice.len() - 1]
+    } else {
+        slice
+    }
+}
+
+#[test]
+fn test_chomp() {
+    assert_eq!(chomp(b""), b"");
+    assert_eq!(chomp(b"foo"), b"foo");
+    assert_eq!(chomp(b"foo\r\n"), b"foo");
+    assert_eq!(chomp(b"foo\n"), b"foo");
+    assert_eq!(chomp(b"foo\0"), b"foo");
+}
+
+fn parse_u64_radix(bytes: &[u8], radix: u32) -> Option<u64> {
+    if bytes.is_empty() {
+        return None;
+    }
+
+    bytes.iter().try_fold(0u64, |acc, byte| {
+        acc.checked_mul(radix as u64).and_then(|acc| {
+            (*byte as char)
+                .to_digit(radix)
+                .and_then(|digit| acc.checked_add(digit as u64))
+        })
+    })
+}
+
+fn parse_u64(bytes: &[u8]) -> Option<u64> {
+    parse_u64_radix(bytes, 10)
+}
+
+#[test]
+fn test_parse_u64() {
+    assert_eq!(parse_u64(b"0"), Some(0));
+    assert_eq!(parse_u64(b"10"), Some(10));
+    assert_eq!(parse_u64(b"42"), Some(42));
+    assert_eq!(parse_u64(b"4294967296"), Some(4294967296));
+    assert_eq!(parse_u64(b"18446744073709551615"), Some(u64::MAX));
+    assert_eq!(parse_u64(b"18446744073709551616"), None);
+    assert_eq!(parse_u64(b"12345six"), None);
+    assert_eq!(parse_u64(b"nope"), None);
+    assert_eq!(parse_u64(b""), None);
+}
+
+#[test]
+fn test_parse_octal() {
+    assert_eq!(parse_u64_radix(b"100644", 8), Some(0o100644));
+    assert_eq!(parse_u64_radix(b"100755", 8), Some(0o100755));
+    assert_eq!(
+        parse_u64_radix(b"1777777777777777777777", 8),
+        Some(u64::MAX)
+    );
+    assert_eq!(parse_u64_radix(b"2000000000000000000000", 8), None);
+    assert_eq!(parse_u64_radix(b"100648", 8), None);
+    assert_eq!(parse_u64_radix(b"", 8), None);
+}
+
+fn parse_range(bytes: &[u8]) -> Option<(u64, u64)> {
+    let mut bits = bytes.split(|&b| b == b',').flat_map(parse_u64);
+
+    Some((bits.next()?, bits.next().unwrap_or(1)))
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(parse_range(b"12,24"), Some((12, 24)));
+    assert_eq!(parse_range(b"12"), Some((12, 1)));
+    assert_eq!(parse_range(b"42,"), Some((42, 1)));
+    assert_eq!(parse_range(b","), None);
+    assert_eq!(parse_range(b""), None);
+}
+
+fn parse_fileinfo(line: &[u8]) -> FileInfo<'_> {
+    let line = &line[3..];
+    let line = chomp(line.strip_prefix(b" ").unwrap_or(line));
+
+    // A quoted filename can hold a tab, so look for one after it.
+    if let Some(len) = quoted_len(line) {
+        let metadata = match &line[len..] {
+            [] => Some(None),
+            [b'\t', metadata @ ..] => Some(Some(metadata)),
+            _ => None,
+        };
+
+        if let (Some(metadata), Some(filename)) = (metadata, unquote(&line[..len])) {
+            return FileInfo { filename, metadata };
+        }
+    }
+
+    match line.iter().position(|&b| b == b'\t') {
+        Some(tab) => FileInfo {
+            filename: Cow::Borrowed(&line[..tab]),
+            metadata: Some(&line[tab + 1..]),
+        },
+        None => FileInfo {
+            filename: Cow::Borrowed(line),
+            metadata: None,
+        },
+    }
+}
+
+#[test]
+fn test_parse_fileinfo() {
+    for line in &[&b"--- foo\tbar\r\n"[..], b"--- foo\tbar\n", b"--- foo\tbar"] {
+        assert_eq!(
+            parse_fileinfo(line),
+            FileInfo {
+                filename: Cow::Borrowed(b"foo"),
+                metadata: Some(b"bar")
+            }
+        );
+    }
+
+    for line in &[&b"--- foo\r\n"[..], b"--- foo\n", b"--- foo"] {
+        assert_eq!(
+            parse_fileinfo(line),
+            FileInfo {
+                filename: Cow::Borrowed(b"foo"),
+                metadata: None
+            }
+        );
+    }
+
+    assert_eq!(
+        parse_fileinfo(b"--- \"a\\tb\"\t2024-01-01\n"),
+        FileInfo {
+            filename: Cow::Borrowed(b"a\tb"),
+            metadata: Some(b"2024-01-01")
+        }
+    );
+    assert_eq!(
+        parse_fileinfo(b"+++ \"b/caf\\303\\251 \\\"x\\\"\"\n"),
+        FileInfo {
+            filename: Cow::Borrowed("b/caf\u{e9} \"x\"".as_bytes()),
+            metadata: None
+        }
+    );
+    assert_eq!(
+        parse_fileinfo(b"--- \"plain\"\n"),
+        FileInfo {
+            filename: Cow::Borrowed(b"plain"),
+            metadata: None
+        }
+    );
+    // Not quoting after all, so split on the first tab as usual.
+    assert_eq!(
+        parse_fileinfo(b"--- \"a\"b\tc\n"),
+        FileInfo {
+            filename: Cow::Borrowed(b"\"a\"b"),
+            metadata: Some(b"c")
+        }
+    );
+    assert_eq!(
+        parse_fileinfo(b"--- \"a\\qb\"\n"),
+        FileInfo {
+            filename: Cow::Borrowed(b"\"a\\qb\""),
+            metadata: None
+        }
+    );
+}
+
+// Length of the C-quoted string at the start of `bytes`, including both quotes.
+fn quoted_len(bytes: &[u8]) -> Option<usize> {
+    if bytes.first() != Some(&b'"') {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (i, &b) in bytes.iter().enumerate().skip(1) {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return Some(i + 1),
+            _ => (),
+        }
+    }
+
+    None
+}
+
+#[test]
+fn test_quoted_len() {
+    assert_eq!(quoted_len(b""), None);
+    assert_eq!(quoted_len(b"foo"), None);
+    assert_eq!(quoted_len(b"\"foo"), None);
+    assert_eq!(quoted_len(b"\"foo\""), Some(5));
+    assert_eq!(quoted_len(b"\"f\\\"o\" bar"), Some(6));
+}
+
+// Decode a C-quoted string, quotes and all, as git writes unusual paths.
+// Returns `None` for escapes git doesn't use.
+fn unquote(quoted: &[u8]) -> Option<Cow<'_, [u8]>> {
+    let inner = &quoted[1..quoted.len() - 1];
+    if !inner.contains(&b'\\') {
+        return Some(Cow::Borrowed(inner));
+    }
+
+    let octal = |b: Option<&u8>| match b {
+        Some(&b @ b'0'..=b'7') => Some(b - b'0'),
+        _ => None,
+    };
+
+    let mut unquoted = Vec::with_capacity(inner.len());
+    let mut bytes = inner.iter();
+    while let Some(&b) = bytes.next() {
+        if b != b'\\' {
+            unquoted.push(b);
+            continue;
+        }
+
+        let escaped = match *bytes.next()? {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'v' => 0x0b,
+            b'f' => 0x0c,
+            b'r' => b'\r',
+            b @ b'"' | b @ b'\\' => b,
+            b @ b'0'..=b'3' => {
+                let (mid, low) = (octal(bytes.next())?, octal(bytes.next())?);
+                (b - b'0') << 6 | mid << 3 | low
+            }
+            _ => return None,
+        };
+        unquoted.push(escaped);
+    }
+
+    Some(Cow::Owned(unquoted))
+}
+
+/// Decode a path C-quoted by git, as it does for paths with control
+/// characters, quotes or backslashes, and with `core.quotepath` for bytes
+/// above 0x7f.  Anything else, including malformed quoting, is returned as
+/// it is.
+///
+/// ```
+/// use diffparser::line::unquote_path;
+///
+/// assert_eq!(&*unquote_path(b"\"caf\\303\\251.txt\""), "caf\u{e9}.txt".as_bytes());
+/// assert_eq!(&*unquote_path(b"plain.txt"), b"plain.txt");
+/// ```
+pub fn unquote_path(raw: &[u8]) -> Cow<'_, [u8]> {
+    match quoted_len(raw) {
+        Some(len) if len == raw.len() => unquote(raw).unwrap_or(Cow::Borrowed(raw)),
+        _ => Cow::Borrowed(raw),
+    }
+}
+
+#[test]
+fn test_unquote_path() {
+    let borrowed = |path: Cow<'_, [u8]>| matches!(path, Cow::Borrowed(_));
+
+    assert!(borrowed(unquote_path(b"foo/bar")));
+    assert!(borrowed(unquote_path(b"\"foo bar\"")));
+    assert_eq!(&*unquote_path(b"\"foo bar\""), b"foo bar");
+    assert_eq!(
+        &*unquote_path(b"\"\\346\\227\\245\\346\\234\\254/\\tx\\n\""),
+        "\u{65e5}\u{672c}/\tx\n".as_bytes()
+    );
+    assert_eq!(&*unquote_path(b"\"a\\\"b\\\\c\""), b"a\"b\\c");
+    assert_eq!(&*unquote_path(b"\"\\377\""), b"\xff");
+    assert_eq!(&*unquote_path(b"\"a\\qb\""), b"\"a\\qb\"");
+    assert_eq!(&*unquote_path(b"\"a\\40\""), b"\"a\\40\"");
+    assert_eq!(&*unquote_path(b"\"a\" b"), b"\"a\" b");
+    assert_eq!(&*unquote_path(b"\"a"), b"\"a");
+    assert_eq!(&*unquote_path(b""), b"");
+}
+
+/// C-quote a path as git does, if it has control characters, quotes or
+/// backslashes, or bytes above 0x7f.  Other paths are returned as they are.
+///
+/// ```
+/// use diffparser::line::quote_path;
+///
+/// assert_eq!(&*quote_path(b"foo bar"), b"foo bar");
+/// assert_eq!(&*quote_path("caf\u{e9}\t".as_bytes()), b"\"caf\\303\\251\\t\"");
+/// ```
+pub fn quote_path(path: &[u8]) -> Cow<'_, [u8]> {
+    let needs_quoting = |b: u8| !(0x20..0x7f).contains(&b) || b == b'"' || b == b'\\';
+    if !path.iter().copied().any(needs_quoting) {
+        return Cow::Borrowed(path);
+    }
+
+    let mut quoted = Vec::with_capacity(path.len() + 2);
+    quoted.push(b'"');
+    for &b in path {
+        let escaped = match b {
+            0x07 => b'a',
+            0x08 => b'b',
+            b'\t' => b't',
+            b'\n' => b'n',
+            0x0b => b'v',
+            0x0c => b'f',
+            b'\r' => b'r',
+            b'"' | b'\\' => b,
+            b if needs_quoting(b) => {
+                quoted.extend_from_slice(format!("\\{:03o}", b).as_bytes());
+                continue;
+            }
+            b => {
+                quoted.push(b);
+                continue;
+            }
+        };
+        quoted.extend_from_slice(&[b'\\', escaped]);
+    }
+    quoted.push(b'"');
+
+    Cow::Owned(quoted)
+}
+
+#[test]
+fn test_quote_path() {
+    for path in [
+        &b"foo/bar"[..],
+        b"foo bar",
+        b"a\"b\\c",
+        b"\x07\x08\t\n\x0b\x0c\r\x01\x7f",
+        "\u{65e5}\u{672c}/x".as_bytes(),
+        b"\xff",
+    ] {
+        assert_eq!(&*unquote_path(&quote_path(path)), path);
+    }
+
+    assert!(matches!(quote_path(b"foo bar"), Cow::Borrowed(_)));
+    assert_eq!(&*quote_path(b"a\"b\\c"), b"\"a\\\"b\\\\c\"");
+    assert_eq!(&*quote_path(b"\x01\x7f"), b"\"\\001\\177\"");
+}
+
+fn strip_component(path: &[u8]) -> &[u8] {
+    path.iter()
+        .position(|&b| b == b'/')
+        .map_or(path, |pos| &path[pos + 1..])
+}
+
+// Split the "a/foo b/foo" part of a "diff --git" line.  Names containing
+// unusual characters are C-quoted, but spaces aren't, so for unquoted names
+// prefer a split that gives the same path on both sides.
+fn split_git_paths(paths: &[u8]) -> Option<(&[u8], &[u8])> {
+    if let Some(len) = quoted_len(paths) {
+        return match &paths[len..] {
+            [b' ', new @ ..] if !new.is_empty() => Some((&paths[..len], new)),
+            _ => None,
+        };
+    }
+
+    if paths.ends_with(b"\"") {
+        return (1..paths.len())
+            .rev()
+            .filter(|&pos| paths[pos - 1] == b' ')
+            .find(|&pos| quoted_len(&paths[pos..]) == Some(paths.len() - pos))
+            .filter(|&pos| pos > 1)
+            .map(|pos| (&paths[..pos - 1], &paths[pos..]));
+    }
+
+    if paths.len() % 2 == 1 {
+        let mid = paths.len() / 2;
+        let (old, new) = (&paths[..mid], &paths[mid + 1..]);
+        if paths[mid] == b' ' && strip_component(old) == strip_component(new) {
+            return Some((old, new));
+        }
+    }
+
+    let pos = paths
+        .windows(b" b/".len())
+        .position(|win| win == b" b/")
+        .or_else(|| paths.iter().position(|&b| b == b' '))?;
+
+    match (&paths[..pos], &paths[pos + 1..]) {
+        (old, new) if !old.is_empty() && !new.is_empty() => Some((old, new)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_split_git_paths() {
+    assert_eq!(split_git_paths(b""), None);
+    assert_eq!(split_git_paths(b"a/foo"), None);
+    assert_eq!(split_git_paths(b"a/foo "), None);
+    assert_eq!(
+        split_git_paths(b"a/foo b/foo"),
+        Some((&b"a/foo"[..], &b"b/foo"[..]))
+    );
+    assert_eq!(
+        split_git_paths(b"a/foo bar b/foo bar"),
+        Some((&b"a/foo bar"[..], &b"b/foo bar"[..]))
+    );
+    assert_eq!(
+        split_git_paths(b"a/old name b/new name"),
+        Some((&b"a/old name"[..], &b"b/new name"[..]))
+    );
+    assert_eq!(
+        split_git_paths(b"\"a/t\\tb\" \"b/t\\tb\""),
+        Some((&b"\"a/t\\tb\""[..], &b"\"b/t\\tb\""[..]))
+    );
+    assert_eq!(
+        split_git_paths(b"a/foo \"b/t\\tb \""),
+        Some((&b"a/foo"[..], &b"\"b/t\\tb \""[..]))
+    );
+}
+
+/// Parse a `diff --git a/foo b/foo` line.
+///
+/// ```
+/// use diffparser::{line::parse_git_header, DiffLine};
+///
+/// assert_eq!(
+///     parse_git_header(b"diff --git a/foo.c b/foo.c\n"),
+///     DiffLine::GitHeader { old_path: b"a/foo.c", new_path: b"b/foo.c" }
+/// );
+/// ```
+pub fn parse_git_header(line: &[u8]) -> DiffLine<'_> {
+    if line.starts_with(b"diff --git ") {
+        if let Some((old_path, new_path)) = split_git_paths(chomp(&line[b"diff --git ".len()..])) {
+            return DiffLine::GitHeader { old_path, new_path };
+        }
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_git_header() {
+    assert_eq!(parse_git_header(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_git_header(b"diff --git \n"),
+        DiffLine::Junk(b"diff --git \n")
+    );
+    assert_eq!(
+        parse_git_header(b"diff --git a/foo b/foo\n"),
+        DiffLine::GitHeader {
+            old_path: b"a/foo",
+            new_path: b"b/foo"
+        }
+    );
+    assert_eq!(
+        parse_git_header(b"diff --git a/foo b/bar\r\n"),
+        DiffLine::GitHeader {
+            old_path: b"a/foo",
+            new_path: b"b/bar"
+        }
+    );
+}
+
+fn is_hex(bytes: &[u8]) -> bool {
+    !bytes.is_empty() && bytes.iter().all(u8::is_ascii_hexdigit)
+}
+
+// The commit in a `Subproject commit` line of a submodule's hunk, which is
+// suffixed `-dirty` if it has changes of its own.
+pub(crate) fn parse_subproject(payload: &[u8]) -> Option<&[u8]> {
+    let commit = chomp(payload.strip_prefix(b"Subproject commit ")?);
+    Some(commit).filter(|commit| is_hex(commit.strip_suffix(b"-dirty").unwrap_or(commit)))
+}
+
+#[test]
+fn test_parse_subproject() {
+    assert_eq!(
+        parse_subproject(b"Subproject commit 1234abc\n"),
+        Some(&b"1234abc"[..])
+    );
+    assert_eq!(
+        parse_subproject(b"Subproject commit 1234abc-dirty\n"),
+        Some(&b"1234abc-dirty"[..])
+    );
+    assert_eq!(parse_subproject(b"Subproject commit\n"), None);
+    assert_eq!(parse_subproject(b"Subproject commit xyz\n"), None);
+    assert_eq!(parse_subproject(b"foo\n"), None);
+}
+
+fn is_octal(bytes: &[u8]) -> bool {
+    !bytes.is_empty() && bytes.iter().all(|b| (b'0'..=b'7').contains(b))
+}
+
+fn parse_index(line: &[u8]) -> DiffLine<'_> {
+    if line.starts_with(b"index ") {
+        // index 1234567..89abcde 100644
+        let mut fields = chomp(&line[b"index ".len()..]).splitn(2, |&b| b == b' ');
+        let oids = fields.next().unwrap_or_default();
+        let mode = fields.next();
+
+        if let Some(pos) = oids.windows(2).position(|win| win == b"..") {
+            let (old_oid, new_oid) = (&oids[..pos], &oids[pos + 2..]);
+            if is_hex(old_oid) && is_hex(new_oid) && mode.is_none_or(is_octal) {
+                return DiffLine::Index {
+                    old_oid,
+                    new_oid,
+                    mode,
+                };
+            }
+        }
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_index() {
+    assert_eq!(parse_index(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_index(b"index 1234567..89abcde 100644\n"),
+        DiffLine::Index {
+            old_oid: b"1234567",
+            new_oid: b"89abcde",
+            mode: Some(b"100644")
+        }
+    );
+    assert_eq!(
+        parse_index(b"index 0000000000000000000000000000000000000000..e69de29bb\n"),
+        DiffLine::Index {
+            old_oid: b"0000000000000000000000000000000000000000",
+            new_oid: b"e69de29bb",
+            mode: None
+        }
+    );
+    assert_eq!(
+        parse_index(b"index e69de29..0000000\r\n"),
+        DiffLine::Index {
+            old_oid: b"e69de29",
+            new_oid: b"0000000",
+            mode: None
+        }
+    );
+    assert_eq!(
+        parse_index(b"index abc..xyz 100644\n"),
+        DiffLine::Junk(b"index abc..xyz 100644\n")
+    );
+    assert_eq!(
+        parse_index(b"index abc.. 100644\n"),
+        DiffLine::Junk(b"index abc.. 100644\n")
+    );
+    assert_eq!(
+        parse_index(b"index abc..def 10064x\n"),
+        DiffLine::Junk(b"index abc..def 10064x\n")
+    );
+    assert_eq!(
+        parse_index(b"index abcdef\n"),
+        DiffLine::Junk(b"index abcdef\n")
+    );
+}
+
+pub(crate) fn parse_mode(line: &[u8], prefix: &[u8]) -> Option<u32> {
+    if line.starts_with(prefix) {
+        parse_u64_radix(chomp(&line[prefix.len()..]), 8).and_then(|mode| u32::try_from(mode).ok())
+    } else {
+        None
+    }
+}
+
+fn parse_path<'a>(line: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
+    if line.starts_with(prefix) {
+        Some(chomp(&line[prefix.len()..])).filter(|path| !path.is_empty())
+    } else {
+        None
+    }
+}
+
+fn parse_similarity(line: &[u8]) -> Option<u8> {
+    if line.starts_with(b"similarity index ") {
+        let pct = chomp(&line[b"similarity index ".len()..]);
+        if pct.ends_with(b"%") {
+            return parse_u64(&pct[..pct.len() - 1])
+                .filter(|&pct| pct <= 100)
+                .map(|pct| pct as u8);
+        }
+    }
+
+    None
+}
+
+/// Parse one of the extended header lines git puts between `diff --git` and
+/// `---`, such as `index`, `new file mode` or `rename from`.
+///
+/// ```
+/// use diffparser::{line::parse_extended_header, DiffLine};
+///
+/// assert_eq!(
+///     parse_extended_header(b"new file mode 100644\n"),
+///     DiffLine::NewFileMode(0o100644)
+/// );
+/// assert_eq!(
+///     parse_extended_header(b"rename from foo.c\n"),
+///     DiffLine::RenameFrom(b"foo.c")
+/// );
+/// ```
+pub fn parse_extended_header(line: &[u8]) -> DiffLine<'_> {
+    if let Some(pct) = parse_similarity(line) {
+        return DiffLine::SimilarityIndex(pct);
+    }
+
+    if let Some(path) = parse_path(line, b"rename from ") {
+        return DiffLine::RenameFrom(path);
+    }
+
+    if let Some(path) = parse_path(line, b"rename to ") {
+        return DiffLine::RenameTo(path);
+    }
+
+    if let Some(path) = parse_path(line, b"copy from ") {
+        return DiffLine::CopyFrom(path);
+    }
+
+    if let Some(path) = parse_path(line, b"copy to ") {
+        return DiffLine::CopyTo(path);
+    }
+
+    if let Some(mode) = parse_mode(line, b"new file mode ") {
+        return DiffLine::NewFileMode(mode);
+    }
+
+    if let Some(mode) = parse_mode(line, b"deleted file mode ") {
+        return DiffLine::DeletedFileMode(mode);
+    }
+
+    if let Some(mode) = parse_mode(line, b"old mode ") {
+        return DiffLine::OldMode(mode);
+    }
+
+    if let Some(mode) = parse_mode(line, b"new mode ") {
+        return DiffLine::NewMode(mode);
+    }
+
+    if chomp(line) == b"GIT binary patch" {
+        return DiffLine::GitBinaryPatch;
+    }
+
+    parse_index(line)
+}
+
+#[test]
+fn test_parse_extended_header() {
+    assert_eq!(parse_extended_header(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_extended_header(b"new file mode 100644\n"),
+        DiffLine::NewFileMode(0o100644)
+    );
+    assert_eq!(
+        parse_extended_header(b"new file mode 100755\n"),
+        DiffLine::NewFileMode(0o100755)
+    );
+    assert_eq!(
+        parse_extended_header(b"deleted file mode 100644\r\n"),
+        DiffLine::DeletedFileMode(0o100644)
+    );
+    assert_eq!(
+        parse_extended_header(b"deleted file mode 100755\n"),
+        DiffLine::DeletedFileMode(0o100755)
+    );
+    assert_eq!(
+        parse_extended_header(b"old mode 100644\n"),
+        DiffLine::OldMode(0o100644)
+    );
+    assert_eq!(
+        parse_extended_header(b"new mode 100755\r\n"),
+        DiffLine::NewMode(0o100755)
+    );
+    assert_eq!(
+        parse_extended_header(b"new mode 1007558\n"),
+        DiffLine::Junk(b"new mode 1007558\n")
+    );
+    assert_eq!(
+        parse_extended_header(b"new file mode 10064x\n"),
+        DiffLine::Junk(b"new file mode 10064x\n")
+    );
+    assert_eq!(
+        parse_extended_header(b"new file mode 100648\n"),
+        DiffLine::Junk(b"new file mode 100648\n")
+    );
+    assert_eq!(
+        parse_extended_header(b"deleted file mode \n"),
+        DiffLine::Junk(b"deleted file mode \n")
+    );
+    assert_eq!(
+        parse_extended_header(b"similarity index 95%\n"),
+        DiffLine::SimilarityIndex(95)
+    );
+    assert_eq!(
+        parse_extended_header(b"similarity index 0%\n"),
+        DiffLine::SimilarityIndex(0)
+    );
+    assert_eq!(
+        parse_extended_header(b"similarity index 100%\n"),
+        DiffLine::SimilarityIndex(100)
+    );
+    assert_eq!(
+        parse_extended_header(b"similarity index 101%\n"),
+        DiffLine::Junk(b"similarity index 101%\n")
+    );
+    assert_eq!(
+        parse_extended_header(b"similarity index 95\n"),
+        DiffLine::Junk(b"similarity index 95\n")
+    );
+    assert_eq!(
+        parse_extended_header(b"rename from foo/bar baz\n"),
+        DiffLine::RenameFrom(b"foo/bar baz")
+    );
+    assert_eq!(
+        parse_extended_header(b"rename to foo/qux\r\n"),
+        DiffLine::RenameTo(b"foo/qux")
+    );
+    assert_eq!(
+        parse_extended_header(b"rename to \n"),
+        DiffLine::Junk(b"rename to \n")
+    );
+    assert_eq!(
+        parse_extended_header(b"copy from foo\n"),
+        DiffLine::CopyFrom(b"foo")
+    );
+    assert_eq!(
+        parse_extended_header(b"copy to bar\n"),
+        DiffLine::CopyTo(b"bar")
+    );
+    assert_eq!(
+        parse_extended_header(b"index 1234567..89abcde\n"),
+        DiffLine::Index {
+            old_oid: b"1234567",
+            new_oid: b"89abcde",
+            mode: None
+        }
+    );
+    assert_eq!(
+        parse_extended_header(b"GIT binary patch\n"),
+        DiffLine::GitBinaryPatch
+    );
+}
+
+/// Parse the `literal 123` or `delta 123` line starting each section of a
+/// `GIT binary patch`.
+///
+/// ```
+/// use diffparser::{line::parse_binary_patch, BinaryPatchKind, DiffLine};
+///
+/// assert_eq!(
+///     parse_binary_patch(b"literal 1234\n"),
+///     DiffLine::BinaryPatch {
+///         kind: BinaryPatchKind::Literal,
+///         size: 1234,
+///     }
+/// );
+/// ```
+pub fn parse_binary_patch(line: &[u8]) -> DiffLine<'_> {
+    let chomped = chomp(line);
+    let (kind, size) = if let Some(size) = chomped.strip_prefix(b"literal ") {
+        (BinaryPatchKind::Literal, size)
+    } else if let Some(size) = chomped.strip_prefix(b"delta ") {
+        (BinaryPatchKind::Delta, size)
+    } else {
+        return DiffLine::Junk(line);
+    };
+
+    match parse_u64(size) {
+        Some(size) => DiffLine::BinaryPatch { kind, size },
+        None => DiffLine::Junk(line),
+    }
+}
+
+#[test]
+fn test_parse_binary_patch() {
+    assert_eq!(
+        parse_binary_patch(b"delta 42\r\n"),
+        DiffLine::BinaryPatch {
+            kind: BinaryPatchKind::Delta,
+            size: 42
+        }
+    );
+    assert_eq!(
+        parse_binary_patch(b"literal 0\n"),
+        DiffLine::BinaryPatch {
+            kind: BinaryPatchKind::Literal,
+            size: 0
+        }
+    );
+    assert_eq!(
+        parse_binary_patch(b"literal\n"),
+        DiffLine::Junk(b"literal\n")
+    );
+    assert_eq!(
+        parse_binary_patch(b"delta 4x\n"),
+        DiffLine::Junk(b"delta 4x\n")
+    );
+}
+
+/// Parse a line of base85 data in a `GIT binary patch`, which starts with a
+/// letter giving how many bytes it decodes to.  The empty line ending each
+/// section is `BinaryData` with no data.
+///
+/// ```
+/// use diffparser::{line::parse_binary_data, DiffLine};
+///
+/// assert_eq!(
+///     parse_binary_data(b"HcmV?d00001\n"),
+///     DiffLine::BinaryData(b"HcmV?d00001")
+/// );
+/// assert_eq!(parse_binary_data(b"\n"), DiffLine::BinaryData(b""));
+/// ```
+pub fn parse_binary_data(line: &[u8]) -> DiffLine<'_> {
+    let data = chomp(line);
+    let len = match data.first() {
+        None if !line.is_empty() => return DiffLine::BinaryData(data),
+        Some(&b @ b'A'..=b'Z') => b - b'A' + 1,
+        Some(&b @ b'a'..=b'z') => b - b'a' + 27,
+        _ => return DiffLine::Junk(line),
+    } as usize;
+
+    let base85 = |b: &u8| b.is_ascii_alphanumeric() || b"!#$%&()*+-;<=>?@^_`{|}~".contains(b);
+    if data.len() - 1 == len.div_ceil(4) * 5 && data[1..].iter().all(base85) {
+        DiffLine::BinaryData(data)
+    } else {
+        DiffLine::Junk(line)
+    }
+}
+
+#[test]
+fn test_parse_binary_data() {
+    assert_eq!(
+        parse_binary_data(b"zcmV;5Y3!Y\n"),
+        DiffLine::Junk(b"zcmV;5Y3!Y\n")
+    );
+    assert_eq!(
+        parse_binary_data(b"DcmV;5\r\n"),
+        DiffLine::BinaryData(b"DcmV;5")
+    );
+    assert_eq!(
+        parse_binary_data(b"EcmV;5Y3!Y0\n"),
+        DiffLine::BinaryData(b"EcmV;5Y3!Y0")
+    );
+    assert_eq!(
+        parse_binary_data(b"EcmV;5Y3!Y\n"),
+        DiffLine::Junk(b"EcmV;5Y3!Y\n")
+    );
+    assert_eq!(parse_binary_data(b"Dcm V\n"), DiffLine::Junk(b"Dcm V\n"));
+    assert_eq!(
+        parse_binary_data(b"diff --git a/b b/b\n"),
+        DiffLine::Junk(b"diff --git a/b b/b\n")
+    );
+    assert_eq!(parse_binary_data(b""), DiffLine::Junk(b""));
+}
+
+/// Parse the lines `diff -r` prints for directories rather than files:
+/// `Only in dir: file`, and `Files a and b differ` when run with `-q`.
+///
+/// ```
+/// use diffparser::{line::parse_only_in, DiffLine};
+///
+/// assert_eq!(
+///     parse_only_in(b"Only in a/foo: bar\n"),
+///     DiffLine::OnlyIn { dir: b"a/foo", file: b"bar" }
+/// );
+/// assert_eq!(
+///     parse_only_in(b"Files a/x and b/x differ\n"),
+///     DiffLine::FilesDiffer(b"a/x", b"b/x")
+/// );
+/// ```
+pub fn parse_only_in(line: &[u8]) -> DiffLine<'_> {
+    if let Some(rest) = parse_path(line, b"Only in ") {
+        if let Some(pos) = rest.windows(2).position(|win| win == b": ") {
+            let (dir, file) = (&rest[..pos], &rest[pos + 2..]);
+            if !dir.is_empty() && !file.is_empty() {
+                return DiffLine::OnlyIn { dir, file };
+            }
+        }
+    }
+
+    if let Some(rest) = parse_path(line, b"Files ") {
+        if let Some(rest) = rest.strip_suffix(b" differ") {
+            if let Some(pos) = rest.windows(5).position(|win| win == b" and ") {
+                let (a, b) = (&rest[..pos], &rest[pos + 5..]);
+                if !a.is_empty() && !b.is_empty() {
+                    return DiffLine::FilesDiffer(a, b);
+                }
+            }
+        }
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_only_in() {
+    assert_eq!(
+        parse_only_in(b"Only in a: b: c\r\n"),
+        DiffLine::OnlyIn {
+            dir: b"a",
+            file: b"b: c"
+        }
+    );
+    assert_eq!(
+        parse_only_in(b"Only in a:\n"),
+        DiffLine::Junk(b"Only in a:\n")
+    );
+    assert_eq!(
+        parse_only_in(b"Files and differ\n"),
+        DiffLine::Junk(b"Files and differ\n")
+    );
+    assert_eq!(
+        parse_only_in(b"Files a b and c d differ\n"),
+        DiffLine::FilesDiffer(b"a b", b"c d")
+    );
+    assert_eq!(parse_only_in(b"Files\n"), DiffLine::Junk(b"Files\n"));
+}
+
+// Anything that can begin a new file block.
+pub(crate) fn parse_file_start(line: &[u8]) -> DiffLine<'_> {
+    // Every header starts with one of these, so most junk can be passed
+    // over without trying each in turn.
+    if !matches!(
+        line.first(),
+        Some(b'd' | b'P' | b'O' | b'F' | b'I' | b'B' | b'-')
+    ) {
+        return DiffLine::Junk(line);
+    }
+
+    match parse_git_header(line) {
+        DiffLine::Junk(line) => match parse_property_changes(line) {
+            DiffLine::Junk(line) => match parse_only_in(line) {
+                DiffLine::Junk(line) => match parse_svn_index(line) {
+                    DiffLine::Junk(line) => parse_old_file(line),
+                    index => index,
+                },
+                only_in => only_in,
+            },
+            header => header,
+        },
+        header => header,
+    }
+}
+
+/// Parse a `--- ` old file header, or a `Binary files ... differ` line.
+///
+/// ```
+/// use diffparser::{line::parse_old_file, DiffLine, FileInfo};
+/// use std::borrow::Cow;
+///
+/// assert_eq!(
+///     parse_old_file(b"--- foo.c\t2019-01-01 00:00:00\n"),
+///     DiffLine::OldFile(FileInfo {
+///         filename: Cow::Borrowed(b"foo.c"),
+///         metadata: Some(b"2019-01-01 00:00:00"),
+///     })
+/// );
+/// assert_eq!(
+///     parse_old_file(b"Binary files a.png and b.png differ\n"),
+///     DiffLine::Binaries(b"a.png", b"b.png")
+/// );
+/// ```
+pub fn parse_old_file(line: &[u8]) -> DiffLine<'_> {
+    match line.first() {
+        // Binary files sigh and blegh differ
+        Some(b'B') => {
+            if let Some(x) = chomp(line)
+                .strip_prefix(b"Binary files ")
+                .and_then(|x| x.strip_suffix(b" differ"))
+            {
+                if let Some(pos) = x.windows(b" and ".len()).position(|win| win == b" and ") {
+                    return DiffLine::Binaries(&x[0..pos], &x[pos + 5..]);
+                }
+            }
+        }
+        Some(b'-') if chomp(line).len() >= "--- x".len() && line.starts_with(b"--- ") => {
+            return DiffLine::OldFile(parse_fileinfo(line));
+        }
+        _ => (),
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_old_file() {
+    assert_eq!(parse_old_file(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_old_file(b"Binary files and differ\n"),
+        DiffLine::Junk(b"Binary files and differ\n")
+    );
+    assert_eq!(
+        parse_old_file(b"Binary files foo and bar differ\n"),
+        DiffLine::Binaries(b"foo", b"bar")
+    );
+    assert_eq!(
+        parse_old_file(b"--- x\n"),
+        DiffLine::OldFile(FileInfo {
+            filename: Cow::Borrowed(b"x"),
+            metadata: None
+        })
+    );
+    assert_eq!(
+        parse_old_file(b"--- foo/bar\n"),
+        DiffLine::OldFile(FileInfo {
+            filename: Cow::Borrowed(b"foo/bar"),
+            metadata: None
+        })
+    );
+    assert_eq!(
+        parse_old_file(b"--- foo/bar\tfoo bar\n"),
+        DiffLine::OldFile(FileInfo {
+            filename: Cow::Borrowed(b"foo/bar"),
+            metadata: Some(b"foo bar")
+        })
+    );
+    assert_eq!(
+        parse_old_file(b"--- foo/bar\t\n"),
+        DiffLine::OldFile(FileInfo {
+            filename: Cow::Borrowed(b"foo/bar"),
+            metadata: Some(b"")
+        })
+    );
+    assert_eq!(parse_old_file(b"--- \n"), DiffLine::Junk(b"--- \n"));
+}
+
+// Whether `line` is a `---` or `+++` header with no space after the marker,
+// and a filename that can't be part of a longer run of the marker.
+fn is_unspaced_header(line: &[u8], marker: u8) -> bool {
+    line.len() > 3
+        && line[..3].iter().all(|&b| b == marker)
+        && !matches!(line[3], b' ' | b'\t' | b'\r' | b'\n')
+        && line[3] != marker
+}
+
+/// Parse a `---` old file header without the usual space after it, like
+/// `---foo`, as some tools write them.
+///
+/// ```
+/// use diffparser::{line::parse_unspaced_old_file, DiffLine, FileInfo};
+/// use std::borrow::Cow;
+///
+/// assert_eq!(
+///     parse_unspaced_old_file(b"---foo.c\n"),
+///     DiffLine::OldFile(FileInfo { filename: Cow::Borrowed(b"foo.c"), metadata: None })
+/// );
+/// ```
+pub fn parse_unspaced_old_file(line: &[u8]) -> DiffLine<'_> {
+    if is_unspaced_header(line, b'-') {
+        return DiffLine::OldFile(parse_fileinfo(line));
+    }
+
+    DiffLine::Junk(line)
+}
+
+/// Parse a `+++` new file header without the usual space after it.
+pub fn parse_unspaced_new_file(line: &[u8]) -> DiffLine<'_> {
+    if is_unspaced_header(line, b'+') {
+        return DiffLine::NewFile(parse_fileinfo(line));
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_unspaced_file() {
+    assert_eq!(
+        parse_unspaced_old_file(b"---a/foo\t2024-01-01\n"),
+        DiffLine::OldFile(FileInfo {
+            filename: Cow::Borrowed(b"a/foo"),
+            metadata: Some(b"2024-01-01")
+        })
+    );
+    assert_eq!(
+        parse_unspaced_new_file(b"+++b/foo\r\n"),
+        DiffLine::NewFile(FileInfo {
+            filename: Cow::Borrowed(b"b/foo"),
+            metadata: None
+        })
+    );
+
+    // Separators and deletions of lines that look like markers.
+    for line in [
+        &b"---\n"[..],
+        b"---",
+        b"----------\n",
+        b"--- foo\n",
+        b"---\tfoo\n",
+    ] {
+        assert_eq!(parse_unspaced_old_file(line), DiffLine::Junk(line));
+    }
+    for line in [&b"+++\n"[..], b"++++\n", b"+++ foo\n", b"---foo\n"] {
+        assert_eq!(parse_unspaced_new_file(line), DiffLine::Junk(line));
+    }
+}
+
+/// Parse a `+++ ` new file header.
+///
+/// ```
+/// use diffparser::{line::parse_new_file, DiffLine, FileInfo};
+/// use std::borrow::Cow;
+///
+/// assert_eq!(
+///     parse_new_file(b"+++ foo.c\n"),
+///     DiffLine::NewFile(FileInfo { filename: Cow::Borrowed(b"foo.c"), metadata: None })
+/// );
+/// ```
+pub fn parse_new_file(line: &[u8]) -> DiffLine<'_> {
+    if chomp(line).len() >= b"+++ x".len() && line.starts_with(b"+++ ") {
+        return DiffLine::NewFile(parse_fileinfo(line));
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_new_file() {
+    assert_eq!(parse_new_file(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_new_file(b"+++ x\n"),
+        DiffLine::NewFile(FileInfo {
+            filename: Cow::Borrowed(b"x"),
+            metadata: None
+        })
+    );
+    assert_eq!(
+        parse_new_file(b"+++ foo/bar\n"),
+        DiffLine::NewFile(FileInfo {
+            filename: Cow::Borrowed(b"foo/bar"),
+            metadata: None
+        })
+    );
+    assert_eq!(
+        parse_new_file(b"+++ foo/bar\tfoo bar\n"),
+        DiffLine::NewFile(FileInfo {
+            filename: Cow::Borrowed(b"foo/bar"),
+            metadata: Some(b"foo bar")
+        })
+    );
+    assert_eq!(
+        parse_new_file(b"+++ foo/bar\t\n"),
+        DiffLine::NewFile(FileInfo {
+            filename: Cow::Borrowed(b"foo/bar"),
+            metadata: Some(b"")
+        })
+    );
+    assert_eq!(parse_new_file(b"+++ \n"), DiffLine::Junk(b"+++ \n"));
+}
+
+// Shared by the @@ hunk headers of ordinary diffs and svn's ## property hunks.
+pub(crate) fn parse_hunk_info<'a>(line: &'a [u8], marker: &[u8]) -> Option<HunkInfo<'a>> {
+    if line.len() >= b"@@ -1 +1 @@".len()
+        && line.starts_with(marker)
+        && line[2..].starts_with(b" -")
+    {
+        // @@ -1,1 +1,1 @@
+        // @@ -1 +1 @@
+
+        let mut hunk = HunkInfo::default();
+
+        let mut chunks = line[3..]
+            .split(|&b| b == b' ')
+            .flat_map(|chunk| chunk.get(1..).and_then(parse_range));
+
+        if let (Some(old), Some(new)) = (chunks.next(), chunks.next()) {
+            hunk.old_line_no = old.0;
+            hunk.old_line_len = old.1;
+            hunk.new_line_no = new.0;
+            hunk.new_line_len = new.1;
+
+            // The context follows the closing marker after a tab, or a space
+            // in some diffs, and may hold tabs of its own.
+            let line = chomp(line);
+            hunk.context = line[2..]
+                .windows(marker.len() + 1)
+                .position(|win| win[0] == b' ' && &win[1..] == marker)
+                .and_then(|pos| match &line[2 + pos + 1 + marker.len()..] {
+                    [b'\t', context @ ..] => Some(context),
+                    [b' ', context @ ..] => Some(context).filter(|c| !c.is_empty()),
+                    _ => None,
+                });
+
+            return Some(hunk);
+        }
+    }
+
+    None
+}
+
+/// Parse a `@@ -1,2 +1,3 @@` hunk header.
+///
+/// ```
+/// use diffparser::{line::parse_hunk, DiffLine, HunkInfo};
+///
+/// assert_eq!(
+///     parse_hunk(b"@@ -1,2 +1,3 @@\tfn main()\n"),
+///     DiffLine::Hunk(HunkInfo {
+///         old_line_no: 1,
+///         old_line_len: 2,
+///         new_line_no: 1,
+///         new_line_len: 3,
+///         context: Some(b"fn main()"),
+///     })
+/// );
+/// ```
+pub fn parse_hunk(line: &[u8]) -> DiffLine<'_> {
+    HunkInfo::parse(line).map_or(DiffLine::Junk(line), DiffLine::Hunk)
+}
+
+#[test]
+fn test_parse_hunk() {
+    assert_eq!(parse_hunk(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_hunk(b"@@ -1 +1 @@\n"),
+        DiffLine::Hunk(HunkInfo {
+            old_line_no: 1,
+            old_line_len: 1,
+            new_line_no: 1,
+            new_line_len: 1,
+            context: None
+        })
+    );
+    assert_eq!(
+        parse_hunk(b"@@ -12,34 +56,78 @@\n"),
+        DiffLine::Hunk(HunkInfo {
+            old_line_no: 12,
+            old_line_len: 34,
+            new_line_no: 56,
+            new_line_len: 78,
+            context: None
+        })
+    );
+    assert_eq!(
+        parse_hunk(b"@@ -12,34 +56,78 @@\tfoo bar\n"),
+        DiffLine::Hunk(HunkInfo {
+            old_line_no: 12,
+            old_line_len: 34,
+            new_line_no: 56,
+            new_line_len: 78,
+            context: Some(b"foo bar")
+        })
+    );
+    assert_eq!(
+        parse_hunk(b"@@ -12,34 +56,78 @@ fn main() {\r\n"),
+        DiffLine::Hunk(HunkInfo {
+            old_line_no: 12,
+            old_line_len: 34,
+            new_line_no: 56,
+            new_line_len: 78,
+            context: Some(b"fn main() {")
+        })
+    );
+    assert_eq!(
+        parse_hunk(b"@@ -1 +1 @@ foo @@ bar\n"),
+        DiffLine::Hunk(HunkInfo {
+            old_line_no: 1,
+            old_line_len: 1,
+            new_line_no: 1,
+            new_line_len: 1,
+            context: Some(b"foo @@ bar")
+        })
+    );
+    // Tabs in the context are kept, whichever separates it from the marker.
+    for line in &[
+        &b"@@ -1,3 +1,4 @@ fn main() {\tlet x = 1;\n"[..],
+        b"@@ -1,3 +1,4 @@\tfn main() {\tlet x = 1;\n",
+    ] {
+        assert_eq!(
+            parse_hunk(line),
+            DiffLine::Hunk(HunkInfo {
+                old_line_no: 1,
+                old_line_len: 3,
+                new_line_no: 1,
+                new_line_len: 4,
+                context: Some(b"fn main() {\tlet x = 1;")
+            })
+        );
+    }
+    for line in &[&b"@@ -1 +1 @@ \n"[..], b"@@ -1 +1 @@ ", b"@@ -1 +1 @@"] {
+        assert_eq!(
+            parse_hunk(line),
+            DiffLine::Hunk(HunkInfo {
+                old_line_no: 1,
+                old_line_len: 1,
+                new_line_no: 1,
+                new_line_len: 1,
+                context: None
+            })
+        );
+    }
+    assert_eq!(
+        parse_hunk(b"@@ -4294967296,2 +5000000000,3 @@\n"),
+        DiffLine::Hunk(HunkInfo {
+            old_line_no: 4294967296,
+            old_line_len: 2,
+            new_line_no: 5000000000,
+            new_line_len: 3,
+            context: None
+        })
+    );
+    assert_eq!(
+        parse_hunk(b"@@ -18446744073709551616 +1 @@\n"),
+        DiffLine::Junk(b"@@ -18446744073709551616 +1 @@\n")
+    );
+}
+
+/// Parse a hunk header which may have stray whitespace around the numbers in
+/// its ranges, like `@@ - 12 , 34 +56,78 @@`.  Any context is what follows
+/// the closing `@@` and a space or tab.
+///
+/// ```
+/// use diffparser::{line::parse_spaced_hunk, DiffLine, HunkInfo};
+///
+/// assert_eq!(
+///     parse_spaced_hunk(b"@@ -12 , 34 +56,78 @@\n"),
+///     DiffLine::Hunk(HunkInfo {
+///         old_line_no: 12,
+///         old_line_len: 34,
+///         new_line_no: 56,
+///         new_line_len: 78,
+///         context: None,
+///     })
+/// );
+/// ```
+pub fn parse_spaced_hunk(line: &[u8]) -> DiffLine<'_> {
+    parse_spaced_hunk_info(line).map_or(DiffLine::Junk(line), DiffLine::Hunk)
+}
+
+fn parse_spaced_hunk_info(line: &[u8]) -> Option<HunkInfo<'_>> {
+    let rest = chomp(line).strip_prefix(b"@@")?;
+    let end = rest.windows(2).position(|win| win == b"@@")?;
+    let (ranges, context) = (&rest[..end], &rest[end + 2..]);
+
+    let plus = ranges.iter().position(|&b| b == b'+')?;
+    let old = ranges[..plus].trim_ascii().strip_prefix(b"-")?;
+    let old = parse_spaced_range(old)?;
+    let new = parse_spaced_range(&ranges[plus + 1..])?;
+
+    let context = match context {
+        [] => None,
+        [b' ' | b'\t', context @ ..] => Some(context).filter(|c| !c.is_empty()),
+        _ => return None,
+    };
+
+    Some(HunkInfo {
+        old_line_no: old.0,
+        old_line_len: old.1,
+        new_line_no: new.0,
+        new_line_len: new.1,
+        context,
+    })
+}
+
+fn parse_spaced_range(bytes: &[u8]) -> Option<(u64, u64)> {
+    let mut bits = bytes
+        .split(|&b| b == b',')
+        .map(|bit| parse_u64(bit.trim_ascii()));
+
+    match (bits.next(), bits.next(), bits.next()) {
+        (Some(start), len, None) => Some((start?, len.unwrap_or(Some(1))?)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_parse_spaced_hunk() {
+    let hunk = |old_line_no, old_line_len, new_line_no, new_line_len, context| {
+        DiffLine::Hunk(HunkInfo {
+            old_line_no,
+            old_line_len,
+            new_line_no,
+            new_line_len,
+            context,
+        })
+    };
+
+    assert_eq!(
+        parse_spaced_hunk(b"@@ -12 , 34 +56 , 78 @@\n"),
+        hunk(12, 34, 56, 78, None)
+    );
+    assert_eq!(
+        parse_spaced_hunk(b"@@  - 1 +  2,3  @@ fn main()\r\n"),
+        hunk(1, 1, 2, 3, Some(&b"fn main()"[..]))
+    );
+    assert_eq!(
+        parse_spaced_hunk(b"@@ -1,2 +1,3 @@\tfoo\n"),
+        hunk(1, 2, 1, 3, Some(&b"foo"[..]))
+    );
+    assert_eq!(parse_spaced_hunk(b"@@ -1 +1 @@"), hunk(1, 1, 1, 1, None));
+
+    for line in [
+        &b"@@ -1 , +1 @@\n"[..],
+        b"@@ -1,2,3 +1 @@\n",
+        b"@@ -1 2 +1 @@\n",
+        b"@@ 1 +1 @@\n",
+        b"@@ -1 +1\n",
+        b"@@ -1 +1 @@x\n",
+    ] {
+        assert_eq!(parse_spaced_hunk(line), DiffLine::Junk(line));
+    }
+}
+
+/// Parse the `## -1 +1 ##` header of an svn property hunk.
+///
+/// ```
+/// use diffparser::{line::parse_prop_hunk, DiffLine, HunkInfo};
+///
+/// assert_eq!(
+///     parse_prop_hunk(b"## -0,0 +1 ##\n"),
+///     DiffLine::PropHunk(HunkInfo {
+///         old_line_no: 0,
+///         old_line_len: 0,
+///         new_line_no: 1,
+///         new_line_len: 1,
+///         context: None,
+///     })
+/// );
+/// ```
+pub fn parse_prop_hunk(line: &[u8]) -> DiffLine<'_> {
+    parse_hunk_info(line, b"##").map_or(DiffLine::Junk(line), DiffLine::PropHunk)
+}
+
+#[test]
+fn test_parse_prop_hunk() {
+    assert_eq!(parse_prop_hunk(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_prop_hunk(b"@@ -1 +1 @@\n"),
+        DiffLine::Junk(b"@@ -1 +1 @@\n")
+    );
+    assert_eq!(
+        parse_prop_hunk(b"## -1,2 +1,3 ##\n"),
+        DiffLine::PropHunk(HunkInfo {
+            old_line_no: 1,
+            old_line_len: 2,
+            new_line_no: 1,
+            new_line_len: 3,
+            context: None
+        })
+    );
+}
+
+pub(crate) fn parse_property_changes(line: &[u8]) -> DiffLine<'_> {
+    match parse_path(line, b"Property changes on: ") {
+        Some(path) => DiffLine::PropertyChanges(path),
+        None => DiffLine::Junk(line),
+    }
+}
+
+/// Parse the `Index: path` line starting each file in svn and cvs diffs.
+///
+/// ```
+/// use diffparser::{line::parse_svn_index, DiffLine};
+///
+/// assert_eq!(parse_svn_index(b"Index: foo.c\n"), DiffLine::SvnIndex(b"foo.c"));
+/// ```
+pub fn parse_svn_index(line: &[u8]) -> DiffLine<'_> {
+    match parse_path(line, b"Index: ") {
+        Some(path) => DiffLine::SvnIndex(path),
+        None => DiffLine::Junk(line),
+    }
+}
+
+/// Parse the line of `=` following an `SvnIndex`.
+///
+/// ```
+/// use diffparser::{line::parse_svn_separator, DiffLine};
+///
+/// assert_eq!(parse_svn_separator(b"=====\n"), DiffLine::SvnSeparator(b"====="));
+/// ```
+pub fn parse_svn_separator(line: &[u8]) -> DiffLine<'_> {
+    let sep = chomp(line);
+    if !sep.is_empty() && sep.iter().all(|&c| c == b'=') {
+        DiffLine::SvnSeparator(sep)
+    } else {
+        DiffLine::Junk(line)
+    }
+}
+
+#[test]
+fn test_parse_svn_index() {
+    assert_eq!(
+        parse_svn_index(b"Index: a b\r\n"),
+        DiffLine::SvnIndex(b"a b")
+    );
+    assert_eq!(parse_svn_index(b"Index:\n"), DiffLine::Junk(b"Index:\n"));
+    assert_eq!(
+        parse_svn_index(b"index 1234567..89abcde\n"),
+        DiffLine::Junk(b"index 1234567..89abcde\n")
+    );
+    assert_eq!(parse_svn_separator(b"\n"), DiffLine::Junk(b"\n"));
+    assert_eq!(parse_svn_separator(b"==-==\n"), DiffLine::Junk(b"==-==\n"));
+}
+
+/// Parse an svn `Added: name`, `Modified: name` or `Deleted: name` line,
+/// which introduce the hunks of a property diff.
+///
+/// ```
+/// use diffparser::{line::parse_property, DiffLine, PropertyChange};
+///
+/// assert_eq!(
+///     parse_property(b"Added: svn:eol-style\n"),
+///     DiffLine::Property { change: PropertyChange::Added, name: b"svn:eol-style" }
+/// );
+/// ```
+pub fn parse_property(line: &[u8]) -> DiffLine<'_> {
+    let changes: [(&[u8], PropertyChange); 3] = [
+        (b"Added: ", PropertyChange::Added),
+        (b"Modified: ", PropertyChange::Modified),
+        (b"Deleted: ", PropertyChange::Deleted),
+    ];
+
+    for &(prefix, change) in &changes {
+        if let Some(name) = parse_path(line, prefix) {
+            return DiffLine::Property { change, name };
+        }
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_property() {
+    assert_eq!(parse_property(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_property(b"Modified: svn:keywords\n"),
+        DiffLine::Property {
+            change: PropertyChange::Modified,
+            name: b"svn:keywords"
+        }
+    );
+    assert_eq!(
+        parse_property(b"Deleted: svn:executable\r\n"),
+        DiffLine::Property {
+            change: PropertyChange::Deleted,
+            name: b"svn:executable"
+        }
+    );
+    assert_eq!(parse_property(b"Added: \n"), DiffLine::Junk(b"Added: \n"));
+    assert_eq!(
+        parse_property_changes(b"Property changes on: foo/bar.c\n"),
+        DiffLine::PropertyChanges(b"foo/bar.c")
+    );
+}
+
+/// Parse the `@@@ -1,2 -1,2 +1,3 @@@` header of a hunk in a combined diff,
+/// which has one more `@` than the merge has parents.
+///
+/// ```
+/// use diffparser::{line::parse_combined_hunk, CombinedHunkInfo, DiffLine};
+///
+/// assert_eq!(
+///     parse_combined_hunk(b"@@@ -1,2 -1,2 +1,3 @@@\n"),
+///     DiffLine::CombinedHunk(CombinedHunkInfo {
+///         old_ranges: vec![(1, 2), (1, 2)],
+///         new_line_no: 1,
+///         new_line_len: 3,
+///         context: None,
+///     })
+/// );
+/// ```
+pub fn parse_combined_hunk(line: &[u8]) -> DiffLine<'_> {
+    let parents = line
+        .iter()
+        .take_while(|&&b| b == b'@')
+        .count()
+        .saturating_sub(1);
+
+    if parents >= 2 {
+        let marker = &line[..=parents];
+        let body = chomp(&line[marker.len()..]);
+
+        // Everything up to the closing marker is ranges, and after it context.
+        let end = body
+            .windows(marker.len() + 1)
+            .position(|win| win[0] == b' ' && &win[1..] == marker);
+
+        if let (Some(b' '), Some(end)) = (body.first(), end) {
+            let mut ranges = body[1..end].split(|&b| b == b' ');
+            let old_ranges = ranges
+                .by_ref()
+                .take(parents)
+                .map(|range| match range.split_first() {
+                    Some((b'-', range)) => parse_range(range),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            let new_range = match ranges.next().and_then(<[u8]>::split_first) {
+                Some((b'+', range)) => parse_range(range),
+                _ => None,
+            };
+
+            if let (Some(old_ranges), Some(new), None) = (old_ranges, new_range, ranges.next()) {
+                let context = &body[end + 1 + marker.len()..];
+                return DiffLine::CombinedHunk(CombinedHunkInfo {
+                    old_ranges,
+                    new_line_no: new.0,
+                    new_line_len: new.1,
+                    context: context.get(1..).filter(|ctx| !ctx.is_empty()),
+                });
+            }
+        }
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_combined_hunk() {
+    assert_eq!(parse_combined_hunk(b""), DiffLine::Junk(b""));
+    assert_eq!(
+        parse_combined_hunk(b"@@ -1 +1 @@\n"),
+        DiffLine::Junk(b"@@ -1 +1 @@\n")
+    );
+    assert_eq!(
+        parse_combined_hunk(b"@@@ -1 +1 @@@\n"),
+        DiffLine::Junk(b"@@@ -1 +1 @@@\n")
+    );
+    assert_eq!(
+        parse_combined_hunk(b"@@@ -1 -1 +1\n"),
+        DiffLine::Junk(b"@@@ -1 -1 +1\n")
+    );
+    assert_eq!(
+        parse_combined_hunk(b"@@@@ -1,2 -3 -4,5 +6,7 @@@@ foo bar\n"),
+        DiffLine::CombinedHunk(CombinedHunkInfo {
+            old_ranges: vec![(1, 2), (3, 1), (4, 5)],
+            new_line_no: 6,
+            new_line_len: 7,
+            context: Some(b"foo bar")
+        })
+    );
+}
+
+/// Parse a line from the body of a combined hunk, given the number of parents.
+///
+/// ```
+/// use diffparser::{line::parse_combined_delta, DiffLine};
+///
+/// assert_eq!(
+///     parse_combined_delta(b" +foo\n", 2),
+///     DiffLine::CombinedDelta { markers: b" +", line: b"foo\n" }
+/// );
+/// ```
+pub fn parse_combined_delta(line: &[u8], parents: usize) -> DiffLine<'_> {
+    if line.starts_with(b"\\") {
+        return DiffLine::NoNewlineAtEof {
+            side: Side::Both,
+            marker: line,
+        };
+    }
+
+    if parents > 0 && line.len() > parents && line[..parents].iter().all(|b| b" +-".contains(b)) {
+        return DiffLine::CombinedDelta {
+            markers: &line[..parents],
+            line: &line[parents..],
+        };
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_combined_delta() {
+    assert_eq!(parse_combined_delta(b"", 2), DiffLine::Junk(b""));
+    assert_eq!(parse_combined_delta(b" \n", 2), DiffLine::Junk(b" \n"));
+    assert_eq!(
+        parse_combined_delta(b"--foo\n", 2),
+        DiffLine::CombinedDelta {
+            markers: b"--",
+            line: b"foo\n"
+        }
+    );
+    assert_eq!(
+        parse_combined_delta(b"+ -foo\n", 3),
+        DiffLine::CombinedDelta {
+            markers: b"+ -",
+            line: b"foo\n"
+        }
+    );
+    assert_eq!(
+        parse_combined_delta(b"+!foo\n", 2),
+        DiffLine::Junk(b"+!foo\n")
+    );
+    assert_eq!(
+        parse_combined_delta(b"\\ No newline at end of file\n", 2),
+        DiffLine::NoNewlineAtEof {
+            side: Side::Both,
+            marker: b"\\ No newline at end of file\n",
+        }
+    );
+}
+
+/// Parse a line from the body of a hunk.  The payload keeps its newline.
+///
+/// A blank line is taken as an empty `Context` line which lost its leading
+/// space in transit, as patch does.  An empty slice is `Junk`.
+///
+/// The side of a `NoNewlineAtEof` depends on the line before it, so it's
+/// always `Side::Both` here.  `DiffParser` fills in the real one.
+///
+/// ```
+/// use diffparser::{line::parse_delta, DiffLine};
+///
+/// assert_eq!(parse_delta(b"+foo\n"), DiffLine::Inserted(b"foo\n", 1));
+/// assert_eq!(parse_delta(b"-foo\n"), DiffLine::Deleted(b"foo\n", 1));
+/// assert_eq!(parse_delta(b" foo\n"), DiffLine::Context(b"foo\n", 1));
+/// ```
+pub fn parse_delta(line: &[u8]) -> DiffLine<'_> {
+    match line.first() {
+        Some(b'+') => DiffLine::Inserted(&line[1..], 1),
+        Some(b'-') => DiffLine::Deleted(&line[1..], 1),
+        Some(b'!') => DiffLine::Modified(&line[1..], 1),
+        Some(b' ') => DiffLine::Context(&line[1..], 1),
+        Some(b'\\') => DiffLine::NoNewlineAtEof {
+            side: Side::Both,
+            marker: line,
+        },
+        Some(_) if chomp(line).is_empty() => DiffLine::Context(line, 0),
+        _ => DiffLine::Junk(line),
+    }
+}
+
+#[test]
+fn test_parse_delta() {
+    assert_eq!(parse_delta(b"+foo\n"), DiffLine::Inserted(b"foo\n", 1));
+    assert_eq!(parse_delta(b"-foo\n"), DiffLine::Deleted(b"foo\n", 1));
+    assert_eq!(parse_delta(b"!foo\n"), DiffLine::Modified(b"foo\n", 1));
+    assert_eq!(parse_delta(b" foo\n"), DiffLine::Context(b"foo\n", 1));
+    assert_eq!(
+        parse_delta(b"\\ No newline at end of file\n"),
+        DiffLine::NoNewlineAtEof {
+            side: Side::Both,
+            marker: b"\\ No newline at end of file\n",
+        }
+    );
+    assert_eq!(parse_delta(b"foo\n"), DiffLine::Junk(b"foo\n"));
+    assert_eq!(parse_delta(b"\n"), DiffLine::Context(b"\n", 0));
+    assert_eq!(parse_delta(b"\r\n"), DiffLine::Context(b"\r\n", 0));
+    assert_eq!(parse_delta(b""), DiffLine::Junk(b""));
+}
+
+/// Parse a context diff's `*** ` old file header.
+///
+/// ```
+/// use diffparser::{line::parse_context_old_file, DiffLine, FileInfo};
+/// use std::borrow::Cow;
+///
+/// assert_eq!(
+///     parse_context_old_file(b"*** foo.c\t2019-01-01 00:00:00\n"),
+///     DiffLine::OldFile(FileInfo {
+///         filename: Cow::Borrowed(b"foo.c"),
+///         metadata: Some(b"2019-01-01 00:00:00"),
+///     })
+/// );
+/// ```
+pub fn parse_context_old_file(line: &[u8]) -> DiffLine<'_> {
+    if chomp(line).len() >= "*** x".len() && line.starts_with(b"*** ") {
+        return DiffLine::OldFile(parse_fileinfo(line));
+    }
+
+    DiffLine::Junk(line)
+}
+
+/// Parse a context diff's `--- ` new file header.
+///
+/// ```
+/// use diffparser::{line::parse_context_new_file, DiffLine, FileInfo};
+/// use std::borrow::Cow;
+///
+/// assert_eq!(
+///     parse_context_new_file(b"--- foo.c\n"),
+///     DiffLine::NewFile(FileInfo {
+///         filename: Cow::Borrowed(b"foo.c"),
+///         metadata: None,
+///     })
+/// );
+/// ```
+pub fn parse_context_new_file(line: &[u8]) -> DiffLine<'_> {
+    if chomp(line).len() >= "--- x".len() && line.starts_with(b"--- ") {
+        return DiffLine::NewFile(parse_fileinfo(line));
+    }
+
+    DiffLine::Junk(line)
+}
+
+// A context diff range is the first and last line numbers, or just one
+// number if they're the same.
+fn parse_context_range(bytes: &[u8]) -> Option<(u64, u64)> {
+    let mut bits = bytes.splitn(2, |&b| b == b',');
+    let first = parse_u64(bits.next()?)?;
+    let last = match bits.next() {
+        Some(last) => parse_u64(last)?,
+        None => first,
+    };
+
+    Some((first, last))
+}
+
+/// Parse an ed script's `c`, `d` or `a` command, as `diff -e` writes them.
+///
+/// ```
+/// use diffparser::{line::parse_ed_command, DiffLine};
+///
+/// assert_eq!(parse_ed_command(b"5c\n"), DiffLine::EdChange(5, 5));
+/// assert_eq!(parse_ed_command(b"2,4d\n"), DiffLine::EdDelete(2, 4));
+/// assert_eq!(parse_ed_command(b"0a\n"), DiffLine::EdAppend(0));
+/// ```
+pub fn parse_ed_command(line: &[u8]) -> DiffLine<'_> {
+    if let Some((&command, range)) = chomp(line).split_last() {
+        match (command, parse_context_range(range)) {
+            (b'c', Some((first, last))) if first <= last => return DiffLine::EdChange(first, last),
+            (b'd', Some((first, last))) if first <= last => return DiffLine::EdDelete(first, last),
+            (b'a', Some((line, last))) if line == last && !range.contains(&b',') => {
+                return DiffLine::EdAppend(line)
+            }
+            _ => (),
+        }
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_ed_command() {
+    assert_eq!(parse_ed_command(b"12,34c\r\n"), DiffLine::EdChange(12, 34));
+    assert_eq!(parse_ed_command(b"7d\n"), DiffLine::EdDelete(7, 7));
+    assert_eq!(parse_ed_command(b"4,2d\n"), DiffLine::Junk(b"4,2d\n"));
+    assert_eq!(parse_ed_command(b"1,2a\n"), DiffLine::Junk(b"1,2a\n"));
+    assert_eq!(parse_ed_command(b"c\n"), DiffLine::Junk(b"c\n"));
+    assert_eq!(parse_ed_command(b"5x\n"), DiffLine::Junk(b"5x\n"));
+    assert_eq!(parse_ed_command(b""), DiffLine::Junk(b""));
+}
+
+/// Parse the `***************` line which starts each hunk of a context diff,
+/// or the `*** 1,4 ****` and `--- 1,4 ----` lines which start its old and new
+/// sections.
+///
+/// ```
+/// use diffparser::{line::parse_context_hunk, DiffLine};
+///
+/// assert_eq!(parse_context_hunk(b"***************\n"), DiffLine::ContextHunk);
+/// assert_eq!(parse_context_hunk(b"*** 1,4 ****\n"), DiffLine::OldRange(1, 4));
+/// assert_eq!(parse_context_hunk(b"--- 5 ----\n"), DiffLine::NewRange(5, 5));
+/// ```
+pub fn parse_context_hunk(line: &[u8]) -> DiffLine<'_> {
+    let chunk = chomp(line);
+
+    if chunk.len() >= 3 && chunk.iter().all(|&b| b == b'*') {
+        return DiffLine::ContextHunk;
+    }
+
+    let range = |prefix: &[u8], suffix: &[u8]| {
+        chunk
+            .strip_prefix(prefix)
+            .and_then(|chunk| chunk.strip_suffix(suffix))
+            .and_then(parse_context_range)
+    };
+
+    if let Some((first, last)) = range(b"*** ", b" ****") {
+        return DiffLine::OldRange(first, last);
+    }
+
+    if let Some((first, last)) = range(b"--- ", b" ----") {
+        return DiffLine::NewRange(first, last);
+    }
+
+    DiffLine::Junk(line)
+}
+
+#[test]
+fn test_parse_context_hunk() {
+    assert_eq!(parse_context_hunk(b"***\r\n"), DiffLine::ContextHunk);
+    assert_eq!(parse_context_hunk(b"**\n"), DiffLine::Junk(b"**\n"));
+    assert_eq!(
+        parse_context_hunk(b"*** 0 ****\n"),
+        DiffLine::OldRange(0, 0)
+    );
+    assert_eq!(
+        parse_context_hunk(b"--- 10,13 ----\n"),
+        DiffLine::NewRange(10, 13)
+    );
+    assert_eq!(
+        parse_context_hunk(b"*** 4294967296,4294967300 ****\n"),
+        DiffLine::OldRange(4294967296, 4294967300)
+    );
+    assert_eq!(
+        parse_context_hunk(b"*** 1,4 ----\n"),
+        DiffLine::Junk(b"*** 1,4 ----\n")
+    );
+    assert_eq!(
+        parse_context_hunk(b"*** foo ****\n"),
+        DiffLine::Junk(b"*** foo ****\n")
+    );
+    assert_eq!(
+        parse_context_hunk(b"--- 1, ----\n"),
+        DiffLine::Junk(b"--- 1, ----\n")
+    );
+}
+
+/// Parse a line from either section of a context diff hunk, where the marker
+/// is followed by a space.  As with `parse_delta`, a blank line is taken as
+/// `Context`.
+///
+/// ```
+/// use diffparser::{line::parse_context_delta, DiffLine};
+///
+/// assert_eq!(parse_context_delta(b"! foo\n"), DiffLine::Modified(b"foo\n", 2));
+/// assert_eq!(parse_context_delta(b"  foo\n"), DiffLine::Context(b"foo\n", 2));
+/// ```
+pub fn parse_context_delta(line: &[u8]) -> DiffLine<'_> {
+    match line {
+        [b'+', b' ', rest @ ..] => DiffLine::Inserted(rest, 2),
+        [b'-', b' ', rest @ ..] => DiffLine::Deleted(rest, 2),
+        [b'!', b' ', rest @ ..] => DiffLine::Modified(rest, 2),
+        [b' ', b' ', rest @ ..] => DiffLine::Context(rest, 2),
+        [b'\\', ..] => DiffLine::NoNewlineAtEof {
+            side: Side::Both,
+            marker: line,
+        },
+        _ if !line.is_empty() && chomp(line).is_empty() => DiffLine::Context(line, 0),
+        _ => DiffLine::Junk(line),
+    }
+}
+
+#[test]
+fn test_parse_context_delta() {
+    assert_eq!(
+        parse_context_delta(b"+ foo\n"),
+        DiffLine::Inserted(b"foo\n", 2)
+    );
+    assert_eq!(
+        parse_context_delta(b"- foo\n"),
+        DiffLine::Deleted(b"foo\n", 2)
+    );
+    assert_eq!(parse_context_delta(b"\n"), DiffLine::Context(b"\n", 0));
+    assert_eq!(parse_context_delta(b"+foo\n"), DiffLine::Junk(b"+foo\n"));
+    assert_eq!(
+        parse_context_delta(b"--- 1,4 ----\n"),
+        DiffLine::Junk(b"--- 1,4 ----\n")
+    );
+    assert_eq!(parse_context_delta(b""), DiffLine::Junk(b""));
+}
+
+// Remove `levels` leading components from a path, like patch -p.
+pub(crate) fn strip_path(path: &[u8], levels: usize) -> &[u8] {
+    if path == b"/dev/null" {
+        return path;
+    }
+
+    (0..levels).fold(path, |path, _| strip_component(path))
+}
+
+#[test]
+fn test_strip_path() {
+    assert_eq!(strip_path(b"a/foo/bar", 0), b"a/foo/bar");
+    assert_eq!(strip_path(b"a/foo/bar", 1), b"foo/bar");
+    assert_eq!(strip_path(b"a/foo/bar", 2), b"bar");
+    assert_eq!(strip_path(b"a/foo/bar", 3), b"bar");
+    assert_eq!(strip_path(b"/usr/src/foo", 1), b"usr/src/foo");
+    assert_eq!(strip_path(b"foo", 1), b"foo");
+    assert_eq!(strip_path(b"/dev/null", 1), b"/dev/null");
+}
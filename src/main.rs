@@ -1,47 +1,17 @@
-use diffparser::{DiffLine, DiffParser};
+use diffparser::{DiffParser, DiffStat};
 
 use std::io::BufReader;
 fn diffstat<R: std::io::BufRead>(diff: R) {
-    let mut files = 0;
-    let mut hunks = 0;
-    let mut insert = 0;
-    let mut delete = 0;
-    let mut modify = 0;
+    let mut stat = DiffStat::new();
 
     let mut parser = DiffParser::new(diff);
 
     while let Some(line) = parser.next_line() {
         let line = line.expect("read error");
-        match line {
-            DiffLine::Inserted(_) => insert += 1,
-            DiffLine::Deleted(_) => delete += 1,
-            DiffLine::Modified(_) => modify += 1,
-            DiffLine::Hunk(_) => hunks += 1,
-            DiffLine::NewFile(_) => files += 1,
-            DiffLine::Binaries(_, _) => files += 1,
-            _ => (),
-        }
+        stat.accumulate(&line);
     }
 
-    print!(" {} files changed", files);
-
-    if hunks > 0 {
-        print!(", {} hunks", hunks);
-    }
-
-    if insert > 0 {
-        print!(", {} insertions(+)", insert);
-    }
-
-    if delete > 0 {
-        print!(", {} deletions(-)", delete);
-    }
-
-    if modify > 0 {
-        print!(", {} modifications(!)", modify);
-    }
-
-    println!();
+    println!("{}", stat);
 }
 
 use std::env;
@@ -0,0 +1,473 @@
+use crate::{
+    BinaryPatchKind, CombinedHunkInfo, DiffLine, FileInfo, HunkInfo, PropertyChange, Side,
+};
+
+use std::borrow::Cow;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedFileInfo {
+    pub filename: Vec<u8>,
+    pub metadata: Option<Vec<u8>>,
+}
+
+/// An owned copy of a `HunkInfo`.  Its line numbers and context are all part
+/// of its identity, so the same changes elsewhere in a file aren't equal.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedHunkInfo {
+    pub old_line_no: u64,
+    pub old_line_len: u64,
+    pub new_line_no: u64,
+    pub new_line_len: u64,
+    pub context: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedCombinedHunkInfo {
+    pub old_ranges: Vec<(u64, u64)>,
+    pub new_line_no: u64,
+    pub new_line_len: u64,
+    pub context: Option<Vec<u8>>,
+}
+
+/// An owned copy of a `DiffLine`, which can outlive the parser that produced it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OwnedDiffLine {
+    GitHeader {
+        old_path: Vec<u8>,
+        new_path: Vec<u8>,
+    },
+    Index {
+        old_oid: Vec<u8>,
+        new_oid: Vec<u8>,
+        mode: Option<Vec<u8>>,
+    },
+    NewFileMode(u32),
+    DeletedFileMode(u32),
+    OldMode(u32),
+    NewMode(u32),
+    SimilarityIndex(u8),
+    RenameFrom(Vec<u8>),
+    RenameTo(Vec<u8>),
+    CopyFrom(Vec<u8>),
+    CopyTo(Vec<u8>),
+    OldFile(OwnedFileInfo),
+    NewFile(OwnedFileInfo),
+    Binaries(Vec<u8>, Vec<u8>),
+    GitBinaryPatch,
+    BinaryPatch {
+        kind: BinaryPatchKind,
+        size: u64,
+    },
+    BinaryData(Vec<u8>),
+    OnlyIn {
+        dir: Vec<u8>,
+        file: Vec<u8>,
+    },
+    FilesDiffer(Vec<u8>, Vec<u8>),
+    Hunk(OwnedHunkInfo),
+    ContextHunk,
+    OldRange(u64, u64),
+    NewRange(u64, u64),
+    EdChange(u64, u64),
+    EdDelete(u64, u64),
+    EdAppend(u64),
+    EdEnd,
+    SvnIndex(Vec<u8>),
+    SvnSeparator(Vec<u8>),
+    PropertyChanges(Vec<u8>),
+    Property {
+        change: PropertyChange,
+        name: Vec<u8>,
+    },
+    PropHunk(OwnedHunkInfo),
+    CombinedHunk(OwnedCombinedHunkInfo),
+    CombinedDelta {
+        markers: Vec<u8>,
+        line: Vec<u8>,
+    },
+    Context(Vec<u8>, u8),
+    Inserted(Vec<u8>, u8),
+    Deleted(Vec<u8>, u8),
+    Modified(Vec<u8>, u8),
+    NoNewlineAtEof {
+        side: Side,
+        marker: Vec<u8>,
+    },
+    MalformedHunk {
+        expected_old: u64,
+        expected_new: u64,
+        actual_old: u64,
+        actual_new: u64,
+    },
+    FileStart(OwnedFileInfo),
+    FileEnd(OwnedFileInfo),
+    Submodule {
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+    Preamble(Vec<u8>),
+    Junk(Vec<u8>),
+}
+
+impl From<&FileInfo<'_>> for OwnedFileInfo {
+    fn from(fi: &FileInfo<'_>) -> Self {
+        Self {
+            filename: fi.filename.to_vec(),
+            metadata: fi.metadata.map(<[u8]>::to_vec),
+        }
+    }
+}
+
+impl From<&HunkInfo<'_>> for OwnedHunkInfo {
+    fn from(hi: &HunkInfo<'_>) -> Self {
+        Self {
+            old_line_no: hi.old_line_no,
+            old_line_len: hi.old_line_len,
+            new_line_no: hi.new_line_no,
+            new_line_len: hi.new_line_len,
+            context: hi.context.map(<[u8]>::to_vec),
+        }
+    }
+}
+
+impl From<&CombinedHunkInfo<'_>> for OwnedCombinedHunkInfo {
+    fn from(hi: &CombinedHunkInfo<'_>) -> Self {
+        Self {
+            old_ranges: hi.old_ranges.clone(),
+            new_line_no: hi.new_line_no,
+            new_line_len: hi.new_line_len,
+            context: hi.context.map(<[u8]>::to_vec),
+        }
+    }
+}
+
+impl From<&DiffLine<'_>> for OwnedDiffLine {
+    fn from(line: &DiffLine<'_>) -> Self {
+        match line {
+            DiffLine::GitHeader { old_path, new_path } => OwnedDiffLine::GitHeader {
+                old_path: old_path.to_vec(),
+                new_path: new_path.to_vec(),
+            },
+            DiffLine::Index {
+                old_oid,
+                new_oid,
+                mode,
+            } => OwnedDiffLine::Index {
+                old_oid: old_oid.to_vec(),
+                new_oid: new_oid.to_vec(),
+                mode: mode.map(<[u8]>::to_vec),
+            },
+            DiffLine::NewFileMode(mode) => OwnedDiffLine::NewFileMode(*mode),
+            DiffLine::DeletedFileMode(mode) => OwnedDiffLine::DeletedFileMode(*mode),
+            DiffLine::OldMode(mode) => OwnedDiffLine::OldMode(*mode),
+            DiffLine::NewMode(mode) => OwnedDiffLine::NewMode(*mode),
+            DiffLine::SimilarityIndex(pct) => OwnedDiffLine::SimilarityIndex(*pct),
+            DiffLine::RenameFrom(p) => OwnedDiffLine::RenameFrom(p.to_vec()),
+            DiffLine::RenameTo(p) => OwnedDiffLine::RenameTo(p.to_vec()),
+            DiffLine::CopyFrom(p) => OwnedDiffLine::CopyFrom(p.to_vec()),
+            DiffLine::CopyTo(p) => OwnedDiffLine::CopyTo(p.to_vec()),
+            DiffLine::OldFile(fi) => OwnedDiffLine::OldFile(fi.into()),
+            DiffLine::NewFile(fi) => OwnedDiffLine::NewFile(fi.into()),
+            DiffLine::Binaries(a, b) => OwnedDiffLine::Binaries(a.to_vec(), b.to_vec()),
+            DiffLine::GitBinaryPatch => OwnedDiffLine::GitBinaryPatch,
+            DiffLine::BinaryPatch { kind, size } => OwnedDiffLine::BinaryPatch {
+                kind: *kind,
+                size: *size,
+            },
+            DiffLine::BinaryData(data) => OwnedDiffLine::BinaryData(data.to_vec()),
+            DiffLine::OnlyIn { dir, file } => OwnedDiffLine::OnlyIn {
+                dir: dir.to_vec(),
+                file: file.to_vec(),
+            },
+            DiffLine::FilesDiffer(a, b) => OwnedDiffLine::FilesDiffer(a.to_vec(), b.to_vec()),
+            DiffLine::Hunk(hi) => OwnedDiffLine::Hunk(hi.into()),
+            DiffLine::ContextHunk => OwnedDiffLine::ContextHunk,
+            DiffLine::OldRange(first, last) => OwnedDiffLine::OldRange(*first, *last),
+            DiffLine::NewRange(first, last) => OwnedDiffLine::NewRange(*first, *last),
+            DiffLine::EdChange(first, last) => OwnedDiffLine::EdChange(*first, *last),
+            DiffLine::EdDelete(first, last) => OwnedDiffLine::EdDelete(*first, *last),
+            DiffLine::EdAppend(line) => OwnedDiffLine::EdAppend(*line),
+            DiffLine::EdEnd => OwnedDiffLine::EdEnd,
+            DiffLine::SvnIndex(p) => OwnedDiffLine::SvnIndex(p.to_vec()),
+            DiffLine::SvnSeparator(sep) => OwnedDiffLine::SvnSeparator(sep.to_vec()),
+            DiffLine::PropertyChanges(p) => OwnedDiffLine::PropertyChanges(p.to_vec()),
+            DiffLine::Property { change, name } => OwnedDiffLine::Property {
+                change: *change,
+                name: name.to_vec(),
+            },
+            DiffLine::PropHunk(hi) => OwnedDiffLine::PropHunk(hi.into()),
+            DiffLine::CombinedHunk(hi) => OwnedDiffLine::CombinedHunk(hi.into()),
+            DiffLine::CombinedDelta { markers, line } => OwnedDiffLine::CombinedDelta {
+                markers: markers.to_vec(),
+                line: line.to_vec(),
+            },
+            DiffLine::Context(l, width) => OwnedDiffLine::Context(l.to_vec(), *width),
+            DiffLine::Inserted(l, width) => OwnedDiffLine::Inserted(l.to_vec(), *width),
+            DiffLine::Deleted(l, width) => OwnedDiffLine::Deleted(l.to_vec(), *width),
+            DiffLine::Modified(l, width) => OwnedDiffLine::Modified(l.to_vec(), *width),
+            DiffLine::NoNewlineAtEof { side, marker } => OwnedDiffLine::NoNewlineAtEof {
+                side: *side,
+                marker: marker.to_vec(),
+            },
+            DiffLine::MalformedHunk {
+                expected_old,
+                expected_new,
+                actual_old,
+                actual_new,
+            } => OwnedDiffLine::MalformedHunk {
+                expected_old: *expected_old,
+                expected_new: *expected_new,
+                actual_old: *actual_old,
+                actual_new: *actual_new,
+            },
+            DiffLine::FileStart(fi) => OwnedDiffLine::FileStart(fi.into()),
+            DiffLine::FileEnd(fi) => OwnedDiffLine::FileEnd(fi.into()),
+            DiffLine::Submodule { old, new } => OwnedDiffLine::Submodule {
+                old: old.to_vec(),
+                new: new.to_vec(),
+            },
+            DiffLine::Preamble(l) => OwnedDiffLine::Preamble(l.to_vec()),
+            DiffLine::Junk(l) => OwnedDiffLine::Junk(l.to_vec()),
+        }
+    }
+}
+
+impl From<DiffLine<'_>> for OwnedDiffLine {
+    fn from(line: DiffLine<'_>) -> Self {
+        Self::from(&line)
+    }
+}
+
+impl OwnedFileInfo {
+    pub fn is_dev_null(&self) -> bool {
+        self.as_file_info().is_dev_null()
+    }
+
+    pub fn as_file_info(&self) -> FileInfo<'_> {
+        FileInfo {
+            filename: Cow::Borrowed(&self.filename),
+            metadata: self.metadata.as_deref(),
+        }
+    }
+
+    pub fn same_file(&self, other: &OwnedFileInfo) -> bool {
+        self.filename == other.filename
+    }
+}
+
+impl OwnedHunkInfo {
+    pub fn as_hunk_info(&self) -> HunkInfo<'_> {
+        HunkInfo {
+            old_line_no: self.old_line_no,
+            old_line_len: self.old_line_len,
+            new_line_no: self.new_line_no,
+            new_line_len: self.new_line_len,
+            context: self.context.as_deref(),
+        }
+    }
+}
+
+impl OwnedCombinedHunkInfo {
+    pub fn as_combined_hunk_info(&self) -> CombinedHunkInfo<'_> {
+        CombinedHunkInfo {
+            old_ranges: self.old_ranges.clone(),
+            new_line_no: self.new_line_no,
+            new_line_len: self.new_line_len,
+            context: self.context.as_deref(),
+        }
+    }
+}
+
+impl OwnedDiffLine {
+    /// Borrow this line as a `DiffLine`, e.g. to feed it back through code
+    /// written against the streaming API.
+    pub fn as_diff_line(&self) -> DiffLine<'_> {
+        match self {
+            OwnedDiffLine::GitHeader { old_path, new_path } => {
+                DiffLine::GitHeader { old_path, new_path }
+            }
+            OwnedDiffLine::Index {
+                old_oid,
+                new_oid,
+                mode,
+            } => DiffLine::Index {
+                old_oid,
+                new_oid,
+                mode: mode.as_deref(),
+            },
+            OwnedDiffLine::NewFileMode(mode) => DiffLine::NewFileMode(*mode),
+            OwnedDiffLine::DeletedFileMode(mode) => DiffLine::DeletedFileMode(*mode),
+            OwnedDiffLine::OldMode(mode) => DiffLine::OldMode(*mode),
+            OwnedDiffLine::NewMode(mode) => DiffLine::NewMode(*mode),
+            OwnedDiffLine::SimilarityIndex(pct) => DiffLine::SimilarityIndex(*pct),
+            OwnedDiffLine::RenameFrom(p) => DiffLine::RenameFrom(p),
+            OwnedDiffLine::RenameTo(p) => DiffLine::RenameTo(p),
+            OwnedDiffLine::CopyFrom(p) => DiffLine::CopyFrom(p),
+            OwnedDiffLine::CopyTo(p) => DiffLine::CopyTo(p),
+            OwnedDiffLine::OldFile(fi) => DiffLine::OldFile(fi.as_file_info()),
+            OwnedDiffLine::NewFile(fi) => DiffLine::NewFile(fi.as_file_info()),
+            OwnedDiffLine::Binaries(a, b) => DiffLine::Binaries(a, b),
+            OwnedDiffLine::GitBinaryPatch => DiffLine::GitBinaryPatch,
+            OwnedDiffLine::BinaryPatch { kind, size } => DiffLine::BinaryPatch {
+                kind: *kind,
+                size: *size,
+            },
+            OwnedDiffLine::BinaryData(data) => DiffLine::BinaryData(data),
+            OwnedDiffLine::OnlyIn { dir, file } => DiffLine::OnlyIn { dir, file },
+            OwnedDiffLine::FilesDiffer(a, b) => DiffLine::FilesDiffer(a, b),
+            OwnedDiffLine::Hunk(hi) => DiffLine::Hunk(hi.as_hunk_info()),
+            OwnedDiffLine::ContextHunk => DiffLine::ContextHunk,
+            OwnedDiffLine::OldRange(first, last) => DiffLine::OldRange(*first, *last),
+            OwnedDiffLine::NewRange(first, last) => DiffLine::NewRange(*first, *last),
+            OwnedDiffLine::EdChange(first, last) => DiffLine::EdChange(*first, *last),
+            OwnedDiffLine::EdDelete(first, last) => DiffLine::EdDelete(*first, *last),
+            OwnedDiffLine::EdAppend(line) => DiffLine::EdAppend(*line),
+            OwnedDiffLine::EdEnd => DiffLine::EdEnd,
+            OwnedDiffLine::SvnIndex(p) => DiffLine::SvnIndex(p),
+            OwnedDiffLine::SvnSeparator(sep) => DiffLine::SvnSeparator(sep),
+            OwnedDiffLine::PropertyChanges(p) => DiffLine::PropertyChanges(p),
+            OwnedDiffLine::Property { change, name } => DiffLine::Property {
+                change: *change,
+                name,
+            },
+            OwnedDiffLine::PropHunk(hi) => DiffLine::PropHunk(hi.as_hunk_info()),
+            OwnedDiffLine::CombinedHunk(hi) => DiffLine::CombinedHunk(hi.as_combined_hunk_info()),
+            OwnedDiffLine::CombinedDelta { markers, line } => {
+                DiffLine::CombinedDelta { markers, line }
+            }
+            OwnedDiffLine::Context(l, width) => DiffLine::Context(l, *width),
+            OwnedDiffLine::Inserted(l, width) => DiffLine::Inserted(l, *width),
+            OwnedDiffLine::Deleted(l, width) => DiffLine::Deleted(l, *width),
+            OwnedDiffLine::Modified(l, width) => DiffLine::Modified(l, *width),
+            OwnedDiffLine::NoNewlineAtEof { side, marker } => DiffLine::NoNewlineAtEof {
+                side: *side,
+                marker,
+            },
+            OwnedDiffLine::MalformedHunk {
+                expected_old,
+                expected_new,
+                actual_old,
+                actual_new,
+            } => DiffLine::MalformedHunk {
+                expected_old: *expected_old,
+                expected_new: *expected_new,
+                actual_old: *actual_old,
+                actual_new: *actual_new,
+            },
+            OwnedDiffLine::FileStart(fi) => DiffLine::FileStart(fi.as_file_info()),
+            OwnedDiffLine::FileEnd(fi) => DiffLine::FileEnd(fi.as_file_info()),
+            OwnedDiffLine::Submodule { old, new } => DiffLine::Submodule { old, new },
+            OwnedDiffLine::Preamble(l) => DiffLine::Preamble(l),
+            OwnedDiffLine::Junk(l) => DiffLine::Junk(l),
+        }
+    }
+}
+
+impl DiffLine<'_> {
+    /// Copy this line out of the parser's buffer.
+    pub fn to_owned(&self) -> OwnedDiffLine {
+        OwnedDiffLine::from(self)
+    }
+}
+
+// The owned types borrow themselves back and defer to the borrowed Display
+// impls, so both always render identically.
+
+impl fmt::Display for OwnedFileInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_file_info().fmt(f)
+    }
+}
+
+impl fmt::Display for OwnedHunkInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_hunk_info().fmt(f)
+    }
+}
+
+impl fmt::Display for OwnedCombinedHunkInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_combined_hunk_info().fmt(f)
+    }
+}
+
+impl fmt::Display for OwnedDiffLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_diff_line().fmt(f)
+    }
+}
+
+#[test]
+fn test_owned_round_trip() {
+    let diff: &[u8] = b"junk
+--- a/foo\t2019-01-01 00:00:00
++++ b/foo\t2019-01-02 00:00:00
+@@ -1,4 +1,4 @@\tfn main()
+ one
+-two
++2
+!three
+ four
+\\ No newline at end of file
+Binary files a/bin and b/bin differ
+";
+
+    let mut parser = crate::DiffParser::new(diff);
+    let mut borrowed = vec![];
+    let mut owned = vec![];
+
+    while let Some(line) = parser.next_line() {
+        let line = line.expect("read error");
+        borrowed.push(line.to_string());
+        owned.push(line.to_owned());
+    }
+
+    assert_eq!(owned.len(), 11);
+    assert_eq!(
+        owned.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        borrowed
+    );
+
+    let mut parser = crate::DiffParser::new(diff);
+    for line in &owned {
+        assert_eq!(parser.next_line().unwrap().unwrap(), line.as_diff_line());
+    }
+}
+
+#[test]
+fn test_owned_hash() {
+    use crate::DiffParser;
+    use std::collections::HashSet;
+
+    // The same change in two files, and again later in the second.
+    let diff = b"--- a/foo
++++ b/foo
+@@ -1 +1 @@
+-foo
++bar
+--- a/baz
++++ b/baz
+@@ -1 +1 @@
+-foo
++bar
+@@ -5 +5 @@
+-foo
++bar
+";
+    let hunks = crate::files(&diff[..])
+        .map(Result::unwrap)
+        .flat_map(|file| file.hunks)
+        .collect::<Vec<_>>();
+    assert_eq!(hunks.len(), 3);
+    let unique = hunks.iter().collect::<HashSet<_>>();
+    assert_eq!(unique.len(), 2);
+    assert!(unique.contains(&hunks[0]));
+    assert!(unique.contains(&hunks[2]));
+
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<HashSet<_>>();
+    assert_eq!(lines.len(), 8);
+    assert!(lines.contains(&OwnedDiffLine::Deleted(b"foo\n".to_vec(), 1)));
+}
@@ -0,0 +1,242 @@
+use crate::DiffLine;
+
+use std::borrow::Cow;
+
+/// Render the lines of a hunk in two columns like `diff -y`, with the old
+/// file on the left and the new on the right, in rows `width` characters
+/// wide at most.
+///
+/// Deleted lines are paired up with the inserted lines after them and marked
+/// `|`, and any left over are marked `<` or `>` with the other side blank.
+/// Context lines appear on both sides.  Lines too long for their column are
+/// cut short.
+///
+/// Context diff hunks are taken with their `OldRange` and `NewRange` lines,
+/// which say whose `Modified` lines are whose.  Those in the old section are
+/// paired with those in the new the same way.
+///
+/// ```
+/// use diffparser::{side_by_side, DiffLine};
+///
+/// let lines = [
+///     DiffLine::Context(b"one\n", 1),
+///     DiffLine::Deleted(b"two\n", 1),
+///     DiffLine::Inserted(b"2\n", 1),
+/// ];
+/// assert_eq!(side_by_side(&lines, 17), "one       one\ntwo     | 2\n");
+/// ```
+pub fn side_by_side(hunk_lines: &[DiffLine], width: usize) -> String {
+    let column = width.saturating_sub(3) / 2;
+    if hunk_lines
+        .iter()
+        .any(|line| matches!(line, DiffLine::OldRange(..) | DiffLine::NewRange(..)))
+    {
+        return context_side_by_side(hunk_lines, column);
+    }
+
+    let mut out = String::new();
+    let mut deleted = vec![];
+    let mut inserted = vec![];
+
+    for line in hunk_lines {
+        match line {
+            DiffLine::Deleted(..) => deleted.push(text(line)),
+            DiffLine::Inserted(..) => inserted.push(text(line)),
+            DiffLine::Context(..) => {
+                changes(&mut out, column, &mut deleted, &mut inserted);
+                let text = text(line);
+                row(&mut out, column, &text, ' ', &text);
+            }
+            _ => (),
+        }
+    }
+    changes(&mut out, column, &mut deleted, &mut inserted);
+
+    out
+}
+
+// The changes up to each context line, and those after the last.
+type Runs<'a> = Vec<(Vec<Cow<'a, str>>, Option<Cow<'a, str>>)>;
+
+fn context_side_by_side(hunk_lines: &[DiffLine], column: usize) -> String {
+    let mut out = String::new();
+    let (mut old, mut new): (Runs, Runs) = (vec![], vec![]);
+    let mut in_new = None;
+    let mut changed = vec![];
+
+    for line in hunk_lines {
+        match line {
+            DiffLine::OldRange(..) | DiffLine::NewRange(..) => {
+                if let Some(in_new) = in_new {
+                    end_section(if in_new { &mut new } else { &mut old }, &mut changed);
+                }
+                in_new = Some(matches!(line, DiffLine::NewRange(..)));
+            }
+            DiffLine::Deleted(..) | DiffLine::Inserted(..) | DiffLine::Modified(..) => {
+                changed.push(text(line))
+            }
+            DiffLine::Context(..) => {
+                let runs = if in_new == Some(true) {
+                    &mut new
+                } else {
+                    &mut old
+                };
+                runs.push((std::mem::take(&mut changed), Some(text(line))));
+            }
+            _ => (),
+        }
+    }
+    if let Some(in_new) = in_new {
+        end_section(if in_new { &mut new } else { &mut old }, &mut changed);
+    }
+
+    // A section with only context to show is left out, so its context is
+    // taken from the other one.
+    if old.is_empty() {
+        old = unchanged(&new);
+    } else if new.is_empty() {
+        new = unchanged(&old);
+    }
+
+    for i in 0..old.len().max(new.len()) {
+        let (mut deleted, old_context) = old.get(i).cloned().unwrap_or_default();
+        let (mut inserted, new_context) = new.get(i).cloned().unwrap_or_default();
+        changes(&mut out, column, &mut deleted, &mut inserted);
+        if let Some(text) = old_context.or(new_context) {
+            row(&mut out, column, &text, ' ', &text);
+        }
+    }
+
+    out
+}
+
+fn unchanged<'a>(runs: &Runs<'a>) -> Runs<'a> {
+    runs.iter()
+        .map(|(_, context)| (vec![], context.clone()))
+        .collect()
+}
+
+// Finish a section's runs with the changes after its last context line.
+fn end_section<'a>(runs: &mut Runs<'a>, changed: &mut Vec<Cow<'a, str>>) {
+    if !changed.is_empty() {
+        runs.push((std::mem::take(changed), None));
+    }
+}
+
+fn text<'a>(line: &DiffLine<'a>) -> Cow<'a, str> {
+    String::from_utf8_lossy(line.content().unwrap_or_default())
+}
+
+// Write the rows for a run of changes, pairing deletions with insertions.
+fn changes(
+    out: &mut String,
+    column: usize,
+    deleted: &mut Vec<Cow<str>>,
+    inserted: &mut Vec<Cow<str>>,
+) {
+    for i in 0..deleted.len().max(inserted.len()) {
+        match (deleted.get(i), inserted.get(i)) {
+            (Some(old), Some(new)) => row(out, column, old, '|', new),
+            (Some(old), None) => row(out, column, old, '<', ""),
+            (None, Some(new)) => row(out, column, "", '>', new),
+            (None, None) => unreachable!(),
+        }
+    }
+    deleted.clear();
+    inserted.clear();
+}
+
+fn row(out: &mut String, column: usize, left: &str, gutter: char, right: &str) {
+    let left = left.chars().take(column).collect::<String>();
+    let right = right.chars().take(column).collect::<String>();
+    let row = format!("{:<column$} {} {}", left, gutter, right, column = column);
+    out.push_str(row.trim_end());
+    out.push('\n');
+}
+
+#[test]
+fn test_side_by_side() {
+    let balanced = [
+        DiffLine::Context(b"one\n", 1),
+        DiffLine::Deleted(b"two\n", 1),
+        DiffLine::Deleted(b"three\n", 1),
+        DiffLine::Inserted(b"2\n", 1),
+        DiffLine::Inserted(b"3\n", 1),
+        DiffLine::Context(b"four\n", 1),
+    ];
+    assert_eq!(
+        side_by_side(&balanced, 21),
+        "one         one
+two       | 2
+three     | 3
+four        four
+"
+    );
+
+    let unbalanced = [
+        DiffLine::Deleted(b"one\n", 1),
+        DiffLine::Deleted(b"two\n", 1),
+        DiffLine::Inserted(b"1\n", 1),
+        DiffLine::Context(b"three\n", 1),
+        DiffLine::Inserted(b"a rather long line\n", 1),
+        DiffLine::Inserted(b"4\n", 1),
+        DiffLine::NoNewlineAtEof {
+            side: crate::Side::New,
+            marker: b"\\ No newline at end of file\n",
+        },
+    ];
+    assert_eq!(
+        side_by_side(&unbalanced, 21),
+        "one       | 1
+two       <
+three       three
+          > a rather
+          > 4
+"
+    );
+
+    assert_eq!(side_by_side(&[], 80), "");
+}
+
+#[test]
+fn test_context_side_by_side() {
+    let changed = [
+        DiffLine::ContextHunk,
+        DiffLine::OldRange(1, 4),
+        DiffLine::Context(b"one\n", 2),
+        DiffLine::Modified(b"two\n", 2),
+        DiffLine::Deleted(b"three\n", 2),
+        DiffLine::Context(b"four\n", 2),
+        DiffLine::NewRange(1, 4),
+        DiffLine::Context(b"one\n", 2),
+        DiffLine::Modified(b"2\n", 2),
+        DiffLine::Context(b"four\n", 2),
+        DiffLine::Inserted(b"5\n", 2),
+    ];
+    assert_eq!(
+        side_by_side(&changed, 21),
+        "one         one
+two       | 2
+three     <
+four        four
+          > 5
+"
+    );
+
+    // The old section's left out, having nothing but context.
+    let inserted = [
+        DiffLine::ContextHunk,
+        DiffLine::OldRange(1, 2),
+        DiffLine::NewRange(1, 3),
+        DiffLine::Context(b"one\n", 2),
+        DiffLine::Inserted(b"1.5\n", 2),
+        DiffLine::Context(b"two\n", 2),
+    ];
+    assert_eq!(
+        side_by_side(&inserted, 21),
+        "one         one
+          > 1.5
+two         two
+"
+    );
+}
@@ -0,0 +1,461 @@
+use crate::{DiffLine, DiffParser};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::ops::Range;
+
+/// Totals for a diff, like `diffstat` prints.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DiffStat {
+    pub files: usize,
+    pub hunks: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub modifications: usize,
+    /// Bytes in the `Inserted` lines, including their newlines.
+    pub inserted_bytes: u64,
+    /// Bytes in the `Deleted` lines, including their newlines.  Lines ed
+    /// scripts delete count as `deletions` but not here, as they only give
+    /// the lines' numbers.
+    pub deleted_bytes: u64,
+}
+
+impl DiffStat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count `line` in the totals.
+    pub fn accumulate(&mut self, line: &DiffLine) {
+        match line {
+            DiffLine::Inserted(l, _) => {
+                self.insertions += 1;
+                self.inserted_bytes += l.len() as u64;
+            }
+            DiffLine::Deleted(l, _) => {
+                self.deletions += 1;
+                self.deleted_bytes += l.len() as u64;
+            }
+            DiffLine::Modified(..) => self.modifications += 1,
+            DiffLine::Hunk(_) | DiffLine::ContextHunk | DiffLine::EdAppend(_) => self.hunks += 1,
+            DiffLine::EdChange(first, last) | DiffLine::EdDelete(first, last) => {
+                self.hunks += 1;
+                self.deletions += (last - first + 1) as usize;
+            }
+            DiffLine::NewFile(_) | DiffLine::Binaries(_, _) | DiffLine::GitBinaryPatch => {
+                self.files += 1
+            }
+            _ => (),
+        }
+    }
+}
+
+impl fmt::Display for DiffStat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, " {} files changed", self.files)?;
+
+        if self.hunks > 0 {
+            write!(f, ", {} hunks", self.hunks)?;
+        }
+
+        if self.insertions > 0 {
+            write!(f, ", {} insertions(+)", self.insertions)?;
+        }
+
+        if self.deletions > 0 {
+            write!(f, ", {} deletions(-)", self.deletions)?;
+        }
+
+        if self.modifications > 0 {
+            write!(f, ", {} modifications(!)", self.modifications)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_diffstat() {
+    let diff = b"--- a/foo
++++ b/foo
+@@ -1,2 +1,3 @@
+-foo
++bar
++bazqux
+ quux
+@@ -10 +11 @@
+-x
++y
+Binary files a/img and b/img differ
+";
+
+    let mut stat = DiffStat::new();
+    let mut parser = crate::DiffParser::new(&diff[..]);
+    while let Some(line) = parser.next_line() {
+        stat.accumulate(&line.unwrap());
+    }
+
+    assert_eq!(
+        stat,
+        DiffStat {
+            files: 2,
+            hunks: 2,
+            insertions: 3,
+            deletions: 2,
+            modifications: 0,
+            inserted_bytes: 13,
+            deleted_bytes: 6,
+        }
+    );
+    assert_eq!(
+        stat.to_string(),
+        " 2 files changed, 2 hunks, 3 insertions(+), 2 deletions(-)"
+    );
+    assert_eq!(DiffStat::new().to_string(), " 0 files changed");
+
+    let ed = b"7,8c\nseven\n.\n4,5d\n0a\nzero\n.\n";
+    let mut stat = DiffStat::new();
+    let mut parser = crate::DiffParser::new(&ed[..]).ed_format();
+    while let Some(line) = parser.next_line() {
+        stat.accumulate(&line.unwrap());
+    }
+
+    assert_eq!(
+        stat,
+        DiffStat {
+            files: 0,
+            hunks: 3,
+            insertions: 2,
+            deletions: 4,
+            modifications: 0,
+            inserted_bytes: 11,
+            deleted_bytes: 0,
+        }
+    );
+}
+
+/// Total up the diff in `diff`.
+pub fn quick_stats(diff: &[u8]) -> DiffStat {
+    let mut parser = DiffParser::new(diff).with_stats();
+    // Reading from a slice can't fail, and a non-strict parser doesn't.
+    while parser.next_line().is_some() {}
+    parser.stats
+}
+
+#[test]
+fn test_quick_stats() {
+    let diff = b"diff --git a/foo b/foo\r
+index 1234567..89abcde 100644\r
+--- a/foo\r
++++ b/foo\r
+@@ -1,3 +1,4 @@\r
+-foo\r
++bar\r
+ baz\r
+-qux\r
++quux\r
++corge\r
+diff --git a/img.png b/img.png
+index 1234567..89abcde 100644
+Binary files a/img.png and b/img.png differ
+";
+
+    // As from `diffstat`: 2 files changed, 3 insertions(+), 2 deletions(-)
+    let stat = quick_stats(diff);
+    assert_eq!((stat.files, stat.insertions, stat.deletions), (2, 3, 2));
+    assert_eq!(stat.hunks, 1);
+    assert_eq!(quick_stats(b""), DiffStat::new());
+}
+
+impl<R: BufRead> DiffParser<R> {
+    /// Keep a running `DiffStat` of the lines returned, to be read from
+    /// `stats()` at any point.
+    pub fn with_stats(mut self) -> Self {
+        self.counting = true;
+        self
+    }
+
+    /// The totals for the lines returned so far, which are all zero without
+    /// `with_stats()`.
+    pub fn stats(&self) -> &DiffStat {
+        &self.stats
+    }
+}
+
+#[test]
+fn test_with_stats() {
+    let diff = b"--- a/foo
++++ b/foo
+@@ -1,2 +1,2 @@
+-foo
++bar
+ baz
+--- a/bar
++++ b/bar
+@@ -1 +1,2 @@
+ x
++y
+";
+
+    let mut parser = DiffParser::new(&diff[..]).with_stats();
+    for _ in 0..5 {
+        parser.next_line().unwrap().unwrap();
+    }
+    assert_eq!((parser.stats().files, parser.stats().hunks), (1, 1));
+    assert_eq!(
+        (parser.stats().insertions, parser.stats().deletions),
+        (1, 1)
+    );
+
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+    }
+    assert_eq!(parser.stats().files, 2);
+    assert_eq!(parser.stats().insertions, 2);
+    assert_eq!(parser.stats().inserted_bytes, 6);
+
+    let mut parser = DiffParser::new(&diff[..])
+        .strip_prefix(1)
+        .filter_paths(vec![b"bar".to_vec()])
+        .with_stats();
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+    }
+    assert_eq!(parser.stats().files, 1);
+    assert_eq!(parser.stats().insertions, 1);
+    assert_eq!(parser.stats().deletions, 0);
+
+    let mut parser = DiffParser::new(&diff[..]);
+    while let Some(line) = parser.next_line() {
+        line.unwrap();
+    }
+    assert_eq!(parser.stats(), &DiffStat::new());
+}
+
+/// Changes to one file, from `per_file_stats()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStat {
+    pub path: Vec<u8>,
+    pub insertions: u32,
+    pub deletions: u32,
+}
+
+/// Displays like a line of `git diff --stat`, with a `+` or `-` for each
+/// line.  A width, as in `{:40}`, scales the graph to fit.
+impl fmt::Display for FileStat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.insertions + self.deletions;
+        let (mut plus, mut minus) = (self.insertions as usize, self.deletions as usize);
+
+        if let Some(width) = f.width() {
+            if total as usize > width {
+                plus = plus * width / total as usize;
+                minus = minus * width / total as usize;
+            }
+        }
+
+        write!(
+            f,
+            " {} | {} {}{}",
+            String::from_utf8_lossy(&self.path),
+            total,
+            "+".repeat(plus),
+            "-".repeat(minus)
+        )
+    }
+}
+
+/// Count the insertions and deletions for each file in the diff read from
+/// `r`.
+///
+/// Files are named by their `+++` header, or `---` for deleted files.  In git
+/// diffs, files with no hunks like renames are included too, under the new
+/// path from their `diff --git` header.
+pub fn per_file_stats<R: BufRead>(r: R) -> io::Result<Vec<FileStat>> {
+    let mut parser = DiffParser::new(r);
+    let mut stats: Vec<FileStat> = vec![];
+    let mut old_path = vec![];
+    // Whether the last entry is from a git header, and awaits its real path.
+    let mut from_header = false;
+
+    while let Some(line) = parser.next_line() {
+        let path = match line? {
+            DiffLine::GitHeader { new_path, .. } => {
+                stats.push(FileStat {
+                    path: new_path.to_vec(),
+                    insertions: 0,
+                    deletions: 0,
+                });
+                from_header = true;
+                continue;
+            }
+            DiffLine::OldFile(fi) => {
+                old_path = fi.filename.to_vec();
+                continue;
+            }
+            DiffLine::NewFile(fi) if fi.is_dev_null() => old_path.clone(),
+            DiffLine::NewFile(fi) => fi.filename.to_vec(),
+            DiffLine::Binaries(a, b) if b == b"/dev/null" => a.to_vec(),
+            DiffLine::Binaries(_, b) => b.to_vec(),
+            DiffLine::Inserted(..) => {
+                if let Some(stat) = stats.last_mut() {
+                    stat.insertions += 1;
+                }
+                continue;
+            }
+            DiffLine::Deleted(..) => {
+                if let Some(stat) = stats.last_mut() {
+                    stat.deletions += 1;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        if from_header {
+            stats.last_mut().unwrap().path = path;
+            from_header = false;
+        } else {
+            stats.push(FileStat {
+                path,
+                insertions: 0,
+                deletions: 0,
+            });
+        }
+    }
+
+    Ok(stats)
+}
+
+#[test]
+fn test_per_file_stats() {
+    let diff = b"diff --git a/foo b/foo
+--- a/foo
++++ b/foo
+@@ -1,2 +1,3 @@
+-foo
++bar
++baz
+ qux
+diff --git a/old b/new
+similarity index 100%
+rename from old
+rename to new
+diff --git a/gone b/gone
+deleted file mode 100644
+--- a/gone
++++ /dev/null
+@@ -1,2 +0,0 @@
+-a
+-b
+--- plain
++++ plain
+@@ -1 +1 @@
+-x
++y
+Binary files a.png and b.png differ
+";
+
+    let stat = |path: &[u8], insertions, deletions| FileStat {
+        path: path.to_vec(),
+        insertions,
+        deletions,
+    };
+
+    let stats = per_file_stats(&diff[..]).unwrap();
+    assert_eq!(
+        stats,
+        vec![
+            stat(b"b/foo", 2, 1),
+            stat(b"b/new", 0, 0),
+            stat(b"a/gone", 0, 2),
+            stat(b"plain", 1, 1),
+            stat(b"b.png", 0, 0),
+        ]
+    );
+
+    assert_eq!(stats[0].to_string(), " b/foo | 3 ++-");
+    assert_eq!(format!("{:4}", stat(b"big", 6, 2)), " big | 8 +++-");
+}
+
+/// Find the lines of each new file that were inserted or modified, as
+/// ranges of line numbers, from the diff read from `r`.
+///
+/// Files are keyed by their `+++` header.  Deleted files have no new lines,
+/// so are left out.
+pub fn changed_ranges<R: BufRead>(r: R) -> io::Result<HashMap<Vec<u8>, Vec<Range<u64>>>> {
+    let mut parser = DiffParser::new(r);
+    let mut changed = HashMap::new();
+    let mut ranges: Option<&mut Vec<Range<u64>>> = None;
+    // The number of the next new line, within a hunk's new lines.
+    let mut line_no = None;
+
+    while let Some(line) = parser.next_line() {
+        match line? {
+            DiffLine::NewFile(fi) if fi.is_dev_null() => ranges = None,
+            DiffLine::NewFile(fi) => {
+                ranges = Some(changed.entry(fi.filename.to_vec()).or_default());
+            }
+            DiffLine::Hunk(hi) => line_no = Some(hi.new_line_no),
+            DiffLine::OldRange(..) => line_no = None,
+            DiffLine::NewRange(first, _) => line_no = Some(first),
+            DiffLine::Context(..) => line_no = line_no.map(|n| n + 1),
+            DiffLine::Inserted(..) | DiffLine::Modified(..) => {
+                if let (Some(ranges), Some(n)) = (&mut ranges, line_no) {
+                    match ranges.last_mut() {
+                        Some(last) if last.end == n => last.end += 1,
+                        _ => ranges.push(n..n + 1),
+                    }
+                }
+                line_no = line_no.map(|n| n + 1);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(changed)
+}
+
+#[test]
+fn test_changed_ranges() {
+    let diff = b"--- a/foo
++++ b/foo
+@@ -1,3 +1,4 @@
+-one
++1
++2
+ three
+-four
++4
+@@ -10,3 +11,4 @@
+ ten
++eleven
+ twelve
++thirteen
+--- a/gone
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+
+    let changed = changed_ranges(&diff[..]).unwrap();
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[&b"b/foo"[..]], vec![1..3, 4..5, 12..13, 14..15]);
+
+    let diff = b"*** a/ctx
+--- b/ctx
+***************
+*** 5,6 ****
+! five
+  six
+--- 5,7 ----
+! 5
+  six
++ seven
+";
+
+    let changed = changed_ranges(&diff[..]).unwrap();
+    assert_eq!(changed[&b"b/ctx"[..]], vec![5..6, 7..8]);
+}
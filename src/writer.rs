@@ -0,0 +1,157 @@
+use crate::{DiffLine, FileInfo, HunkInfo, Side};
+
+use std::borrow::Cow;
+use std::io;
+use std::io::Write;
+
+/// Writes a unified diff a line at a time, the inverse of `DiffParser`.
+///
+/// Headers are written as they're displayed, with a newline.  Hunk
+/// lines are written as given, so should keep their newline: one without is
+/// taken to be the last line of a file missing its newline, and is followed
+/// by a `\ No newline at end of file` marker.
+pub struct DiffWriter<W> {
+    out: W,
+}
+
+impl<W: Write> DiffWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Write a `---` header.
+    pub fn old_file(&mut self, path: &[u8], metadata: Option<&[u8]>) -> io::Result<()> {
+        self.line(&DiffLine::OldFile(FileInfo {
+            filename: Cow::Borrowed(path),
+            metadata,
+        }))
+    }
+
+    /// Write a `+++` header.
+    pub fn new_file(&mut self, path: &[u8], metadata: Option<&[u8]>) -> io::Result<()> {
+        self.line(&DiffLine::NewFile(FileInfo {
+            filename: Cow::Borrowed(path),
+            metadata,
+        }))
+    }
+
+    /// Write a `@@` hunk header.
+    pub fn hunk(&mut self, hunk: HunkInfo<'_>) -> io::Result<()> {
+        self.line(&DiffLine::Hunk(hunk))
+    }
+
+    pub fn context(&mut self, line: &[u8]) -> io::Result<()> {
+        self.delta(b' ', line)
+    }
+
+    pub fn inserted(&mut self, line: &[u8]) -> io::Result<()> {
+        self.delta(b'+', line)
+    }
+
+    pub fn deleted(&mut self, line: &[u8]) -> io::Result<()> {
+        self.delta(b'-', line)
+    }
+
+    /// Write any line, such as one from `DiffParser`.  `MalformedHunk`s and
+    /// the like aren't written, having not come from a diff.
+    pub fn line(&mut self, line: &DiffLine<'_>) -> io::Result<()> {
+        match line {
+            DiffLine::Context(l, _) => self.context(l),
+            DiffLine::Inserted(l, _) => self.inserted(l),
+            DiffLine::Deleted(l, _) => self.deleted(l),
+            DiffLine::Modified(l, _) => self.delta(b'!', l),
+            line => line.writeln(&mut self.out),
+        }
+    }
+
+    // Hunk lines keep their bytes as they are, rather than going through a
+    // lossy `Display`.
+    fn delta(&mut self, marker: u8, line: &[u8]) -> io::Result<()> {
+        self.out.write_all(&[marker])?;
+        self.out.write_all(line)?;
+        if !line.ends_with(b"\n") {
+            self.out.write_all(b"\n")?;
+            let marker = DiffLine::NoNewlineAtEof {
+                side: Side::Both,
+                marker: b"\\ No newline at end of file",
+            };
+            marker.writeln(&mut self.out)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_diff_writer() {
+    use crate::{DiffParser, OwnedDiffLine, OwnedFileInfo, OwnedHunkInfo};
+
+    let mut writer = DiffWriter::new(vec![]);
+    writer.old_file(b"a/foo", Some(b"2019-01-01")).unwrap();
+    writer.new_file(b"b/foo", None).unwrap();
+    writer
+        .hunk(HunkInfo {
+            old_line_no: 1,
+            old_line_len: 2,
+            new_line_no: 1,
+            new_line_len: 2,
+            context: Some(b"fn main()"),
+        })
+        .unwrap();
+    writer.context(b"foo\n").unwrap();
+    writer.deleted(b"bar\n").unwrap();
+    writer.inserted(b"baz").unwrap();
+    let diff = writer.into_inner();
+
+    assert_eq!(
+        String::from_utf8_lossy(&diff),
+        "--- a/foo\t2019-01-01
++++ b/foo
+@@ -1,2 +1,2 @@\tfn main()
+ foo
+-bar
++baz
+\\ No newline at end of file
+"
+    );
+
+    let lines = DiffParser::new(&diff[..])
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            OwnedDiffLine::OldFile(OwnedFileInfo {
+                filename: b"a/foo".to_vec(),
+                metadata: Some(b"2019-01-01".to_vec()),
+            }),
+            OwnedDiffLine::NewFile(OwnedFileInfo {
+                filename: b"b/foo".to_vec(),
+                metadata: None,
+            }),
+            OwnedDiffLine::Hunk(OwnedHunkInfo {
+                old_line_no: 1,
+                old_line_len: 2,
+                new_line_no: 1,
+                new_line_len: 2,
+                context: Some(b"fn main()".to_vec()),
+            }),
+            OwnedDiffLine::Context(b"foo\n".to_vec(), 1),
+            OwnedDiffLine::Deleted(b"bar\n".to_vec(), 1),
+            OwnedDiffLine::Inserted(b"baz\n".to_vec(), 1),
+            OwnedDiffLine::NoNewlineAtEof {
+                side: Side::New,
+                marker: b"\\ No newline at end of file\n".to_vec(),
+            },
+        ]
+    );
+
+    let mut writer = DiffWriter::new(vec![]);
+    for line in &lines {
+        writer.line(&line.as_diff_line()).unwrap();
+    }
+    assert_eq!(writer.into_inner(), diff);
+}